@@ -0,0 +1,78 @@
+//! Lexing throughput over the corpus in `elixir/bench`, in bytes and tokens
+//! per second. `tokens.ex` holds only tokens the lexer handles, so it lexes
+//! to the end. The other files are real code, lexed only as far as the lexer
+//! goes today, and only that part counts towards their throughput; files it
+//! stops on at the first byte are skipped until it learns identifiers.
+//!
+//! There is no parser yet, so `cst` stands in for parsing: it builds the
+//! lossless tree of the same part of each file, as `parse` will. The tree
+//! of the rest would be error tokens, which would say nothing about speed.
+//!
+//!     cargo bench --bench throughput
+
+extern crate criterion;
+extern crate elixir_parser;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use elixir_parser::cst;
+use elixir_parser::lexer::Lexer;
+use std::fs;
+
+const CORPUS: &[&str] = &["tokens.ex", "phoenix.ex", "heredocs.ex", "sigils.ex"];
+
+fn corpus() -> Vec<(&'static str, String)> {
+    CORPUS
+        .iter()
+        .map(|name| {
+            let path = format!("{}/elixir/bench/{}", env!("CARGO_MANIFEST_DIR"), name);
+            let source =
+                fs::read_to_string(&path).unwrap_or_else(|err| panic!("{}: {}", path, err));
+            (*name, source)
+        })
+        .collect()
+}
+
+/// The number of tokens in `source` and how many of its bytes the lexer
+/// gets through.
+fn lexed(source: &str) -> (usize, usize) {
+    let mut lexer = Lexer::new(source);
+    let tokens = lexer.by_ref().filter(Result::is_ok).count();
+    (tokens, lexer.offset())
+}
+
+fn lex(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lex");
+    for (name, source) in corpus() {
+        let (tokens, lexed) = lexed(&source);
+        if lexed == 0 {
+            continue;
+        }
+
+        group.throughput(Throughput::BytesDecimal(lexed as u64));
+        group.bench_function(format!("{}/bytes", name), |b| {
+            b.iter(|| Lexer::new(black_box(&source)).count())
+        });
+        group.throughput(Throughput::Elements(tokens as u64));
+        group.bench_function(format!("{}/tokens", name), |b| {
+            b.iter(|| Lexer::new(black_box(&source)).count())
+        });
+    }
+    group.finish();
+}
+
+fn cst(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cst");
+    for (name, source) in corpus() {
+        let (_, lexed) = lexed(&source);
+        if lexed == 0 {
+            continue;
+        }
+        let source = &source[..lexed];
+        group.throughput(Throughput::BytesDecimal(lexed as u64));
+        group.bench_function(name, |b| b.iter(|| cst::build(black_box(source))));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, lex, cst);
+criterion_main!(benches);
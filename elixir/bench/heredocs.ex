@@ -0,0 +1,213 @@
+defmodule Shop.Mailer.Templates do
+  @moduledoc """
+  Plain-text and HTML bodies for the emails the shop sends.
+
+  Each template is a function of the assigns it needs, returning a
+  `{subject, text, html}` tuple. Templates are kept in code rather than in
+  files so they are compiled and checked along with everything else:
+
+      iex> {subject, _text, _html} = Templates.order_confirmation(order)
+      iex> subject
+      "Your order #1042 is confirmed"
+
+  Interpolated values are escaped by `html_escape/1` in the HTML bodies
+  only; plain-text bodies are sent as they are.
+  """
+
+  import Phoenix.HTML, only: [html_escape: 1, safe_to_string: 1]
+
+  @signature """
+  --
+  The Shop team
+  https://shop.example.com
+  """
+
+  @doc """
+  The email sent once an order has been paid.
+
+  ## Options
+
+    * `:include_invoice` - attach a link to the invoice, defaults to `true`
+    * `:locale` - the locale of the dates, defaults to `"en"`
+  """
+  def order_confirmation(order, opts \\ []) do
+    invoice? = Keyword.get(opts, :include_invoice, true)
+
+    subject = "Your order ##{order.number} is confirmed"
+
+    text = """
+    Hi #{order.customer.first_name},
+
+    Thanks for your order! We have received your payment of #{format_money(order.total)}
+    and will let you know as soon as your items ship.
+
+    Order number: #{order.number}
+    Placed on:    #{format_date(order.inserted_at, opts)}
+
+    #{Enum.map_join(order.line_items, "\n", &text_line_item/1)}
+
+    #{if invoice?, do: "Your invoice: #{invoice_url(order)}", else: ""}
+    #{@signature}
+    """
+
+    html = """
+    <p>Hi #{escape(order.customer.first_name)},</p>
+    <p>
+      Thanks for your order! We have received your payment of
+      <strong>#{format_money(order.total)}</strong> and will let you know as
+      soon as your items ship.
+    </p>
+    <table class="line-items">
+      <thead>
+        <tr><th>Item</th><th>Quantity</th><th>Price</th></tr>
+      </thead>
+      <tbody>
+        #{Enum.map_join(order.line_items, "\n", &html_line_item/1)}
+      </tbody>
+    </table>
+    """
+
+    {subject, text, html}
+  end
+
+  @doc """
+  The email sent when an order ships, with its tracking link.
+  """
+  def order_shipped(order, shipment) do
+    subject = "Order ##{order.number} is on its way"
+
+    text = """
+    Hi #{order.customer.first_name},
+
+    Good news: your order has shipped with #{shipment.carrier}.
+
+    Track it here: #{shipment.tracking_url}
+
+    Expected delivery: #{format_date(shipment.estimated_delivery, [])}
+    #{@signature}
+    """
+
+    html = """
+    <p>Hi #{escape(order.customer.first_name)},</p>
+    <p>Good news: your order has shipped with #{escape(shipment.carrier)}.</p>
+    <p><a href="#{shipment.tracking_url}">Track your package</a></p>
+    """
+
+    {subject, text, html}
+  end
+
+  @doc """
+  The email sent when a refund is issued.
+
+  The text body quotes the reason given by support verbatim:
+
+      Reason: "Item arrived damaged"
+  """
+  def refund_issued(order, refund) do
+    subject = "Refund for order ##{order.number}"
+
+    text = ~S"""
+    We have issued a refund. It can take up to 10 days to appear on your
+    statement. Amounts are shown as \#{amount}, which is not interpolated.
+    """
+
+    text =
+      text <>
+        """
+
+        Amount: #{format_money(refund.amount)}
+        Reason: "#{refund.reason}"
+        #{@signature}
+        """
+
+    html = """
+    <p>We have issued a refund of <strong>#{format_money(refund.amount)}</strong>.</p>
+    <blockquote>#{escape(refund.reason)}</blockquote>
+    """
+
+    {subject, text, html}
+  end
+
+  @doc ~S'''
+  The email sent to reset a password. The link expires after one hour.
+
+  Single-quoted heredocs are charlists:
+
+      iex> '''
+      ...> abc
+      ...> '''
+      'abc\n'
+  '''
+  def password_reset(user, url) do
+    subject = "Reset your password"
+
+    text = """
+    Hi #{user.first_name},
+
+    Someone asked to reset the password of your account. If it was you,
+    follow the link below within the next hour:
+
+        #{url}
+
+    If you did not ask for this, you can ignore this email.
+    #{@signature}
+    """
+
+    html = """
+    <p>Hi #{escape(user.first_name)},</p>
+    <p>Someone asked to reset the password of your account.</p>
+    <p><a href="#{url}">Choose a new password</a></p>
+    <p>If you did not ask for this, you can ignore this email.</p>
+    """
+
+    {subject, text, html}
+  end
+
+  @doc """
+  A weekly digest of the orders an account received.
+  """
+  def weekly_digest(account, stats) do
+    subject = "Your week: #{stats.orders} orders, #{format_money(stats.revenue)}"
+
+    text = """
+    Here is how #{account.name} did this week.
+
+      Orders:   #{stats.orders} (#{percent(stats.orders_change)} vs last week)
+      Revenue:  #{format_money(stats.revenue)} (#{percent(stats.revenue_change)})
+      Refunds:  #{stats.refunds}
+      Top item: #{stats.top_product || "none"}
+
+    #{@signature}
+    """
+
+    {subject, text, nil}
+  end
+
+  defp text_line_item(item) do
+    """
+      * #{item.quantity} x #{item.product.name} - #{format_money(item.total)}
+    """
+  end
+
+  defp html_line_item(item) do
+    """
+    <tr>
+      <td>#{escape(item.product.name)}</td>
+      <td>#{item.quantity}</td>
+      <td>#{format_money(item.total)}</td>
+    </tr>
+    """
+  end
+
+  defp escape(value), do: value |> html_escape() |> safe_to_string()
+
+  defp format_money(%Money{amount: amount, currency: currency}),
+    do: "#{currency} #{:erlang.float_to_binary(amount / 100, decimals: 2)}"
+
+  defp format_date(datetime, _opts), do: Calendar.strftime(datetime, "%B %-d, %Y")
+
+  defp percent(change) when change >= 0, do: "+#{round(change * 100)}%"
+  defp percent(change), do: "#{round(change * 100)}%"
+
+  defp invoice_url(order), do: "https://shop.example.com/invoices/#{order.id}"
+end
@@ -0,0 +1,349 @@
+defmodule ShopWeb.OrderController do
+  use ShopWeb, :controller
+
+  alias Shop.{Accounts, Catalog, Orders, Repo}
+  alias Shop.Orders.{Order, LineItem}
+  alias ShopWeb.Router.Helpers, as: Routes
+
+  import Ecto.Query, only: [from: 2, where: 3, preload: 2]
+  require Logger
+
+  plug :require_authenticated_user when action not in [:index, :show]
+  plug :load_order when action in [:show, :edit, :update, :delete, :cancel]
+  plug :authorize_order when action in [:edit, :update, :delete, :cancel]
+
+  action_fallback ShopWeb.FallbackController
+
+  @page_size 25
+  @max_page_size 100
+  @sortable_fields ~w(inserted_at total status customer_name)a
+  @statuses [:pending, :paid, :shipped, :delivered, :cancelled, :refunded]
+
+  @type params :: %{optional(String.t()) => term()}
+
+  def index(conn, params) do
+    page = parse_page(params)
+    page_size = parse_page_size(params)
+    sort = parse_sort(params)
+
+    query =
+      from o in Order,
+        where: o.account_id == ^conn.assigns.current_account.id,
+        order_by: ^sort,
+        limit: ^page_size,
+        offset: ^((page - 1) * page_size),
+        preload: [:customer, line_items: :product]
+
+    query =
+      case Map.get(params, "status") do
+        nil -> query
+        "" -> query
+        status when status in ~w(pending paid shipped delivered cancelled refunded) ->
+          where(query, [o], o.status == ^String.to_existing_atom(status))
+        _other -> query
+      end
+
+    orders = Repo.all(query)
+    total = Orders.count_orders(conn.assigns.current_account, params)
+
+    conn
+    |> put_resp_header("x-total-count", Integer.to_string(total))
+    |> render("index.html",
+      orders: orders,
+      page: page,
+      page_size: page_size,
+      total_pages: div(total + page_size - 1, page_size),
+      statuses: @statuses
+    )
+  end
+
+  def show(conn, _params) do
+    order = conn.assigns.order
+    events = Orders.list_events(order, limit: 50)
+    render(conn, "show.html", order: order, events: events)
+  end
+
+  def new(conn, params) do
+    changeset = Orders.change_order(%Order{line_items: [%LineItem{}]}, params)
+    products = Catalog.list_products(active: true)
+    render(conn, "new.html", changeset: changeset, products: products)
+  end
+
+  def create(conn, %{"order" => order_params}) do
+    account = conn.assigns.current_account
+
+    with {:ok, customer} <- Accounts.fetch_customer(account, order_params["customer_id"]),
+         {:ok, %Order{} = order} <- Orders.create_order(account, customer, order_params),
+         :ok <- Orders.reserve_stock(order) do
+      Logger.info("order #{order.id} created for customer #{customer.id}")
+
+      conn
+      |> put_flash(:info, "Order created successfully.")
+      |> redirect(to: Routes.order_path(conn, :show, order))
+    else
+      {:error, :customer_not_found} ->
+        conn
+        |> put_flash(:error, "Unknown customer.")
+        |> redirect(to: Routes.order_path(conn, :new))
+
+      {:error, %Ecto.Changeset{} = changeset} ->
+        products = Catalog.list_products(active: true)
+
+        conn
+        |> put_status(:unprocessable_entity)
+        |> render("new.html", changeset: changeset, products: products)
+
+      {:error, {:out_of_stock, product}} ->
+        conn
+        |> put_flash(:error, "#{product.name} is out of stock.")
+        |> redirect(to: Routes.order_path(conn, :new))
+    end
+  end
+
+  def edit(conn, _params) do
+    order = conn.assigns.order
+    changeset = Orders.change_order(order)
+    render(conn, "edit.html", order: order, changeset: changeset)
+  end
+
+  def update(conn, %{"order" => order_params}) do
+    order = conn.assigns.order
+
+    case Orders.update_order(order, order_params) do
+      {:ok, order} ->
+        conn
+        |> put_flash(:info, "Order updated successfully.")
+        |> redirect(to: Routes.order_path(conn, :show, order))
+
+      {:error, %Ecto.Changeset{} = changeset} ->
+        render(conn, "edit.html", order: order, changeset: changeset)
+    end
+  end
+
+  def delete(conn, _params) do
+    {:ok, _order} = Orders.delete_order(conn.assigns.order)
+
+    conn
+    |> put_flash(:info, "Order deleted successfully.")
+    |> redirect(to: Routes.order_path(conn, :index))
+  end
+
+  def cancel(conn, %{"reason" => reason}) when byte_size(reason) > 0 do
+    order = conn.assigns.order
+
+    case Orders.cancel_order(order, reason: reason, notify: true) do
+      {:ok, %Order{status: :cancelled} = order} ->
+        Logger.warning("order #{order.id} cancelled: #{inspect(reason)}")
+        json(conn, %{id: order.id, status: order.status})
+
+      {:error, :already_shipped} ->
+        conn
+        |> put_status(:conflict)
+        |> json(%{error: "order has already shipped"})
+
+      {:error, reason} ->
+        {:error, reason}
+    end
+  end
+
+  def cancel(conn, _params) do
+    conn
+    |> put_status(:bad_request)
+    |> json(%{error: "a reason is required"})
+  end
+
+  def export(conn, params) do
+    orders = Orders.stream_orders(conn.assigns.current_account, params)
+
+    conn =
+      conn
+      |> put_resp_content_type("text/csv")
+      |> put_resp_header("content-disposition", ~s(attachment; filename="orders.csv"))
+      |> send_chunked(200)
+
+    Repo.transaction(fn ->
+      orders
+      |> Stream.map(&to_csv_row/1)
+      |> Stream.chunk_every(500)
+      |> Enum.reduce_while(conn, fn rows, conn ->
+        case chunk(conn, Enum.join(rows, "\n") <> "\n") do
+          {:ok, conn} -> {:cont, conn}
+          {:error, :closed} -> {:halt, conn}
+        end
+      end)
+    end)
+    |> case do
+      {:ok, conn} -> conn
+      {:error, _} -> conn
+    end
+  end
+
+  defp to_csv_row(%Order{} = order) do
+    [
+      order.id,
+      order.customer.name,
+      Atom.to_string(order.status),
+      Decimal.to_string(order.total, :normal),
+      DateTime.to_iso8601(order.inserted_at)
+    ]
+    |> Enum.map(&escape_csv/1)
+    |> Enum.join(",")
+  end
+
+  defp escape_csv(value) when is_binary(value) do
+    if String.contains?(value, [",", "\"", "\n"]) do
+      "\"" <> String.replace(value, "\"", "\"\"") <> "\""
+    else
+      value
+    end
+  end
+
+  defp escape_csv(value), do: to_string(value)
+
+  defp load_order(%{params: %{"id" => id}} = conn, _opts) do
+    case Orders.get_order(conn.assigns.current_account, id) do
+      nil ->
+        conn
+        |> put_status(:not_found)
+        |> put_view(ShopWeb.ErrorView)
+        |> render(:"404")
+        |> halt()
+
+      %Order{} = order ->
+        assign(conn, :order, Repo.preload(order, [:customer, line_items: :product]))
+    end
+  end
+
+  defp authorize_order(conn, _opts) do
+    user = conn.assigns.current_user
+    order = conn.assigns.order
+
+    if user.role in [:admin, :manager] or order.created_by_id == user.id do
+      conn
+    else
+      conn
+      |> put_flash(:error, "You are not allowed to change this order.")
+      |> redirect(to: Routes.order_path(conn, :show, order))
+      |> halt()
+    end
+  end
+
+  defp parse_page(%{"page" => page}) do
+    case Integer.parse(page) do
+      {page, ""} when page > 0 -> page
+      _ -> 1
+    end
+  end
+
+  defp parse_page(_params), do: 1
+
+  defp parse_page_size(%{"page_size" => size}) do
+    case Integer.parse(size) do
+      {size, ""} when size in 1..@max_page_size -> size
+      _ -> @page_size
+    end
+  end
+
+  defp parse_page_size(_params), do: @page_size
+
+  defp parse_sort(%{"sort" => "-" <> field}) when field in @sortable_fields,
+    do: [desc: String.to_existing_atom(field)]
+
+  defp parse_sort(%{"sort" => field}) when field in @sortable_fields,
+    do: [asc: String.to_existing_atom(field)]
+
+  defp parse_sort(_params), do: [desc: :inserted_at]
+end
+
+defmodule ShopWeb.OrderLive.Index do
+  use ShopWeb, :live_view
+
+  alias Shop.Orders
+  alias Phoenix.PubSub
+
+  @impl true
+  def mount(_params, %{"account_id" => account_id}, socket) do
+    if connected?(socket) do
+      PubSub.subscribe(Shop.PubSub, "orders:#{account_id}")
+      :timer.send_interval(30_000, self(), :refresh)
+    end
+
+    {:ok,
+     socket
+     |> assign(:account_id, account_id)
+     |> assign(:filter, %{status: nil, query: ""})
+     |> assign(:selected, MapSet.new())
+     |> assign_orders(), temporary_assigns: [orders: []]}
+  end
+
+  @impl true
+  def handle_params(params, _url, socket) do
+    {:noreply, apply_action(socket, socket.assigns.live_action, params)}
+  end
+
+  defp apply_action(socket, :index, _params) do
+    socket
+    |> assign(:page_title, "Orders")
+    |> assign(:order, nil)
+  end
+
+  defp apply_action(socket, :show, %{"id" => id}) do
+    socket
+    |> assign(:page_title, "Order #{id}")
+    |> assign(:order, Orders.get_order!(socket.assigns.account_id, id))
+  end
+
+  @impl true
+  def handle_event("filter", %{"filter" => filter}, socket) do
+    filter = %{
+      status: blank_to_nil(filter["status"]),
+      query: String.trim(filter["query"] || "")
+    }
+
+    {:noreply, socket |> assign(:filter, filter) |> assign_orders()}
+  end
+
+  def handle_event("toggle", %{"id" => id}, %{assigns: %{selected: selected}} = socket) do
+    selected =
+      if MapSet.member?(selected, id),
+        do: MapSet.delete(selected, id),
+        else: MapSet.put(selected, id)
+
+    {:noreply, assign(socket, :selected, selected)}
+  end
+
+  def handle_event("ship_selected", _params, socket) do
+    results =
+      socket.assigns.selected
+      |> Enum.map(&Orders.get_order!(socket.assigns.account_id, &1))
+      |> Enum.map(&Orders.ship_order/1)
+
+    {shipped, failed} = Enum.split_with(results, &match?({:ok, _}, &1))
+
+    socket =
+      socket
+      |> put_flash(:info, "Shipped #{length(shipped)} orders.")
+      |> then(fn socket ->
+        if failed == [], do: socket, else: put_flash(socket, :error, "#{length(failed)} failed.")
+      end)
+      |> assign(:selected, MapSet.new())
+      |> assign_orders()
+
+    {:noreply, socket}
+  end
+
+  @impl true
+  def handle_info({:order_updated, %{id: id} = order}, socket) do
+    {:noreply, update(socket, :orders, fn orders -> [order | Enum.reject(orders, &(&1.id == id))] end)}
+  end
+
+  def handle_info(:refresh, socket), do: {:noreply, assign_orders(socket)}
+
+  defp assign_orders(socket) do
+    %{account_id: account_id, filter: filter} = socket.assigns
+    assign(socket, :orders, Orders.list_orders(account_id, Map.to_list(filter)))
+  end
+
+  defp blank_to_nil(""), do: nil
+  defp blank_to_nil(value), do: value
+end
@@ -0,0 +1,138 @@
+defmodule Shop.Parsing do
+  @moduledoc ~S"""
+  Parsers for the formats partners send us: SKUs, postcodes, tracking
+  numbers and the `key=value` lines of legacy feeds.
+  """
+
+  @sku ~r/\A[A-Z]{3}-\d{4}(?:-[A-Z0-9]{2})?\z/
+  @postcode ~r/^(?<area>[A-Z]{1,2})(?<district>\d[A-Z\d]?) ?(?<sector>\d)(?<unit>[A-Z]{2})$/i
+  @tracking %{
+    ups: ~r/\b1Z[0-9A-Z]{16}\b/,
+    fedex: ~r/\b(\d{12}|\d{15})\b/,
+    usps: ~r/\b(94|93|92|95)\d{20}\b/
+  }
+  @carriers ~w(ups fedex usps dhl)a
+  @feed_keys ~w[sku name price currency stock]
+  @separators ~c(,;|)
+  @date_formats ~W(%Y-%m-%d %d/%m/%Y %m-%d-%Y)
+
+  def valid_sku?(sku), do: sku =~ @sku
+
+  def parse_postcode(input) do
+    input = input |> String.upcase() |> String.trim()
+
+    case Regex.named_captures(@postcode, input) do
+      %{"area" => area, "district" => district, "sector" => sector, "unit" => unit} ->
+        {:ok, "#{area}#{district} #{sector}#{unit}"}
+
+      nil ->
+        {:error, "invalid postcode: #{inspect(input)}"}
+    end
+  end
+
+  def detect_carrier(text) do
+    Enum.find_value(@carriers, :unknown, fn carrier ->
+      with %Regex{} = regex <- @tracking[carrier],
+           [number | _] <- Regex.run(regex, text) do
+        {carrier, number}
+      else
+        _ -> nil
+      end
+    end)
+  end
+
+  def parse_feed_line(line) do
+    line
+    |> String.split(~r/\s*[#{@separators}]\s*/, trim: true)
+    |> Enum.map(&String.split(&1, ~r/\s*=\s*/, parts: 2))
+    |> Enum.reduce_while(%{}, fn
+      [key, value], acc when key in @feed_keys -> {:cont, Map.put(acc, key, value)}
+      [key, _value], _acc -> {:halt, {:error, ~s(unknown key "#{key}")}}
+      [other], _acc -> {:halt, {:error, ~s(expected key=value, got "#{other}")}}
+    end)
+    |> case do
+      {:error, _} = error -> error
+      fields -> {:ok, fields}
+    end
+  end
+
+  def normalize_name(name) do
+    name
+    |> String.replace(~r/[\x{2018}\x{2019}]/u, "'")
+    |> String.replace(~r/[\x{201C}\x{201D}]/u, ~s("))
+    |> String.replace(~r/\s+/, " ")
+    |> String.trim()
+  end
+
+  def price_pattern(currency) do
+    symbol = Map.fetch!(%{"USD" => "\\$", "EUR" => "€", "GBP" => "£"}, currency)
+    ~r/#{symbol}\s?(?<amount>\d{1,3}(?:,\d{3})*(?:\.\d{2})?)/
+  end
+
+  def slug(text) do
+    text
+    |> String.downcase()
+    |> String.replace(~r/[^a-z0-9\s-]/, "")
+    |> String.replace(~r/[\s-]+/, "-")
+    |> String.trim("-")
+  end
+
+  def parse_date(text) do
+    Enum.find_value(@date_formats, {:error, :invalid_date}, fn format ->
+      case Timex.parse(text, format, :strftime) do
+        {:ok, date} -> {:ok, date}
+        {:error, _} -> nil
+      end
+    end)
+  end
+
+  def render_badge(assigns) do
+    ~H"""
+    <span class={"badge badge-#{@status}"} title={~s(Status: #{@status})}>
+      <%= String.capitalize(to_string(@status)) %>
+    </span>
+    """
+  end
+
+  def render_row(assigns) do
+    ~H"""
+    <tr id={"order-#{@order.id}"} class={if @selected, do: "selected"}>
+      <td><%= @order.number %></td>
+      <td><%= @order.customer.name %></td>
+      <td><.badge status={@order.status} /></td>
+      <td class="text-right"><%= format_money(@order.total) %></td>
+    </tr>
+    """
+  end
+
+  def sql_for(table, columns) do
+    cols = Enum.map_join(columns, ", ", &~s("#{&1}"))
+    ~s{SELECT #{cols} FROM "#{table}" WHERE deleted_at IS NULL}
+  end
+
+  def messages do
+    [
+      ~s(Order "#{1042}" shipped),
+      ~S(No #{interpolation} here),
+      ~s[Brackets #{"[" <> "]"} inside],
+      ~s<Angle #{1 + 1} brackets>,
+      ~s|Pipes #{Enum.join(~w(a b c), "|")}|,
+      ~s'Single #{"quotes"}',
+      ~s"Double #{:quotes}",
+      ~s/Slash #{1 / 2}/,
+      ~c(charlist #{:interpolated}),
+      ~w(#{"interpolated"} words)s,
+      ~r{nested #{~r/regex/.source} regex}x,
+      ~D[2024-01-31],
+      ~T[23:59:59.999],
+      ~N[2024-01-31 23:59:59],
+      ~U[2024-01-31 23:59:59Z]
+    ]
+  end
+
+  def log_line(entry) do
+    "[#{entry.level |> to_string() |> String.upcase()}] #{entry.time} " <>
+      "#{entry.module}.#{entry.function}/#{entry.arity}: " <>
+      "#{Enum.map_join(entry.metadata, " ", fn {k, v} -> "#{k}=#{inspect(v)}" end)}"
+  end
+end
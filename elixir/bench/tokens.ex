@@ -0,0 +1,428 @@
+# Every token the lexer handles today, with whitespace and comments
+# between them, so throughput is measured over the whole file.
+
+  # section 0
+...: :... 0xDEADBEEF ?0 :% :{} 0o17 ?* :&&&
+  0x1F ?Z :>>> :~>> 0o777 ?: :<|> :~~~
+  ?a :!= :=~ 0b1 ?λ :&& :|| 0x0
+:~> :<~ 0b1010 ?é :<> :.. 0xffffffffffffffffffff ?% * 0b1
+  :-> 0xDEADBEEF ?0 :! :^ 0o17 ?* :-  # trailing comment
+  ...: 0x1F ?Z :< :> 0o777 ?: :. :...
+?a :%{} :% 0b1 ?λ :!== :&&& 0x0
+  :<<< :>>> 0b1010 ?é :<~> :<|> 0xffffffffffffffffffff ?%
+  :!= 0xDEADBEEF ?0 :>= :&& 0o17 ?* :~>
+0x1F ?Z :\\ :<> 0o777 ?: :-- :->
+  ...: ?a :@ :! 0b1 ?λ :+ :- 0x0 * 0b1010
+
+  # section 1
+  :/ :< 0b1010 ?é :| :. 0xffffffffffffffffffff ?%
+:%{} 0xDEADBEEF ?0 :=== :!== 0o17 ?* :<<<
+  0x1F ?Z :<<~ :<~> 0o777 ?: :== :!=  # trailing comment
+  ?a :<= :>= 0b1 ?λ :|> :~> 0x0
+...: :<- :\\ 0b1010 ?é :++ :-- 0xffffffffffffffffffff ?%
+  :@ 0xDEADBEEF ?0 :& :+ 0o17 ?* :/
+  0x1F ?Z := :| 0o777 ?: :<<>> :%{} * 0xDEADBEEF
+?a :{} :=== 0b1 ?λ :||| :<<< 0x0
+  :~>> :<<~ 0b1010 ?é :~~~ :== 0xffffffffffffffffffff ?%
+  ...: :<= 0xDEADBEEF ?0 :|| :|> 0o17 ?* :<-
+0x1F ?Z :.. :++ 0o777 ?: ::: :@
+
+  # section 2
+  ?a :^ :& 0b1 ?λ :* :/ 0x0  # trailing comment
+  :> := 0b1010 ?é :... :<<>> 0xffffffffffffffffffff ?%
+:{} 0xDEADBEEF ?0 :&&& :||| 0o17 ?* :~>> * 0x1F
+  ...: 0x1F ?Z :<|> :~~~ 0o777 ?: :=~ :<=
+  ?a :&& :|| 0b1 ?λ :<~ :<- 0x0
+:<> :.. 0b1010 ?é :-> ::: 0xffffffffffffffffffff ?%
+  :^ 0xDEADBEEF ?0 :- :* 0o17 ?* :>
+  0x1F ?Z :. :... 0o777 ?: :% :{}
+...: ?a :!== :&&& 0b1 ?λ :>>> :~>> 0x0
+  :<~> :<|> 0b1010 ?é :!= :=~ 0xffffffffffffffffffff ?% * 0x0  # trailing comment
+  :&& 0xDEADBEEF ?0 :~> :<~ 0o17 ?* :<>
+
+  # section 3
+0x1F ?Z :-- :-> 0o777 ?: :! :^
+  ?a :+ :- 0b1 ?λ :< :> 0x0
+  ...: :| :. 0b1010 ?é :%{} :% 0xffffffffffffffffffff ?%
+:!== 0xDEADBEEF ?0 :<<< :>>> 0o17 ?* :<~>
+  0x1F ?Z :== :!= 0o777 ?: :>= :&&
+  ?a :|> :~> 0b1 ?λ :\\ :<> 0x0 * 0xffffffffffffffffffff
+:++ :-- 0b1010 ?é :@ :! 0xffffffffffffffffffff ?%
+  ...: :+ 0xDEADBEEF ?0 :/ :< 0o17 ?* :|  # trailing comment
+  0x1F ?Z :<<>> :%{} 0o777 ?: :=== :!==
+?a :||| :<<< 0b1 ?λ :<<~ :<~> 0x0
+  :~~~ :== 0b1010 ?é :<= :>= 0xffffffffffffffffffff ?%
+
+  # section 4
+  :|> 0xDEADBEEF ?0 :<- :\\ 0o17 ?* :++
+...: 0x1F ?Z ::: :@ 0o777 ?: :& :+ * 0o17
+  ?a :* :/ 0b1 ?λ := :| 0x0
+  :... :<<>> 0b1010 ?é :{} :=== 0xffffffffffffffffffff ?%
+:||| 0xDEADBEEF ?0 :~>> :<<~ 0o17 ?* :~~~
+  0x1F ?Z :=~ :<= 0o777 ?: :|| :|>  # trailing comment
+  ...: ?a :<~ :<- 0b1 ?λ :.. :++ 0x0
+:-> ::: 0b1010 ?é :^ :& 0xffffffffffffffffffff ?%
+  :* 0xDEADBEEF ?0 :> := 0o17 ?* :... * 0o777
+  0x1F ?Z :% :{} 0o777 ?: :&&& :|||
+?a :>>> :~>> 0b1 ?λ :<|> :~~~ 0x0
+
+  # section 5
+  ...: :!= :=~ 0b1010 ?é :&& :|| 0xffffffffffffffffffff ?%
+  :<~ 0xDEADBEEF ?0 :<> :.. 0o17 ?* :->
+0x1F ?Z :! :^ 0o777 ?: :- :*
+  ?a :< :> 0b1 ?λ :. :... 0x0  # trailing comment
+  :%{} :% 0b1010 ?é :!== :&&& 0xffffffffffffffffffff ?% * 0b1
+...: :>>> 0xDEADBEEF ?0 :<~> :<|> 0o17 ?* :!=
+  0x1F ?Z :>= :&& 0o777 ?: :~> :<~
+  ?a :\\ :<> 0b1 ?λ :-- :-> 0x0
+:@ :! 0b1010 ?é :+ :- 0xffffffffffffffffffff ?%
+  :< 0xDEADBEEF ?0 :| :. 0o17 ?* :%{}
+  ...: 0x1F ?Z :=== :!== 0o777 ?: :<<< :>>>
+
+  # section 6
+?a :<<~ :<~> 0b1 ?λ :== :!= 0x0 * 0b1010
+  :<= :>= 0b1010 ?é :|> :~> 0xffffffffffffffffffff ?%  # trailing comment
+  :\\ 0xDEADBEEF ?0 :++ :-- 0o17 ?* :@
+0x1F ?Z :& :+ 0o777 ?: :/ :<
+  ...: ?a := :| 0b1 ?λ :<<>> :%{} 0x0
+  :{} :=== 0b1010 ?é :||| :<<< 0xffffffffffffffffffff ?%
+:<<~ 0xDEADBEEF ?0 :~~~ :== 0o17 ?* :<=
+  0x1F ?Z :|| :|> 0o777 ?: :<- :\\ * 0xDEADBEEF
+  ?a :.. :++ 0b1 ?λ ::: :@ 0x0
+...: :^ :& 0b1010 ?é :* :/ 0xffffffffffffffffffff ?%
+  := 0xDEADBEEF ?0 :... :<<>> 0o17 ?* :{}  # trailing comment
+
+  # section 7
+  0x1F ?Z :&&& :||| 0o777 ?: :~>> :<<~
+?a :<|> :~~~ 0b1 ?λ :=~ :<= 0x0
+  :&& :|| 0b1010 ?é :<~ :<- 0xffffffffffffffffffff ?%
+  ...: :.. 0xDEADBEEF ?0 :-> ::: 0o17 ?* :^ * 0x1F
+0x1F ?Z :- :* 0o777 ?: :> :=
+  ?a :. :... 0b1 ?λ :% :{} 0x0
+  :!== :&&& 0b1010 ?é :>>> :~>> 0xffffffffffffffffffff ?%
+:<|> 0xDEADBEEF ?0 :!= :=~ 0o17 ?* :&&
+  ...: 0x1F ?Z :~> :<~ 0o777 ?: :<> :..  # trailing comment
+  ?a :-- :-> 0b1 ?λ :! :^ 0x0
+:+ :- 0b1010 ?é :< :> 0xffffffffffffffffffff ?% * 0x0
+
+  # section 8
+  :. 0xDEADBEEF ?0 :%{} :% 0o17 ?* :!==
+  0x1F ?Z :<<< :>>> 0o777 ?: :<~> :<|>
+...: ?a :== :!= 0b1 ?λ :>= :&& 0x0
+  :|> :~> 0b1010 ?é :\\ :<> 0xffffffffffffffffffff ?%
+  :-- 0xDEADBEEF ?0 :@ :! 0o17 ?* :+
+0x1F ?Z :/ :< 0o777 ?: :| :.
+  ?a :<<>> :%{} 0b1 ?λ :=== :!== 0x0 * 0xffffffffffffffffffff  # trailing comment
+  ...: :||| :<<< 0b1010 ?é :<<~ :<~> 0xffffffffffffffffffff ?%
+:== 0xDEADBEEF ?0 :<= :>= 0o17 ?* :|>
+  0x1F ?Z :<- :\\ 0o777 ?: :++ :--
+  ?a ::: :@ 0b1 ?λ :& :+ 0x0
+
+  # section 9
+:* :/ 0b1010 ?é := :| 0xffffffffffffffffffff ?%
+  ...: :<<>> 0xDEADBEEF ?0 :{} :=== 0o17 ?* :|||
+  0x1F ?Z :~>> :<<~ 0o777 ?: :~~~ :== * 0o17
+?a :=~ :<= 0b1 ?λ :|| :|> 0x0
+  :<~ :<- 0b1010 ?é :.. :++ 0xffffffffffffffffffff ?%  # trailing comment
+  ::: 0xDEADBEEF ?0 :^ :& 0o17 ?* :*
+...: 0x1F ?Z :> := 0o777 ?: :... :<<>>
+  ?a :% :{} 0b1 ?λ :&&& :||| 0x0
+  :>>> :~>> 0b1010 ?é :<|> :~~~ 0xffffffffffffffffffff ?%
+:=~ 0xDEADBEEF ?0 :&& :|| 0o17 ?* :<~ * 0o777
+  0x1F ?Z :<> :.. 0o777 ?: :-> :::
+
+  # section 10
+  ...: ?a :! :^ 0b1 ?λ :- :* 0x0
+:< :> 0b1010 ?é :. :... 0xffffffffffffffffffff ?%
+  :% 0xDEADBEEF ?0 :!== :&&& 0o17 ?* :>>>  # trailing comment
+  0x1F ?Z :<~> :<|> 0o777 ?: :!= :=~
+?a :>= :&& 0b1 ?λ :~> :<~ 0x0
+  ...: :\\ :<> 0b1010 ?é :-- :-> 0xffffffffffffffffffff ?% * 0b1
+  :! 0xDEADBEEF ?0 :+ :- 0o17 ?* :<
+0x1F ?Z :| :. 0o777 ?: :%{} :%
+  ?a :=== :!== 0b1 ?λ :<<< :>>> 0x0
+  :<<~ :<~> 0b1010 ?é :== :!= 0xffffffffffffffffffff ?%
+...: :>= 0xDEADBEEF ?0 :|> :~> 0o17 ?* :\\
+
+  # section 11
+  0x1F ?Z :++ :-- 0o777 ?: :@ :!  # trailing comment
+  ?a :& :+ 0b1 ?λ :/ :< 0x0 * 0b1010
+:= :| 0b1010 ?é :<<>> :%{} 0xffffffffffffffffffff ?%
+  :=== 0xDEADBEEF ?0 :||| :<<< 0o17 ?* :<<~
+  ...: 0x1F ?Z :~~~ :== 0o777 ?: :<= :>=
+?a :|| :|> 0b1 ?λ :<- :\\ 0x0
+  :.. :++ 0b1010 ?é ::: :@ 0xffffffffffffffffffff ?%
+  :& 0xDEADBEEF ?0 :* :/ 0o17 ?* :=
+0x1F ?Z :... :<<>> 0o777 ?: :{} :=== * 0xDEADBEEF
+  ...: ?a :&&& :||| 0b1 ?λ :~>> :<<~ 0x0  # trailing comment
+  :<|> :~~~ 0b1010 ?é :=~ :<= 0xffffffffffffffffffff ?%
+
+  # section 12
+:|| 0xDEADBEEF ?0 :<~ :<- 0o17 ?* :..
+  0x1F ?Z :-> ::: 0o777 ?: :^ :&
+  ?a :- :* 0b1 ?λ :> := 0x0
+...: :. :... 0b1010 ?é :% :{} 0xffffffffffffffffffff ?%
+  :&&& 0xDEADBEEF ?0 :>>> :~>> 0o17 ?* :<|> * 0x1F
+  0x1F ?Z :!= :=~ 0o777 ?: :&& :||
+?a :~> :<~ 0b1 ?λ :<> :.. 0x0
+  :-- :-> 0b1010 ?é :! :^ 0xffffffffffffffffffff ?%  # trailing comment
+  ...: :- 0xDEADBEEF ?0 :< :> 0o17 ?* :.
+0x1F ?Z :%{} :% 0o777 ?: :!== :&&&
+  ?a :<<< :>>> 0b1 ?λ :<~> :<|> 0x0
+
+  # section 13
+  :== :!= 0b1010 ?é :>= :&& 0xffffffffffffffffffff ?% * 0x0
+:~> 0xDEADBEEF ?0 :\\ :<> 0o17 ?* :--
+  ...: 0x1F ?Z :@ :! 0o777 ?: :+ :-
+  ?a :/ :< 0b1 ?λ :| :. 0x0
+:<<>> :%{} 0b1010 ?é :=== :!== 0xffffffffffffffffffff ?%
+  :<<< 0xDEADBEEF ?0 :<<~ :<~> 0o17 ?* :==  # trailing comment
+  0x1F ?Z :<= :>= 0o777 ?: :|> :~>
+...: ?a :<- :\\ 0b1 ?λ :++ :-- 0x0 * 0xffffffffffffffffffff
+  ::: :@ 0b1010 ?é :& :+ 0xffffffffffffffffffff ?%
+  :/ 0xDEADBEEF ?0 := :| 0o17 ?* :<<>>
+0x1F ?Z :{} :=== 0o777 ?: :||| :<<<
+
+  # section 14
+  ?a :~>> :<<~ 0b1 ?λ :~~~ :== 0x0
+  ...: :=~ :<= 0b1010 ?é :|| :|> 0xffffffffffffffffffff ?%
+:<- 0xDEADBEEF ?0 :.. :++ 0o17 ?* :::
+  0x1F ?Z :^ :& 0o777 ?: :* :/ * 0o17  # trailing comment
+  ?a :> := 0b1 ?λ :... :<<>> 0x0
+:% :{} 0b1010 ?é :&&& :||| 0xffffffffffffffffffff ?%
+  ...: :~>> 0xDEADBEEF ?0 :<|> :~~~ 0o17 ?* :=~
+  0x1F ?Z :&& :|| 0o777 ?: :<~ :<-
+?a :<> :.. 0b1 ?λ :-> ::: 0x0
+  :! :^ 0b1010 ?é :- :* 0xffffffffffffffffffff ?%
+  :> 0xDEADBEEF ?0 :. :... 0o17 ?* :% * 0o777
+
+  # section 15
+...: 0x1F ?Z :!== :&&& 0o777 ?: :>>> :~>>
+  ?a :<~> :<|> 0b1 ?λ :!= :=~ 0x0  # trailing comment
+  :>= :&& 0b1010 ?é :~> :<~ 0xffffffffffffffffffff ?%
+:<> 0xDEADBEEF ?0 :-- :-> 0o17 ?* :!
+  0x1F ?Z :+ :- 0o777 ?: :< :>
+  ...: ?a :| :. 0b1 ?λ :%{} :% 0x0
+:=== :!== 0b1010 ?é :<<< :>>> 0xffffffffffffffffffff ?% * 0b1
+  :<~> 0xDEADBEEF ?0 :== :!= 0o17 ?* :>=
+  0x1F ?Z :|> :~> 0o777 ?: :\\ :<>
+?a :++ :-- 0b1 ?λ :@ :! 0x0
+  ...: :& :+ 0b1010 ?é :/ :< 0xffffffffffffffffffff ?%  # trailing comment
+
+  # section 16
+  :| 0xDEADBEEF ?0 :<<>> :%{} 0o17 ?* :===
+0x1F ?Z :||| :<<< 0o777 ?: :<<~ :<~>
+  ?a :~~~ :== 0b1 ?λ :<= :>= 0x0 * 0b1010
+  :|| :|> 0b1010 ?é :<- :\\ 0xffffffffffffffffffff ?%
+...: :++ 0xDEADBEEF ?0 ::: :@ 0o17 ?* :&
+  0x1F ?Z :* :/ 0o777 ?: := :|
+  ?a :... :<<>> 0b1 ?λ :{} :=== 0x0
+:&&& :||| 0b1010 ?é :~>> :<<~ 0xffffffffffffffffffff ?%
+  :~~~ 0xDEADBEEF ?0 :=~ :<= 0o17 ?* :||  # trailing comment
+  ...: 0x1F ?Z :<~ :<- 0o777 ?: :.. :++ * 0xDEADBEEF
+?a :-> ::: 0b1 ?λ :^ :& 0x0
+
+  # section 17
+  :- :* 0b1010 ?é :> := 0xffffffffffffffffffff ?%
+  :... 0xDEADBEEF ?0 :% :{} 0o17 ?* :&&&
+0x1F ?Z :>>> :~>> 0o777 ?: :<|> :~~~
+  ...: ?a :!= :=~ 0b1 ?λ :&& :|| 0x0
+  :~> :<~ 0b1010 ?é :<> :.. 0xffffffffffffffffffff ?%
+:-> 0xDEADBEEF ?0 :! :^ 0o17 ?* :- * 0x1F
+  0x1F ?Z :< :> 0o777 ?: :. :...  # trailing comment
+  ?a :%{} :% 0b1 ?λ :!== :&&& 0x0
+...: :<<< :>>> 0b1010 ?é :<~> :<|> 0xffffffffffffffffffff ?%
+  :!= 0xDEADBEEF ?0 :>= :&& 0o17 ?* :~>
+  0x1F ?Z :\\ :<> 0o777 ?: :-- :->
+
+  # section 18
+?a :@ :! 0b1 ?λ :+ :- 0x0
+  :/ :< 0b1010 ?é :| :. 0xffffffffffffffffffff ?% * 0x0
+  ...: :%{} 0xDEADBEEF ?0 :=== :!== 0o17 ?* :<<<
+0x1F ?Z :<<~ :<~> 0o777 ?: :== :!=
+  ?a :<= :>= 0b1 ?λ :|> :~> 0x0  # trailing comment
+  :<- :\\ 0b1010 ?é :++ :-- 0xffffffffffffffffffff ?%
+:@ 0xDEADBEEF ?0 :& :+ 0o17 ?* :/
+  ...: 0x1F ?Z := :| 0o777 ?: :<<>> :%{}
+  ?a :{} :=== 0b1 ?λ :||| :<<< 0x0 * 0xffffffffffffffffffff
+:~>> :<<~ 0b1010 ?é :~~~ :== 0xffffffffffffffffffff ?%
+  :<= 0xDEADBEEF ?0 :|| :|> 0o17 ?* :<-
+
+  # section 19
+  0x1F ?Z :.. :++ 0o777 ?: ::: :@
+...: ?a :^ :& 0b1 ?λ :* :/ 0x0
+  :> := 0b1010 ?é :... :<<>> 0xffffffffffffffffffff ?%  # trailing comment
+  :{} 0xDEADBEEF ?0 :&&& :||| 0o17 ?* :~>>
+0x1F ?Z :<|> :~~~ 0o777 ?: :=~ :<= * 0o17
+  ?a :&& :|| 0b1 ?λ :<~ :<- 0x0
+  ...: :<> :.. 0b1010 ?é :-> ::: 0xffffffffffffffffffff ?%
+:^ 0xDEADBEEF ?0 :- :* 0o17 ?* :>
+  0x1F ?Z :. :... 0o777 ?: :% :{}
+  ?a :!== :&&& 0b1 ?λ :>>> :~>> 0x0
+:<~> :<|> 0b1010 ?é :!= :=~ 0xffffffffffffffffffff ?%
+
+  # section 20
+  ...: :&& 0xDEADBEEF ?0 :~> :<~ 0o17 ?* :<> * 0o777  # trailing comment
+  0x1F ?Z :-- :-> 0o777 ?: :! :^
+?a :+ :- 0b1 ?λ :< :> 0x0
+  :| :. 0b1010 ?é :%{} :% 0xffffffffffffffffffff ?%
+  :!== 0xDEADBEEF ?0 :<<< :>>> 0o17 ?* :<~>
+...: 0x1F ?Z :== :!= 0o777 ?: :>= :&&
+  ?a :|> :~> 0b1 ?λ :\\ :<> 0x0
+  :++ :-- 0b1010 ?é :@ :! 0xffffffffffffffffffff ?% * 0b1
+:+ 0xDEADBEEF ?0 :/ :< 0o17 ?* :|
+  0x1F ?Z :<<>> :%{} 0o777 ?: :=== :!==  # trailing comment
+  ...: ?a :||| :<<< 0b1 ?λ :<<~ :<~> 0x0
+
+  # section 21
+:~~~ :== 0b1010 ?é :<= :>= 0xffffffffffffffffffff ?%
+  :|> 0xDEADBEEF ?0 :<- :\\ 0o17 ?* :++
+  0x1F ?Z ::: :@ 0o777 ?: :& :+
+?a :* :/ 0b1 ?λ := :| 0x0 * 0b1010
+  ...: :... :<<>> 0b1010 ?é :{} :=== 0xffffffffffffffffffff ?%
+  :||| 0xDEADBEEF ?0 :~>> :<<~ 0o17 ?* :~~~
+0x1F ?Z :=~ :<= 0o777 ?: :|| :|>
+  ?a :<~ :<- 0b1 ?λ :.. :++ 0x0  # trailing comment
+  :-> ::: 0b1010 ?é :^ :& 0xffffffffffffffffffff ?%
+...: :* 0xDEADBEEF ?0 :> := 0o17 ?* :...
+  0x1F ?Z :% :{} 0o777 ?: :&&& :||| * 0xDEADBEEF
+
+  # section 22
+  ?a :>>> :~>> 0b1 ?λ :<|> :~~~ 0x0
+:!= :=~ 0b1010 ?é :&& :|| 0xffffffffffffffffffff ?%
+  :<~ 0xDEADBEEF ?0 :<> :.. 0o17 ?* :->
+  ...: 0x1F ?Z :! :^ 0o777 ?: :- :*
+?a :< :> 0b1 ?λ :. :... 0x0
+  :%{} :% 0b1010 ?é :!== :&&& 0xffffffffffffffffffff ?%  # trailing comment
+  :>>> 0xDEADBEEF ?0 :<~> :<|> 0o17 ?* :!= * 0x1F
+0x1F ?Z :>= :&& 0o777 ?: :~> :<~
+  ...: ?a :\\ :<> 0b1 ?λ :-- :-> 0x0
+  :@ :! 0b1010 ?é :+ :- 0xffffffffffffffffffff ?%
+:< 0xDEADBEEF ?0 :| :. 0o17 ?* :%{}
+
+  # section 23
+  0x1F ?Z :=== :!== 0o777 ?: :<<< :>>>
+  ?a :<<~ :<~> 0b1 ?λ :== :!= 0x0
+...: :<= :>= 0b1010 ?é :|> :~> 0xffffffffffffffffffff ?% * 0x0
+  :\\ 0xDEADBEEF ?0 :++ :-- 0o17 ?* :@  # trailing comment
+  0x1F ?Z :& :+ 0o777 ?: :/ :<
+?a := :| 0b1 ?λ :<<>> :%{} 0x0
+  :{} :=== 0b1010 ?é :||| :<<< 0xffffffffffffffffffff ?%
+  ...: :<<~ 0xDEADBEEF ?0 :~~~ :== 0o17 ?* :<=
+0x1F ?Z :|| :|> 0o777 ?: :<- :\\
+  ?a :.. :++ 0b1 ?λ ::: :@ 0x0 * 0xffffffffffffffffffff
+  :^ :& 0b1010 ?é :* :/ 0xffffffffffffffffffff ?%
+
+  # section 24
+:= 0xDEADBEEF ?0 :... :<<>> 0o17 ?* :{}
+  ...: 0x1F ?Z :&&& :||| 0o777 ?: :~>> :<<~  # trailing comment
+  ?a :<|> :~~~ 0b1 ?λ :=~ :<= 0x0
+:&& :|| 0b1010 ?é :<~ :<- 0xffffffffffffffffffff ?%
+  :.. 0xDEADBEEF ?0 :-> ::: 0o17 ?* :^
+  0x1F ?Z :- :* 0o777 ?: :> := * 0o17
+...: ?a :. :... 0b1 ?λ :% :{} 0x0
+  :!== :&&& 0b1010 ?é :>>> :~>> 0xffffffffffffffffffff ?%
+  :<|> 0xDEADBEEF ?0 :!= :=~ 0o17 ?* :&&
+0x1F ?Z :~> :<~ 0o777 ?: :<> :..
+  ?a :-- :-> 0b1 ?λ :! :^ 0x0  # trailing comment
+
+  # section 25
+  ...: :+ :- 0b1010 ?é :< :> 0xffffffffffffffffffff ?%
+:. 0xDEADBEEF ?0 :%{} :% 0o17 ?* :!== * 0o777
+  0x1F ?Z :<<< :>>> 0o777 ?: :<~> :<|>
+  ?a :== :!= 0b1 ?λ :>= :&& 0x0
+:|> :~> 0b1010 ?é :\\ :<> 0xffffffffffffffffffff ?%
+  ...: :-- 0xDEADBEEF ?0 :@ :! 0o17 ?* :+
+  0x1F ?Z :/ :< 0o777 ?: :| :.
+?a :<<>> :%{} 0b1 ?λ :=== :!== 0x0
+  :||| :<<< 0b1010 ?é :<<~ :<~> 0xffffffffffffffffffff ?% * 0b1  # trailing comment
+  :== 0xDEADBEEF ?0 :<= :>= 0o17 ?* :|>
+...: 0x1F ?Z :<- :\\ 0o777 ?: :++ :--
+
+  # section 26
+  ?a ::: :@ 0b1 ?λ :& :+ 0x0
+  :* :/ 0b1010 ?é := :| 0xffffffffffffffffffff ?%
+:<<>> 0xDEADBEEF ?0 :{} :=== 0o17 ?* :|||
+  0x1F ?Z :~>> :<<~ 0o777 ?: :~~~ :==
+  ...: ?a :=~ :<= 0b1 ?λ :|| :|> 0x0 * 0b1010
+:<~ :<- 0b1010 ?é :.. :++ 0xffffffffffffffffffff ?%
+  ::: 0xDEADBEEF ?0 :^ :& 0o17 ?* :*  # trailing comment
+  0x1F ?Z :> := 0o777 ?: :... :<<>>
+?a :% :{} 0b1 ?λ :&&& :||| 0x0
+  ...: :>>> :~>> 0b1010 ?é :<|> :~~~ 0xffffffffffffffffffff ?%
+  :=~ 0xDEADBEEF ?0 :&& :|| 0o17 ?* :<~
+
+  # section 27
+0x1F ?Z :<> :.. 0o777 ?: :-> ::: * 0xDEADBEEF
+  ?a :! :^ 0b1 ?λ :- :* 0x0
+  :< :> 0b1010 ?é :. :... 0xffffffffffffffffffff ?%
+...: :% 0xDEADBEEF ?0 :!== :&&& 0o17 ?* :>>>
+  0x1F ?Z :<~> :<|> 0o777 ?: :!= :=~  # trailing comment
+  ?a :>= :&& 0b1 ?λ :~> :<~ 0x0
+:\\ :<> 0b1010 ?é :-- :-> 0xffffffffffffffffffff ?%
+  :! 0xDEADBEEF ?0 :+ :- 0o17 ?* :< * 0x1F
+  ...: 0x1F ?Z :| :. 0o777 ?: :%{} :%
+?a :=== :!== 0b1 ?λ :<<< :>>> 0x0
+  :<<~ :<~> 0b1010 ?é :== :!= 0xffffffffffffffffffff ?%
+
+  # section 28
+  :>= 0xDEADBEEF ?0 :|> :~> 0o17 ?* :\\
+0x1F ?Z :++ :-- 0o777 ?: :@ :!
+  ...: ?a :& :+ 0b1 ?λ :/ :< 0x0  # trailing comment
+  := :| 0b1010 ?é :<<>> :%{} 0xffffffffffffffffffff ?% * 0x0
+:=== 0xDEADBEEF ?0 :||| :<<< 0o17 ?* :<<~
+  0x1F ?Z :~~~ :== 0o777 ?: :<= :>=
+  ?a :|| :|> 0b1 ?λ :<- :\\ 0x0
+...: :.. :++ 0b1010 ?é ::: :@ 0xffffffffffffffffffff ?%
+  :& 0xDEADBEEF ?0 :* :/ 0o17 ?* :=
+  0x1F ?Z :... :<<>> 0o777 ?: :{} :===
+?a :&&& :||| 0b1 ?λ :~>> :<<~ 0x0 * 0xffffffffffffffffffff
+
+  # section 29
+  :<|> :~~~ 0b1010 ?é :=~ :<= 0xffffffffffffffffffff ?%  # trailing comment
+  ...: :|| 0xDEADBEEF ?0 :<~ :<- 0o17 ?* :..
+0x1F ?Z :-> ::: 0o777 ?: :^ :&
+  ?a :- :* 0b1 ?λ :> := 0x0
+  :. :... 0b1010 ?é :% :{} 0xffffffffffffffffffff ?%
+:&&& 0xDEADBEEF ?0 :>>> :~>> 0o17 ?* :<|>
+  ...: 0x1F ?Z :!= :=~ 0o777 ?: :&& :|| * 0o17
+  ?a :~> :<~ 0b1 ?λ :<> :.. 0x0
+:-- :-> 0b1010 ?é :! :^ 0xffffffffffffffffffff ?%
+  :- 0xDEADBEEF ?0 :< :> 0o17 ?* :.  # trailing comment
+  0x1F ?Z :%{} :% 0o777 ?: :!== :&&&
+
+  # section 30
+...: ?a :<<< :>>> 0b1 ?λ :<~> :<|> 0x0
+  :== :!= 0b1010 ?é :>= :&& 0xffffffffffffffffffff ?%
+  :~> 0xDEADBEEF ?0 :\\ :<> 0o17 ?* :-- * 0o777
+0x1F ?Z :@ :! 0o777 ?: :+ :-
+  ?a :/ :< 0b1 ?λ :| :. 0x0
+  ...: :<<>> :%{} 0b1010 ?é :=== :!== 0xffffffffffffffffffff ?%
+:<<< 0xDEADBEEF ?0 :<<~ :<~> 0o17 ?* :==
+  0x1F ?Z :<= :>= 0o777 ?: :|> :~>  # trailing comment
+  ?a :<- :\\ 0b1 ?λ :++ :-- 0x0
+::: :@ 0b1010 ?é :& :+ 0xffffffffffffffffffff ?% * 0b1
+  ...: :/ 0xDEADBEEF ?0 := :| 0o17 ?* :<<>>
+
+  # section 31
+  0x1F ?Z :{} :=== 0o777 ?: :||| :<<<
+?a :~>> :<<~ 0b1 ?λ :~~~ :== 0x0
+  :=~ :<= 0b1010 ?é :|| :|> 0xffffffffffffffffffff ?%
+  :<- 0xDEADBEEF ?0 :.. :++ 0o17 ?* :::
+...: 0x1F ?Z :^ :& 0o777 ?: :* :/
+  ?a :> := 0b1 ?λ :... :<<>> 0x0 * 0b1010  # trailing comment
+  :% :{} 0b1010 ?é :&&& :||| 0xffffffffffffffffffff ?%
+:~>> 0xDEADBEEF ?0 :<|> :~~~ 0o17 ?* :=~
+  0x1F ?Z :&& :|| 0o777 ?: :<~ :<-
+  ...: ?a :<> :.. 0b1 ?λ :-> ::: 0x0
+:! :^ 0b1010 ?é :- :* 0xffffffffffffffffffff ?%
+
+  # section 32
+  :> 0xDEADBEEF ?0 :. :... 0o17 ?* :%
+  0x1F ?Z :!== :&&& 0o777 ?: :>>> :~>> * 0xDEADBEEF
+?a :<~> :<|> 0b1 ?λ :!= :=~ 0x0
+  ...: :>= :&& 0b1010 ?é :~> :<~ 0xffffffffffffffffffff ?%  # trailing comment
+  :<> 0xDEADBEEF ?0 :-- :-> 0o17 ?* :!
+0x1F ?Z :+ :- 0o777 ?: :< :>
+  ?a :| :. 0b1 ?λ :%{} :% 0x0
+  :=== :!== 0b1010 ?é :<<< :>>> 0xffffffffffffffffffff ?%
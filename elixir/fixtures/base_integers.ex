@@ -0,0 +1 @@
+0xf1A:+0b110:-0o73
\ No newline at end of file
@@ -0,0 +1,36 @@
+# Regenerates the expected `.tokens` file next to every `.ex` fixture using
+# the real Elixir tokenizer:
+#
+#     elixir elixir/fixtures/regen.exs
+#
+# Each line is `LINE:COLUMN KIND VALUE`, one token per line. Comparing against
+# these files in `cargo test` does not need Elixir installed.
+
+format = fn
+  {:int, {line, column, value}, _original} ->
+    "#{line}:#{column} int #{value}"
+
+  {:char, {line, column, _original}, value} ->
+    "#{line}:#{column} char #{value}"
+
+  {kind, {line, column, _}, value} when kind in [:atom, :kw_identifier] ->
+    "#{line}:#{column} #{kind} #{value}"
+
+  token ->
+    raise "no fixture format for #{inspect(token)}"
+end
+
+tokenize = fn source ->
+  case :elixir_tokenizer.tokenize(String.to_charlist(source), 1, 1, []) do
+    {:ok, tokens} -> tokens
+    {:ok, _line, _column, _warnings, tokens} -> tokens
+    # Newer tokenizers return the accumulator reversed plus any terminators.
+    {:ok, _line, _column, _warnings, tokens, terminators} -> Enum.reverse(tokens, terminators)
+  end
+end
+
+for path <- Path.wildcard(Path.join(__DIR__, "*.ex")) do
+  lines = path |> File.read!() |> tokenize.() |> Enum.map(&[format.(&1), ?\n])
+  File.write!(Path.rootname(path) <> ".tokens", lines)
+  IO.puts("wrote #{Path.relative_to_cwd(Path.rootname(path))}.tokens")
+end
@@ -0,0 +1,312 @@
+//! The AST in an arena. Nodes of any number of files live in one `Arena`
+//! and refer to each other by `NodeId`, a four-byte index that stays valid
+//! for the life of the arena, so analyses can key maps by node and keep
+//! references across files. Children and metadata are ranges into vectors
+//! shared by the whole arena rather than a `Vec` per node, and every node
+//! knows its parent.
+//!
+//! `Arena::alloc` copies an owned `Ast` in and `Arena::to_ast` copies one
+//! back out.
+
+use ast::Ast;
+use num_bigint::BigInt;
+use std::ops::Index;
+use symbol::Symbol;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct NodeId(u32);
+
+impl NodeId {
+    /// The node's position in the arena, counting from zero in the order
+    /// nodes were allocated.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A node's children, read with `Arena::children`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Children {
+    start: u32,
+    len: u32,
+}
+
+/// A node's metadata, read with `Arena::meta`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Meta {
+    start: u32,
+    len: u32,
+}
+
+/// A node, in the shape of `Ast`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Node {
+    Atom(Symbol),
+    Int(BigInt),
+    Float(f64),
+    String(String),
+    List(Children),
+    Pair(NodeId, NodeId),
+    Var(Symbol, Meta, Option<Symbol>),
+    Call(NodeId, Meta, Children),
+}
+
+#[derive(Default)]
+pub struct Arena {
+    nodes: Vec<Node>,
+    parents: Vec<Option<NodeId>>,
+    children: Vec<NodeId>,
+    meta: Vec<(Symbol, NodeId)>,
+}
+
+impl Arena {
+    pub fn new() -> Self {
+        Arena::default()
+    }
+
+    /// Copies `ast` into the arena and returns the id of its root. Children
+    /// are allocated before their parents.
+    pub fn alloc(&mut self, ast: &Ast) -> NodeId {
+        let node = match ast {
+            Ast::Atom(name) => Node::Atom(*name),
+            Ast::Int(i) => Node::Int(i.clone()),
+            Ast::Float(f) => Node::Float(*f),
+            Ast::String(s) => Node::String(s.clone()),
+            Ast::List(elems) => Node::List(self.alloc_children(elems)),
+            Ast::Pair(a, b) => Node::Pair(self.alloc(a), self.alloc(b)),
+            Ast::Var(name, meta, context) => Node::Var(*name, self.alloc_meta(meta), *context),
+            Ast::Call(callee, meta, args) => {
+                let callee = self.alloc(callee);
+                let meta = self.alloc_meta(meta);
+                Node::Call(callee, meta, self.alloc_children(args))
+            }
+        };
+        self.push(node)
+    }
+
+    fn alloc_children(&mut self, asts: &[Ast]) -> Children {
+        // Nested children are pushed while allocating, so this node's range
+        // is taken first and filled in as each child is allocated.
+        let start = self.children.len();
+        self.children.resize(start + asts.len(), NodeId(0));
+        for (i, ast) in asts.iter().enumerate() {
+            self.children[start + i] = self.alloc(ast);
+        }
+        Children {
+            start: start as u32,
+            len: asts.len() as u32,
+        }
+    }
+
+    fn alloc_meta(&mut self, meta: &[(Symbol, Ast)]) -> Meta {
+        let start = self.meta.len();
+        self.meta
+            .extend(meta.iter().map(|&(key, _)| (key, NodeId(0))));
+        for (i, (_, value)) in meta.iter().enumerate() {
+            self.meta[start + i].1 = self.alloc(value);
+        }
+        Meta {
+            start: start as u32,
+            len: meta.len() as u32,
+        }
+    }
+
+    fn push(&mut self, node: Node) -> NodeId {
+        let id = NodeId(self.nodes.len() as u32);
+        for child in child_ids(&self.children, &self.meta, &node) {
+            self.parents[child.index()] = Some(id);
+        }
+        self.nodes.push(node);
+        self.parents.push(None);
+        id
+    }
+
+    /// Copies the tree rooted at `id` back out as an owned `Ast`.
+    pub fn to_ast(&self, id: NodeId) -> Ast {
+        match &self[id] {
+            Node::Atom(name) => Ast::Atom(*name),
+            Node::Int(i) => Ast::Int(i.clone()),
+            Node::Float(f) => Ast::Float(*f),
+            Node::String(s) => Ast::String(s.clone()),
+            Node::List(elems) => Ast::List(self.asts(*elems)),
+            Node::Pair(a, b) => Ast::Pair(Box::new(self.to_ast(*a)), Box::new(self.to_ast(*b))),
+            Node::Var(name, meta, context) => Ast::Var(*name, self.meta_asts(*meta), *context),
+            Node::Call(callee, meta, args) => Ast::Call(
+                Box::new(self.to_ast(*callee)),
+                self.meta_asts(*meta),
+                self.asts(*args),
+            ),
+        }
+    }
+
+    fn asts(&self, children: Children) -> Vec<Ast> {
+        self.children(children)
+            .iter()
+            .map(|&id| self.to_ast(id))
+            .collect()
+    }
+
+    fn meta_asts(&self, meta: Meta) -> Vec<(Symbol, Ast)> {
+        self.meta(meta)
+            .iter()
+            .map(|&(key, value)| (key, self.to_ast(value)))
+            .collect()
+    }
+
+    pub fn children(&self, children: Children) -> &[NodeId] {
+        let start = children.start as usize;
+        &self.children[start..start + children.len as usize]
+    }
+
+    pub fn meta(&self, meta: Meta) -> &[(Symbol, NodeId)] {
+        let start = meta.start as usize;
+        &self.meta[start..start + meta.len as usize]
+    }
+
+    /// The value of the metadata `key` of a variable or call, such as its
+    /// `line`.
+    pub fn meta_value(&self, id: NodeId, key: &str) -> Option<NodeId> {
+        let meta = match self[id] {
+            Node::Var(_, meta, _) | Node::Call(_, meta, _) => meta,
+            _ => return None,
+        };
+        self.meta(meta)
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|&(_, value)| value)
+    }
+
+    /// The node `id` is a child of, or `None` for a root. Metadata values
+    /// are children of the variable or call they describe.
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.parents[id.index()]
+    }
+
+    /// The children of a node in source order: a call's callee, metadata
+    /// values and arguments, a pair's elements or a list's.
+    pub fn child_ids<'a>(&'a self, node: &Node) -> impl Iterator<Item = NodeId> + 'a {
+        child_ids(&self.children, &self.meta, node)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Every node with its id, in allocation order.
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &Node)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (NodeId(i as u32), node))
+    }
+}
+
+/// The children of `node`, read from the arena's `children` and `meta`
+/// without borrowing the rest of it, so `push` can set parents as it goes.
+fn child_ids<'a>(
+    children: &'a [NodeId],
+    meta: &'a [(Symbol, NodeId)],
+    node: &Node,
+) -> impl Iterator<Item = NodeId> + 'a {
+    let range = |start: u32, len: u32| start as usize..(start + len) as usize;
+    let none: (Option<NodeId>, Option<NodeId>) = (None, None);
+    let (own, meta, args) = match *node {
+        Node::Atom(_) | Node::Int(_) | Node::Float(_) | Node::String(_) => (none, &[][..], &[][..]),
+        Node::List(elems) => (none, &[][..], &children[range(elems.start, elems.len)]),
+        Node::Pair(a, b) => ((Some(a), Some(b)), &[][..], &[][..]),
+        Node::Var(_, m, _) => (none, &meta[range(m.start, m.len)], &[][..]),
+        Node::Call(callee, m, elems) => (
+            (Some(callee), None),
+            &meta[range(m.start, m.len)],
+            &children[range(elems.start, elems.len)],
+        ),
+    };
+    own.0
+        .into_iter()
+        .chain(own.1)
+        .chain(meta.iter().map(|&(_, value)| value))
+        .chain(args.iter().cloned())
+}
+
+impl Index<NodeId> for Arena {
+    type Output = Node;
+
+    fn index(&self, id: NodeId) -> &Node {
+        &self.nodes[id.index()]
+    }
+}
+
+#[cfg(test)]
+fn sample() -> Ast {
+    // Enum.map(list, fn x -> x end)
+    let x = Ast::Var("x".into(), vec![("line".into(), Ast::Int(1.into()))], None);
+    let alias = Ast::Call(
+        Box::new(Ast::Atom("__aliases__".into())),
+        vec![],
+        vec![Ast::Atom("Enum".into())],
+    );
+    let dot = Ast::Call(
+        Box::new(Ast::Atom(".".into())),
+        vec![],
+        vec![alias, Ast::Atom("map".into())],
+    );
+    let clause = Ast::Call(
+        Box::new(Ast::Atom("->".into())),
+        vec![],
+        vec![Ast::List(vec![x.clone()]), x],
+    );
+    let func = Ast::Call(Box::new(Ast::Atom("fn".into())), vec![], vec![clause]);
+    Ast::Call(
+        Box::new(dot),
+        vec![("line".into(), Ast::Int(1.into()))],
+        vec![Ast::Var("list".into(), vec![], None), func],
+    )
+}
+
+#[test]
+fn round_trip() {
+    let ast = sample();
+    let mut arena = Arena::new();
+    let root = arena.alloc(&ast);
+    assert_eq!(arena.to_ast(root), ast);
+    assert_eq!(root.index(), arena.len() - 1);
+
+    // A second file shares the arena, and the first keeps its ids.
+    let other = arena.alloc(&Ast::List(vec![Ast::Float(1.5), Ast::String("s".into())]));
+    assert_ne!(other, root);
+    assert_eq!(arena.to_ast(root), ast);
+    assert_eq!(arena.iter().count(), arena.len());
+}
+
+#[test]
+fn navigation() {
+    let mut arena = Arena::new();
+    let root = arena.alloc(&sample());
+    let args = match arena[root] {
+        Node::Call(_, _, args) => arena.children(args).to_vec(),
+        ref node => panic!("{:?}", node),
+    };
+    assert_eq!(args.len(), 2);
+    match arena[args[0]] {
+        Node::Var(name, _, None) => assert_eq!(name, "list"),
+        ref node => panic!("{:?}", node),
+    }
+    assert!(args.iter().all(|&arg| arena.parent(arg) == Some(root)));
+    assert_eq!(arena.parent(root), None);
+
+    let line = arena.meta_value(root, "line").unwrap();
+    assert_eq!(arena[line], Node::Int(1.into()));
+    assert_eq!(arena.parent(line), Some(root));
+    assert_eq!(arena.meta_value(args[1], "line"), None);
+
+    // Every node but the root is the child of exactly its parent.
+    for (id, node) in arena.iter() {
+        for child in arena.child_ids(node) {
+            assert_eq!(arena.parent(child), Some(id));
+        }
+    }
+}
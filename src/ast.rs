@@ -0,0 +1,36 @@
+use num_bigint::BigInt;
+use symbol::Symbol;
+
+/// Metadata attached to variables and calls, such as `[line: 1, column: 3]`.
+pub type Meta = Vec<(Symbol, Ast)>;
+
+/// Elixir's quoted form, the AST returned by `Code.string_to_quoted/2`.
+///
+/// Literals quote to themselves. Everything else is a three-element tuple:
+/// `{name, meta, context}` for a variable and `{callee, meta, args}` for a
+/// call, where the callee is an atom for local calls and operators or
+/// another node, such as `{:., meta, [module, :fun]}`, for remote calls.
+/// Tuples of any size other than two are calls to `:{}`.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
+pub enum Ast {
+    /// An atom, including `true`, `false` and `nil`.
+    Atom(Symbol),
+    #[cfg_attr(feature = "serde", serde(with = "::json::bigint"))]
+    Int(BigInt),
+    Float(f64),
+    /// A binary, which is what a double-quoted string literal quotes to.
+    String(String),
+    /// A list. Charlists quote to lists of integers.
+    List(Vec<Ast>),
+    /// A two-element tuple.
+    Pair(Box<Ast>, Box<Ast>),
+    /// A variable with its name, metadata and context, `None` for `nil`.
+    Var(Symbol, Meta, Option<Symbol>),
+    /// A call with its callee, metadata and arguments.
+    Call(Box<Ast>, Meta, Vec<Ast>),
+}
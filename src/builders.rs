@@ -0,0 +1,52 @@
+//! Builders for the quoted forms tests are written against.
+//!
+//! Metadata holds just the line, which is left out for line 0, as for
+//! tests that never look at lines.
+
+use ast::{Ast, Meta};
+
+pub fn at(line: usize) -> Meta {
+    if line == 0 {
+        vec![]
+    } else {
+        vec![("line".into(), Ast::Int(line.into()))]
+    }
+}
+
+pub fn atom(name: &str) -> Ast {
+    Ast::Atom(name.into())
+}
+
+pub fn var(name: &str, line: usize) -> Ast {
+    Ast::Var(name.into(), at(line), None)
+}
+
+/// A call to the local function, macro or operator `name`.
+pub fn call(name: &str, line: usize, args: Vec<Ast>) -> Ast {
+    Ast::Call(Box::new(atom(name)), at(line), args)
+}
+
+/// A call to `module.fun`, where `module` is usually an `alias`.
+pub fn remote(module: Ast, fun: &str, line: usize, args: Vec<Ast>) -> Ast {
+    let dot = call(".", line, vec![module, atom(fun)]);
+    Ast::Call(Box::new(dot), at(line), args)
+}
+
+/// An alias such as `Shop.Cart`, split at the dots.
+pub fn alias(name: &str, line: usize) -> Ast {
+    call("__aliases__", line, name.split('.').map(atom).collect())
+}
+
+/// A keyword list, such as the `do:` options of a block.
+pub fn kw(pairs: Vec<(&str, Ast)>) -> Ast {
+    Ast::List(
+        pairs
+            .into_iter()
+            .map(|(key, value)| Ast::Pair(Box::new(atom(key)), Box::new(value)))
+            .collect(),
+    )
+}
+
+pub fn block_of(exprs: Vec<Ast>) -> Ast {
+    call("__block__", 0, exprs)
+}
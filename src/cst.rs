@@ -0,0 +1,345 @@
+//! A lossless concrete syntax tree on top of `rowan`. Every byte of the input
+//! is in exactly one token, trivia included, so `build(input).text()` is the
+//! input again. Green nodes are immutable and shared; `SyntaxNode` adds
+//! parent pointers and offsets, and clones are cheap reference counts.
+//!
+//! Without a parser, `build` produces a `File` node whose children are the
+//! lexer's tokens and trivia. The node kinds for calls, blocks, operators and
+//! containers are what a parser will fill in.
+
+use ast::Ast;
+use lexer::{Lexer, Tok, TriviaKind};
+use rowan::{GreenNodeBuilder, Language};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[repr(u16)]
+pub enum SyntaxKind {
+    // Tokens
+    Atom,
+    Char,
+    Int,
+    KwIdentifier,
+    Star,
+    Whitespace,
+    Newline,
+    Comment,
+    /// Input the lexer rejected.
+    Error,
+    /// Input the lexer does not handle yet, such as identifiers.
+    Unsupported,
+
+    // Nodes
+    File,
+    /// Statements separated by newlines or `;`.
+    Block,
+    /// `do ... end` with its `else`, `after`, `rescue` and `catch` parts.
+    DoBlock,
+    /// A clause `args -> body`.
+    StabClause,
+    Call,
+    /// The arguments of a call, with or without parentheses.
+    Args,
+    /// `left.right`.
+    Dot,
+    /// `Foo.Bar`.
+    Alias,
+    BinaryOp,
+    UnaryOp,
+    Paren,
+    List,
+    Keyword,
+    Tuple,
+    Map,
+    Struct,
+    Bitstring,
+    String,
+    Sigil,
+    Interpolation,
+    Fn,
+}
+
+/// Every kind, in declaration order, so a raw kind indexes its kind.
+const KINDS: &[SyntaxKind] = &[
+    SyntaxKind::Atom,
+    SyntaxKind::Char,
+    SyntaxKind::Int,
+    SyntaxKind::KwIdentifier,
+    SyntaxKind::Star,
+    SyntaxKind::Whitespace,
+    SyntaxKind::Newline,
+    SyntaxKind::Comment,
+    SyntaxKind::Error,
+    SyntaxKind::Unsupported,
+    SyntaxKind::File,
+    SyntaxKind::Block,
+    SyntaxKind::DoBlock,
+    SyntaxKind::StabClause,
+    SyntaxKind::Call,
+    SyntaxKind::Args,
+    SyntaxKind::Dot,
+    SyntaxKind::Alias,
+    SyntaxKind::BinaryOp,
+    SyntaxKind::UnaryOp,
+    SyntaxKind::Paren,
+    SyntaxKind::List,
+    SyntaxKind::Keyword,
+    SyntaxKind::Tuple,
+    SyntaxKind::Map,
+    SyntaxKind::Struct,
+    SyntaxKind::Bitstring,
+    SyntaxKind::String,
+    SyntaxKind::Sigil,
+    SyntaxKind::Interpolation,
+    SyntaxKind::Fn,
+];
+
+impl SyntaxKind {
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::Whitespace | SyntaxKind::Newline | SyntaxKind::Comment
+        )
+    }
+
+    /// Whether the token is input the lexer rejected or does not handle.
+    pub fn is_error(self) -> bool {
+        matches!(self, SyntaxKind::Error | SyntaxKind::Unsupported)
+    }
+}
+
+impl From<SyntaxKind> for rowan::SyntaxKind {
+    fn from(kind: SyntaxKind) -> Self {
+        rowan::SyntaxKind(kind as u16)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Elixir {}
+
+impl Language for Elixir {
+    type Kind = SyntaxKind;
+
+    fn kind_from_raw(raw: rowan::SyntaxKind) -> SyntaxKind {
+        KINDS[raw.0 as usize]
+    }
+
+    fn kind_to_raw(kind: SyntaxKind) -> rowan::SyntaxKind {
+        kind.into()
+    }
+}
+
+pub type SyntaxNode = rowan::SyntaxNode<Elixir>;
+pub type SyntaxToken = rowan::SyntaxToken<Elixir>;
+pub type SyntaxElement = rowan::SyntaxElement<Elixir>;
+
+/// Builds the tree for `input`. Where the lexer stops on input it does not
+/// handle yet, that character, or the whole word it starts, becomes an
+/// `Unsupported` token and lexing resumes after it.
+pub fn build(input: &str) -> SyntaxNode {
+    // Trivia before a lexical error is attached to the next token, so the
+    // pieces are put back in source order before building.
+    let mut pieces = Vec::new();
+    let mut resume = Lexer::new(input).checkpoint();
+    loop {
+        let mut lexer = Lexer::resume_with_trivia(input, resume);
+        for item in lexer.by_ref() {
+            match item {
+                Ok(token) => {
+                    pieces.extend(token.leading.iter().map(trivia_piece));
+                    pieces.push((token.start, token.end, token_kind(&token.tok)));
+                    pieces.extend(token.trailing.iter().map(trivia_piece));
+                }
+                Err(err) => {
+                    let (start, end) = err.span();
+                    pieces.push((start, end, SyntaxKind::Error));
+                }
+            }
+        }
+        pieces.extend(lexer.remaining_trivia().iter().map(trivia_piece));
+        if lexer.at_end() {
+            break;
+        }
+        resume = lexer.checkpoint();
+        let unsupported = unsupported_len(&input[resume.offset..]);
+        pieces.push((
+            resume.offset,
+            resume.offset + unsupported,
+            SyntaxKind::Unsupported,
+        ));
+        resume.col += input[resume.offset..resume.offset + unsupported]
+            .chars()
+            .count() as u32;
+        resume.offset += unsupported;
+    }
+    pieces.sort_by_key(|&(start, _, _)| start);
+
+    let mut builder = GreenNodeBuilder::new();
+    builder.start_node(SyntaxKind::File.into());
+    let mut pieces = pieces.into_iter().peekable();
+    while let Some((start, mut end, kind)) = pieces.next() {
+        // Neighbouring errors are one token, as `foo` is rather than three.
+        if kind.is_error() {
+            while let Some(&(next, next_end, next_kind)) = pieces.peek() {
+                if next != end || next_kind != kind {
+                    break;
+                }
+                end = next_end;
+                pieces.next();
+            }
+        }
+        builder.token(kind.into(), &input[start..end]);
+    }
+    builder.finish_node();
+    SyntaxNode::new_root(builder.finish())
+}
+
+/// The length of the unsupported input at the start of `rest`: one
+/// character, or a whole identifier so that the digits in `foo1` are not
+/// taken for a number.
+fn unsupported_len(rest: &str) -> usize {
+    let word = |c: char| c.is_alphanumeric() || c == '_';
+    match rest.chars().next() {
+        Some(c) if word(c) => {
+            let end = rest.find(|c: char| !word(c)).unwrap_or(rest.len());
+            // `?` and `!` may end an identifier.
+            match rest[end..].chars().next() {
+                Some('?') | Some('!') => end + 1,
+                _ => end,
+            }
+        }
+        Some(c) => c.len_utf8(),
+        None => 0,
+    }
+}
+
+fn trivia_piece(trivia: &::lexer::Trivia) -> (usize, usize, SyntaxKind) {
+    let kind = match trivia.kind {
+        TriviaKind::Whitespace => SyntaxKind::Whitespace,
+        TriviaKind::Newline => SyntaxKind::Newline,
+        TriviaKind::Comment => SyntaxKind::Comment,
+    };
+    (trivia.start, trivia.end, kind)
+}
+
+fn token_kind(tok: &Tok) -> SyntaxKind {
+    match tok {
+        Tok::Atom(_) => SyntaxKind::Atom,
+        Tok::Char(_) => SyntaxKind::Char,
+        Tok::Int(_) => SyntaxKind::Int,
+        Tok::KwIdentifier(_) => SyntaxKind::KwIdentifier,
+        Tok::Star => SyntaxKind::Star,
+    }
+}
+
+/// Converts a node to the quoted form. A file is a block of its
+/// expressions. Returns `None` for errors and for nodes and tokens that are
+/// not expressions on their own, such as a keyword identifier.
+pub fn to_ast(node: &SyntaxNode) -> Option<Ast> {
+    match node.kind() {
+        SyntaxKind::File | SyntaxKind::Block => {
+            let mut exprs = node
+                .children_with_tokens()
+                .filter(|element| !element.kind().is_trivia())
+                .map(|element| match element {
+                    rowan::NodeOrToken::Node(node) => to_ast(&node),
+                    rowan::NodeOrToken::Token(token) => token_to_ast(&token),
+                })
+                .collect::<Option<Vec<_>>>()?;
+            if exprs.len() == 1 {
+                exprs.pop()
+            } else {
+                Some(Ast::Call(
+                    Box::new(Ast::Atom("__block__".into())),
+                    vec![],
+                    exprs,
+                ))
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Converts a literal token. Characters quote to their code point, as
+/// `?a` is `97`.
+pub fn token_to_ast(token: &SyntaxToken) -> Option<Ast> {
+    match Lexer::new(token.text()).next()?.ok()?.1 {
+        Tok::Atom(s) => Some(Ast::Atom(s)),
+        Tok::Char(c) => Some(Ast::Int((c as u32).into())),
+        Tok::Int(i) => Some(Ast::Int(i)),
+        Tok::KwIdentifier(_) | Tok::Star => None,
+    }
+}
+
+#[test]
+fn kinds_round_trip() {
+    for (i, &kind) in KINDS.iter().enumerate() {
+        assert_eq!(kind as usize, i);
+        assert_eq!(Elixir::kind_from_raw(Elixir::kind_to_raw(kind)), kind);
+    }
+}
+
+#[test]
+fn lossless() {
+    let input = "# ops\n:+ :- \r\n0x1F ?a\n<<<<<<< HEAD\n:* foo bar";
+    let file = build(input);
+    assert_eq!(file.text().to_string(), input);
+    assert_eq!(file.kind(), SyntaxKind::File);
+
+    let kinds: Vec<SyntaxKind> = file
+        .children_with_tokens()
+        .map(|element| element.kind())
+        .collect();
+    use self::SyntaxKind::*;
+    assert_eq!(
+        kinds,
+        vec![
+            Comment,
+            Newline,
+            Atom,
+            Whitespace,
+            Atom,
+            Whitespace,
+            Newline,
+            Int,
+            Whitespace,
+            Char,
+            Newline,
+            Error,
+            Atom,
+            Whitespace,
+            Unsupported,
+            Whitespace,
+            Unsupported,
+        ]
+    );
+}
+
+#[test]
+fn navigation() {
+    let file = build(":+ 0b11");
+    let int = file.token_at_offset(4.into()).right_biased().unwrap();
+    assert_eq!(int.kind(), SyntaxKind::Int);
+    assert_eq!(int.text_range(), rowan::TextRange::new(3.into(), 7.into()));
+    assert_eq!(int.parent(), Some(file.clone()));
+    let space = int.prev_sibling_or_token().unwrap();
+    assert_eq!(space.kind(), SyntaxKind::Whitespace);
+    assert_eq!(
+        space.prev_sibling_or_token().unwrap().kind(),
+        SyntaxKind::Atom
+    );
+    assert!(int.next_sibling_or_token().is_none());
+}
+
+#[test]
+fn file_to_ast() {
+    assert_eq!(to_ast(&build("0x10")), Some(Ast::Int(16.into())));
+    assert_eq!(
+        to_ast(&build(":+\n?a")),
+        Some(Ast::Call(
+            Box::new(Ast::Atom("__block__".into())),
+            vec![],
+            vec![Ast::Atom("+".into()), Ast::Int(97.into())],
+        ))
+    );
+    assert_eq!(to_ast(&build(":+ foo")), None);
+}
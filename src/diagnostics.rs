@@ -0,0 +1,271 @@
+use lexer::{Lexed, LexicalError};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const GREEN: &str = "\x1b[1;32m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// A byte range of the source with an optional message, drawn under the
+/// source line it starts on.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Label {
+    pub start: usize,
+    pub end: usize,
+    pub message: String,
+}
+
+/// An error pointing at the source that caused it, with notes for related
+/// places such as where an unclosed block was opened.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Diagnostic {
+    pub message: String,
+    pub label: Label,
+    pub notes: Vec<Label>,
+}
+
+impl Diagnostic {
+    pub fn error<S: Into<String>>(message: S, start: usize, end: usize) -> Self {
+        Diagnostic {
+            message: message.into(),
+            label: Label {
+                start,
+                end,
+                message: String::new(),
+            },
+            notes: Vec::new(),
+        }
+    }
+
+    /// Sets the message printed next to the underline of the error span.
+    pub fn with_label<S: Into<String>>(mut self, message: S) -> Self {
+        self.label.message = message.into();
+        self
+    }
+
+    pub fn with_note<S: Into<String>>(mut self, start: usize, end: usize, message: S) -> Self {
+        self.notes.push(Label {
+            start,
+            end,
+            message: message.into(),
+        });
+        self
+    }
+
+    /// Renders the diagnostic in the style of rustc: a header, the location
+    /// and the offending source line with the span underlined. `color` adds
+    /// ANSI escapes and should only be set when writing to a terminal.
+    pub fn render(&self, name: &str, source: &str, color: bool) -> String {
+        let style = |code: &'static str| if color { code } else { "" };
+        let width = ::std::iter::once(&self.label)
+            .chain(&self.notes)
+            .map(|label| line_col(source, label.start).0.to_string().len())
+            .max()
+            .unwrap_or(1);
+
+        let mut out = format!(
+            "{}error{}{}: {}{}\n",
+            style(RED),
+            style(RESET),
+            style(BOLD),
+            self.message,
+            style(RESET)
+        );
+        snippet(
+            &mut out,
+            name,
+            source,
+            &self.label,
+            '^',
+            style(RED),
+            width,
+            color,
+        );
+        for note in &self.notes {
+            out.push_str(&format!(
+                "{}note{}: {}\n",
+                style(GREEN),
+                style(RESET),
+                note.message
+            ));
+            let label = Label {
+                message: String::new(),
+                ..note.clone()
+            };
+            snippet(
+                &mut out,
+                name,
+                source,
+                &label,
+                '-',
+                style(BLUE),
+                width,
+                color,
+            );
+        }
+        out
+    }
+}
+
+impl<'a> From<&'a LexicalError> for Diagnostic {
+    fn from(err: &'a LexicalError) -> Self {
+        let (start, end) = err.span();
+        Diagnostic::error(err.to_string(), start, end)
+    }
+}
+
+/// The error for input the lexer stopped on at byte `offset` because it
+/// does not handle it yet. An offset past the end of `source` points at its
+/// end.
+pub fn unsupported(source: &str, offset: usize) -> Diagnostic {
+    let offset = floor_char_boundary(source, offset);
+    let end = offset + source[offset..].chars().next().map_or(0, char::len_utf8);
+    Diagnostic::error("unsupported syntax", offset, end)
+        .with_label("the lexer does not handle this yet")
+}
+
+/// The errors of lexing `source`, then where the lexer stopped if it did
+/// not reach the end.
+pub fn lexing(source: &str, errors: &[LexicalError], stopped: Option<usize>) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = errors.iter().map(Diagnostic::from).collect();
+    if let Some(offset) = stopped {
+        diagnostics.push(unsupported(source, offset));
+    }
+    diagnostics
+}
+
+/// Lexes `source` and returns its errors, including where the lexer stopped
+/// if it did not reach the end.
+pub fn lex(source: &str) -> Vec<Diagnostic> {
+    let lexed = Lexed::new(source);
+    lexing(source, &lexed.errors, lexed.stopped)
+}
+
+/// The one-based line and column of a byte offset, counting columns in
+/// characters the way Elixir does.
+pub fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let offset = floor_char_boundary(source, offset);
+    let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line = source[..line_start].matches('\n').count() + 1;
+    (line, source[line_start..offset].chars().count() + 1)
+}
+
+fn floor_char_boundary(source: &str, offset: usize) -> usize {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+#[allow(clippy::too_many_arguments)]
+fn snippet(
+    out: &mut String,
+    name: &str,
+    source: &str,
+    label: &Label,
+    marker: char,
+    marker_style: &str,
+    width: usize,
+    color: bool,
+) {
+    let style = |code: &'static str| if color { code } else { "" };
+    let start = floor_char_boundary(source, label.start);
+    let (line, col) = line_col(source, start);
+    let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[line_start..]
+        .find('\n')
+        .map_or(source.len(), |i| line_start + i);
+    let text = source[line_start..line_end].trim_end_matches('\r');
+
+    // Tabs are expanded so the underline lines up with the text above it.
+    let expand = |s: &str| s.replace('\t', "    ");
+    let end = floor_char_boundary(source, label.end.max(start)).min(line_start + text.len());
+    let padding = expand(&source[line_start..start]).chars().count();
+    let underline = expand(&source[start..end]).chars().count().max(1);
+
+    let gutter = " ".repeat(width);
+    out.push_str(&format!(
+        "{}{}-->{} {}:{}:{}\n",
+        gutter,
+        style(BLUE),
+        style(RESET),
+        name,
+        line,
+        col
+    ));
+    out.push_str(&format!("{} {}|{}\n", gutter, style(BLUE), style(RESET)));
+    out.push_str(&format!(
+        "{}{:>width$} |{} {}\n",
+        style(BLUE),
+        line,
+        style(RESET),
+        expand(text),
+        width = width
+    ));
+    let mut marks = format!(
+        "{} {}|{} {}{}{}",
+        gutter,
+        style(BLUE),
+        style(RESET),
+        " ".repeat(padding),
+        marker_style,
+        marker.to_string().repeat(underline)
+    );
+    if !label.message.is_empty() {
+        marks.push(' ');
+        marks.push_str(&label.message);
+    }
+    marks.push_str(style(RESET));
+    out.push_str(marks.trim_end());
+    out.push('\n');
+}
+
+#[test]
+fn line_col_counts_characters() {
+    let source = "ab\nçd\n";
+    assert_eq!(line_col(source, 0), (1, 1));
+    assert_eq!(line_col(source, 3), (2, 1));
+    assert_eq!(line_col(source, 5), (2, 2));
+    assert_eq!(line_col(source, 100), (3, 1));
+}
+
+#[test]
+fn render_lexical_error() {
+    let source = ":+\n<<<<<<< HEAD\n:-\n";
+    let err = LexicalError::VersionControlMarker { start: 3, end: 10 };
+    let rendered = Diagnostic::from(&err)
+        .with_label("conflict starts here")
+        .render("lib/foo.ex", source, false);
+    assert_eq!(
+        rendered,
+        "error: found an unexpected version control marker, please resolve the conflicts
+ --> lib/foo.ex:2:1
+  |
+2 | <<<<<<< HEAD
+  | ^^^^^^^ conflict starts here
+"
+    );
+}
+
+#[test]
+fn render_note() {
+    let source = "defmodule Foo do\n  def bar do\n";
+    let rendered = Diagnostic::error("missing terminator: end", 29, 29)
+        .with_note(14, 16, "unclosed delimiter")
+        .render("foo.ex", source, false);
+    assert_eq!(
+        rendered,
+        "error: missing terminator: end
+ --> foo.ex:2:13
+  |
+2 |   def bar do
+  |             ^
+note: unclosed delimiter
+ --> foo.ex:1:15
+  |
+1 | defmodule Foo do
+  |               --
+"
+    );
+}
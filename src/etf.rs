@@ -0,0 +1,699 @@
+//! Erlang's External Term Format, as written by `:erlang.term_to_binary/1`
+//! and read by `:erlang.binary_to_term/1`, for exchanging ASTs and tokens
+//! with a BEAM node.
+//!
+//! The encoder makes the same choices as `term_to_binary/1` on OTP 26 and
+//! later, so `encode_ast` of a quoted expression is byte for byte what
+//! `Code.string_to_quoted!(source) |> :erlang.term_to_binary()` produces.
+//! Atoms use the UTF-8 atom tags, lists of integers from 0 to 255 are
+//! written as `STRING_EXT` and integers use the smallest tag that fits. The
+//! decoder also accepts the Latin-1 atom tags and old-style floats older
+//! nodes send, but not compressed terms.
+
+use ast::{Ast, Meta};
+use diagnostics::line_col;
+use lexer::Tok;
+use num_bigint::{BigInt, Sign};
+use num_traits::ToPrimitive;
+use std::fmt;
+use symbol::Symbol;
+
+const VERSION: u8 = 131;
+const NEW_FLOAT_EXT: u8 = 70;
+const SMALL_INTEGER_EXT: u8 = 97;
+const INTEGER_EXT: u8 = 98;
+const FLOAT_EXT: u8 = 99;
+const ATOM_EXT: u8 = 100;
+const SMALL_TUPLE_EXT: u8 = 104;
+const LARGE_TUPLE_EXT: u8 = 105;
+const NIL_EXT: u8 = 106;
+const STRING_EXT: u8 = 107;
+const LIST_EXT: u8 = 108;
+const BINARY_EXT: u8 = 109;
+const SMALL_BIG_EXT: u8 = 110;
+const LARGE_BIG_EXT: u8 = 111;
+const SMALL_ATOM_EXT: u8 = 115;
+const ATOM_UTF8_EXT: u8 = 118;
+const SMALL_ATOM_UTF8_EXT: u8 = 119;
+
+/// How deeply lists and tuples may nest in decoded input, so crafted input
+/// fails instead of overflowing the stack. A quoted expression nests about
+/// two terms for each level of code.
+const MAX_DEPTH: usize = 512;
+
+/// The subset of Erlang terms that quoted expressions and tokens are made
+/// of. Lists are always proper.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Term {
+    Atom(String),
+    Int(BigInt),
+    Float(f64),
+    Binary(Vec<u8>),
+    List(Vec<Term>),
+    Tuple(Vec<Term>),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum DecodeError {
+    UnexpectedEnd,
+    TrailingBytes,
+    UnsupportedVersion(u8),
+    UnsupportedTag(u8),
+    InvalidUtf8,
+    InvalidFloat,
+    ImproperList,
+    TooDeep,
+    /// An atom too long to intern, or one too many, see `Symbol::try_intern`.
+    AtomLimit(String),
+    /// The input is a valid term but not the shape that was asked for.
+    Unexpected(&'static str, Term),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "unexpected end of input"),
+            DecodeError::TrailingBytes => write!(f, "unexpected bytes after the term"),
+            DecodeError::UnsupportedVersion(v) => write!(f, "unsupported version {}", v),
+            DecodeError::UnsupportedTag(tag) => write!(f, "unsupported tag {}", tag),
+            DecodeError::InvalidUtf8 => write!(f, "invalid UTF-8"),
+            DecodeError::InvalidFloat => write!(f, "invalid float"),
+            DecodeError::ImproperList => write!(f, "improper lists are not supported"),
+            DecodeError::TooDeep => write!(f, "terms nested more than {} deep", MAX_DEPTH),
+            DecodeError::AtomLimit(name) => write!(f, "cannot intern the atom {:?}", name),
+            DecodeError::Unexpected(expected, term) => {
+                write!(f, "expected {}, got {:?}", expected, term)
+            }
+        }
+    }
+}
+
+pub fn encode(term: &Term) -> Vec<u8> {
+    let mut out = vec![VERSION];
+    encode_term(term, &mut out);
+    out
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Term, DecodeError> {
+    let mut decoder = Decoder {
+        bytes,
+        pos: 0,
+        depth: 0,
+    };
+    match decoder.u8()? {
+        VERSION => {}
+        v => return Err(DecodeError::UnsupportedVersion(v)),
+    }
+    let term = decoder.term()?;
+    if decoder.pos != bytes.len() {
+        return Err(DecodeError::TrailingBytes);
+    }
+    Ok(term)
+}
+
+pub fn encode_ast(ast: &Ast) -> Vec<u8> {
+    encode(&Term::from(ast))
+}
+
+pub fn decode_ast(bytes: &[u8]) -> Result<Ast, DecodeError> {
+    decode(bytes)?.to_ast()
+}
+
+/// Encodes tokens as the list `:elixir_tokenizer.tokenize/4` returns, such
+/// as `{:int, {1, 1, 241}, '0xf1'}` or `{:atom, {1, 6, nil}, :+}`. `source`
+/// is the lexed input, needed for positions and original spellings.
+pub fn encode_tokens(source: &str, tokens: &[(usize, Tok, usize)]) -> Vec<u8> {
+    let terms = tokens
+        .iter()
+        .map(|token| token_to_term(source, token))
+        .collect();
+    encode(&Term::List(terms))
+}
+
+pub fn decode_tokens(bytes: &[u8]) -> Result<Vec<Tok>, DecodeError> {
+    match decode(bytes)? {
+        Term::List(terms) => terms.iter().map(token_from_term).collect(),
+        term => Err(DecodeError::Unexpected("a list of tokens", term)),
+    }
+}
+
+pub fn token_to_term(source: &str, token: &(usize, Tok, usize)) -> Term {
+    let (start, ref tok, end) = *token;
+    let (line, col) = line_col(source, start);
+    let position = |extra: Term| {
+        Term::Tuple(vec![
+            Term::Int(BigInt::from(line)),
+            Term::Int(BigInt::from(col)),
+            extra,
+        ])
+    };
+    let original = || {
+        let text = source.get(start..end).unwrap_or("");
+        Term::List(
+            text.chars()
+                .map(|c| Term::Int(BigInt::from(c as u32)))
+                .collect(),
+        )
+    };
+    let atom = |s: &str| Term::Atom(s.to_string());
+    let (kind, meta, value) = match tok {
+        Tok::Atom(s) => ("atom", position(atom("nil")), atom(s)),
+        Tok::KwIdentifier(s) => ("kw_identifier", position(atom("nil")), atom(s)),
+        Tok::Int(i) => ("int", position(Term::Int(i.clone())), original()),
+        Tok::Char(c) => (
+            "char",
+            position(original()),
+            Term::Int(BigInt::from(*c as u32)),
+        ),
+        Tok::Star => ("mult_op", position(atom("nil")), atom("*")),
+    };
+    Term::Tuple(vec![atom(kind), meta, value])
+}
+
+pub fn token_from_term(term: &Term) -> Result<Tok, DecodeError> {
+    let unexpected = || DecodeError::Unexpected("a token", term.clone());
+    let (kind, meta, value) = match term {
+        Term::Tuple(elems) if elems.len() == 3 => (&elems[0], &elems[1], &elems[2]),
+        _ => return Err(unexpected()),
+    };
+    match (kind, meta, value) {
+        (Term::Atom(kind), _, Term::Atom(s)) if kind == "atom" => Ok(Tok::Atom(symbol(s)?)),
+        (Term::Atom(kind), _, Term::Atom(s)) if kind == "kw_identifier" => {
+            Ok(Tok::KwIdentifier(symbol(s)?))
+        }
+        (Term::Atom(kind), _, Term::Atom(s)) if kind == "mult_op" && s == "*" => Ok(Tok::Star),
+        (Term::Atom(kind), Term::Tuple(meta), _) if kind == "int" && meta.len() == 3 => {
+            match meta[2] {
+                Term::Int(ref i) => Ok(Tok::Int(i.clone())),
+                _ => Err(unexpected()),
+            }
+        }
+        (Term::Atom(kind), _, Term::Int(i)) if kind == "char" => i
+            .to_u32()
+            .and_then(::std::char::from_u32)
+            .map(Tok::Char)
+            .ok_or_else(unexpected),
+        _ => Err(unexpected()),
+    }
+}
+
+impl<'a> From<&'a Ast> for Term {
+    fn from(ast: &'a Ast) -> Self {
+        match ast {
+            Ast::Atom(s) => Term::Atom(s.to_string()),
+            Ast::Int(i) => Term::Int(i.clone()),
+            Ast::Float(f) => Term::Float(*f),
+            Ast::String(s) => Term::Binary(s.clone().into_bytes()),
+            Ast::List(elems) => Term::List(elems.iter().map(Term::from).collect()),
+            Ast::Pair(a, b) => Term::Tuple(vec![Term::from(&**a), Term::from(&**b)]),
+            Ast::Var(name, meta, context) => Term::Tuple(vec![
+                Term::Atom(name.to_string()),
+                meta_to_term(meta),
+                Term::Atom(
+                    context
+                        .map_or("nil", |context| context.as_str())
+                        .to_string(),
+                ),
+            ]),
+            Ast::Call(callee, meta, args) => Term::Tuple(vec![
+                Term::from(&**callee),
+                meta_to_term(meta),
+                Term::List(args.iter().map(Term::from).collect()),
+            ]),
+        }
+    }
+}
+
+impl Term {
+    /// Reads a quoted expression back. Tuples must have two elements or be a
+    /// variable or call with keyword metadata, as in any valid quoted form.
+    pub fn to_ast(&self) -> Result<Ast, DecodeError> {
+        match self {
+            Term::Atom(s) => symbol(s).map(Ast::Atom),
+            Term::Int(i) => Ok(Ast::Int(i.clone())),
+            Term::Float(f) => Ok(Ast::Float(*f)),
+            Term::Binary(bytes) => String::from_utf8(bytes.clone())
+                .map(Ast::String)
+                .map_err(|_| DecodeError::InvalidUtf8),
+            Term::List(elems) => elems
+                .iter()
+                .map(Term::to_ast)
+                .collect::<Result<_, _>>()
+                .map(Ast::List),
+            Term::Tuple(elems) if elems.len() == 2 => Ok(Ast::Pair(
+                Box::new(elems[0].to_ast()?),
+                Box::new(elems[1].to_ast()?),
+            )),
+            Term::Tuple(elems) if elems.len() == 3 => {
+                let meta = meta_from_term(&elems[1])?;
+                match (&elems[0], &elems[2]) {
+                    (Term::Atom(name), Term::Atom(context)) => Ok(Ast::Var(
+                        symbol(name)?,
+                        meta,
+                        if context == "nil" {
+                            None
+                        } else {
+                            Some(symbol(context)?)
+                        },
+                    )),
+                    (callee, Term::List(args)) => Ok(Ast::Call(
+                        Box::new(callee.to_ast()?),
+                        meta,
+                        args.iter().map(Term::to_ast).collect::<Result<_, _>>()?,
+                    )),
+                    _ => Err(DecodeError::Unexpected("a quoted expression", self.clone())),
+                }
+            }
+            _ => Err(DecodeError::Unexpected("a quoted expression", self.clone())),
+        }
+    }
+}
+
+/// Interns a decoded atom, within the limits for untrusted input.
+fn symbol(name: &str) -> Result<Symbol, DecodeError> {
+    Symbol::try_intern(name).ok_or_else(|| DecodeError::AtomLimit(name.to_string()))
+}
+
+fn meta_to_term(meta: &Meta) -> Term {
+    Term::List(
+        meta.iter()
+            .map(|(key, value)| Term::Tuple(vec![Term::Atom(key.to_string()), Term::from(value)]))
+            .collect(),
+    )
+}
+
+fn meta_from_term(term: &Term) -> Result<Meta, DecodeError> {
+    let unexpected = || DecodeError::Unexpected("keyword metadata", term.clone());
+    match term {
+        Term::List(elems) => elems
+            .iter()
+            .map(|elem| match elem {
+                Term::Tuple(pair) if pair.len() == 2 => match pair[0] {
+                    Term::Atom(ref key) => Ok((symbol(key)?, pair[1].to_ast()?)),
+                    _ => Err(unexpected()),
+                },
+                _ => Err(unexpected()),
+            })
+            .collect(),
+        _ => Err(unexpected()),
+    }
+}
+
+fn encode_term(term: &Term, out: &mut Vec<u8>) {
+    match term {
+        Term::Atom(s) => {
+            if s.len() < 256 {
+                out.push(SMALL_ATOM_UTF8_EXT);
+                out.push(s.len() as u8);
+            } else {
+                out.push(ATOM_UTF8_EXT);
+                out.extend_from_slice(&(s.len() as u16).to_be_bytes());
+            }
+            out.extend_from_slice(s.as_bytes());
+        }
+        Term::Int(i) => encode_int(i, out),
+        Term::Float(f) => {
+            out.push(NEW_FLOAT_EXT);
+            out.extend_from_slice(&f.to_bits().to_be_bytes());
+        }
+        Term::Binary(bytes) => {
+            out.push(BINARY_EXT);
+            out.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+            out.extend_from_slice(bytes);
+        }
+        Term::List(elems) if elems.is_empty() => out.push(NIL_EXT),
+        Term::List(elems) => {
+            let bytes: Option<Vec<u8>> = elems
+                .iter()
+                .map(|elem| match elem {
+                    Term::Int(i) => i.to_u8(),
+                    _ => None,
+                })
+                .collect();
+            match bytes {
+                Some(ref bytes) if bytes.len() <= 0xffff => {
+                    out.push(STRING_EXT);
+                    out.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+                    out.extend_from_slice(bytes);
+                }
+                _ => {
+                    out.push(LIST_EXT);
+                    out.extend_from_slice(&(elems.len() as u32).to_be_bytes());
+                    for elem in elems {
+                        encode_term(elem, out);
+                    }
+                    out.push(NIL_EXT);
+                }
+            }
+        }
+        Term::Tuple(elems) => {
+            if elems.len() < 256 {
+                out.push(SMALL_TUPLE_EXT);
+                out.push(elems.len() as u8);
+            } else {
+                out.push(LARGE_TUPLE_EXT);
+                out.extend_from_slice(&(elems.len() as u32).to_be_bytes());
+            }
+            for elem in elems {
+                encode_term(elem, out);
+            }
+        }
+    }
+}
+
+fn encode_int(i: &BigInt, out: &mut Vec<u8>) {
+    if let Some(small) = i.to_u8() {
+        out.push(SMALL_INTEGER_EXT);
+        out.push(small);
+    } else if let Some(int) = i.to_i32() {
+        out.push(INTEGER_EXT);
+        out.extend_from_slice(&int.to_be_bytes());
+    } else {
+        let (sign, digits) = i.to_bytes_le();
+        if digits.len() < 256 {
+            out.push(SMALL_BIG_EXT);
+            out.push(digits.len() as u8);
+        } else {
+            out.push(LARGE_BIG_EXT);
+            out.extend_from_slice(&(digits.len() as u32).to_be_bytes());
+        }
+        out.push(if sign == Sign::Minus { 1 } else { 0 });
+        out.extend_from_slice(&digits);
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// The lists and tuples being decoded.
+    depth: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        if self.bytes.len() - self.pos < n {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+        let bytes = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<usize, DecodeError> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]) as usize)
+    }
+
+    fn u32(&mut self) -> Result<usize, DecodeError> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+    }
+
+    fn term(&mut self) -> Result<Term, DecodeError> {
+        // Only lists and tuples recurse, so they are kept apart from the
+        // other tags to keep each level's stack frame small.
+        match self.u8()? {
+            SMALL_TUPLE_EXT => {
+                let n = self.u8()? as usize;
+                self.terms(n).map(Term::Tuple)
+            }
+            LARGE_TUPLE_EXT => {
+                let n = self.u32()?;
+                self.terms(n).map(Term::Tuple)
+            }
+            LIST_EXT => {
+                let n = self.u32()?;
+                let elems = self.terms(n)?;
+                match self.u8()? {
+                    NIL_EXT => Ok(Term::List(elems)),
+                    _ => Err(DecodeError::ImproperList),
+                }
+            }
+            tag => self.scalar(tag),
+        }
+    }
+
+    fn scalar(&mut self, tag: u8) -> Result<Term, DecodeError> {
+        match tag {
+            SMALL_INTEGER_EXT => Ok(Term::Int(BigInt::from(self.u8()?))),
+            INTEGER_EXT => {
+                let b = self.take(4)?;
+                Ok(Term::Int(BigInt::from(i32::from_be_bytes([
+                    b[0], b[1], b[2], b[3],
+                ]))))
+            }
+            SMALL_BIG_EXT => {
+                let n = self.u8()? as usize;
+                self.big(n)
+            }
+            LARGE_BIG_EXT => {
+                let n = self.u32()?;
+                self.big(n)
+            }
+            NEW_FLOAT_EXT => {
+                let b = self.take(8)?;
+                let mut bits = [0; 8];
+                bits.copy_from_slice(b);
+                Ok(Term::Float(f64::from_bits(u64::from_be_bytes(bits))))
+            }
+            FLOAT_EXT => {
+                let b = self.take(31)?;
+                ::std::str::from_utf8(b)
+                    .ok()
+                    .and_then(|s| s.trim_end_matches('\0').parse().ok())
+                    .map(Term::Float)
+                    .ok_or(DecodeError::InvalidFloat)
+            }
+            ATOM_EXT => {
+                let n = self.u16()?;
+                self.latin1_atom(n)
+            }
+            SMALL_ATOM_EXT => {
+                let n = self.u8()? as usize;
+                self.latin1_atom(n)
+            }
+            ATOM_UTF8_EXT => {
+                let n = self.u16()?;
+                self.utf8_atom(n)
+            }
+            SMALL_ATOM_UTF8_EXT => {
+                let n = self.u8()? as usize;
+                self.utf8_atom(n)
+            }
+            NIL_EXT => Ok(Term::List(Vec::new())),
+            STRING_EXT => {
+                let n = self.u16()?;
+                let bytes = self.take(n)?;
+                Ok(Term::List(
+                    bytes.iter().map(|b| Term::Int(BigInt::from(*b))).collect(),
+                ))
+            }
+            BINARY_EXT => {
+                let n = self.u32()?;
+                Ok(Term::Binary(self.take(n)?.to_vec()))
+            }
+            tag => Err(DecodeError::UnsupportedTag(tag)),
+        }
+    }
+
+    fn terms(&mut self, n: usize) -> Result<Vec<Term>, DecodeError> {
+        if self.depth == MAX_DEPTH {
+            return Err(DecodeError::TooDeep);
+        }
+        self.depth += 1;
+        // Every term takes at least one byte, which bounds the allocation.
+        let mut terms = Vec::with_capacity(n.min(self.bytes.len() - self.pos));
+        for _ in 0..n {
+            terms.push(self.term()?);
+        }
+        self.depth -= 1;
+        Ok(terms)
+    }
+
+    fn big(&mut self, n: usize) -> Result<Term, DecodeError> {
+        let sign = match self.u8()? {
+            0 => Sign::Plus,
+            _ => Sign::Minus,
+        };
+        Ok(Term::Int(BigInt::from_bytes_le(sign, self.take(n)?)))
+    }
+
+    fn latin1_atom(&mut self, n: usize) -> Result<Term, DecodeError> {
+        Ok(Term::Atom(
+            self.take(n)?.iter().map(|&b| b as char).collect(),
+        ))
+    }
+
+    fn utf8_atom(&mut self, n: usize) -> Result<Term, DecodeError> {
+        String::from_utf8(self.take(n)?.to_vec())
+            .map(Term::Atom)
+            .map_err(|_| DecodeError::InvalidUtf8)
+    }
+}
+
+#[test]
+fn encode_quoted_call() {
+    // Code.string_to_quoted!("foo(1, \"a\")") |> :erlang.term_to_binary()
+    let ast = Ast::Call(
+        Box::new(Ast::Atom("foo".into())),
+        vec![("line".into(), Ast::Int(BigInt::from(1)))],
+        vec![Ast::Int(BigInt::from(1)), Ast::String("a".to_string())],
+    );
+    let bytes = encode_ast(&ast);
+    assert_eq!(
+        bytes,
+        vec![
+            131, 104, 3, 119, 3, b'f', b'o', b'o', 108, 0, 0, 0, 1, 104, 2, 119, 4, b'l', b'i',
+            b'n', b'e', 97, 1, 106, 108, 0, 0, 0, 2, 97, 1, 109, 0, 0, 0, 1, b'a', 106,
+        ]
+    );
+    assert_eq!(decode_ast(&bytes), Ok(ast));
+}
+
+#[test]
+fn encode_integers() {
+    let cases: Vec<(BigInt, Vec<u8>)> = vec![
+        (BigInt::from(255), vec![131, 97, 255]),
+        (BigInt::from(256), vec![131, 98, 0, 0, 1, 0]),
+        (BigInt::from(-1), vec![131, 98, 255, 255, 255, 255]),
+        (
+            BigInt::from(1u64 << 32),
+            vec![131, 110, 5, 0, 0, 0, 0, 0, 1],
+        ),
+        (
+            -BigInt::from(1u64 << 32),
+            vec![131, 110, 5, 1, 0, 0, 0, 0, 1],
+        ),
+    ];
+    for (i, bytes) in cases {
+        assert_eq!(encode(&Term::Int(i.clone())), bytes);
+        assert_eq!(decode(&bytes), Ok(Term::Int(i)));
+    }
+
+    let large = BigInt::from(1) << 2048;
+    let bytes = encode(&Term::Int(large.clone()));
+    assert_eq!(&bytes[..7], &[131, 111, 0, 0, 1, 1, 0]);
+    assert_eq!(decode(&bytes), Ok(Term::Int(large)));
+}
+
+#[test]
+fn encode_lists() {
+    let charlist = Term::List(vec![
+        Term::Int(BigInt::from(104)),
+        Term::Int(BigInt::from(105)),
+    ]);
+    assert_eq!(encode(&charlist), vec![131, 107, 0, 2, 104, 105]);
+    assert_eq!(encode(&Term::List(vec![])), vec![131, 106]);
+    assert_eq!(decode(&[131, 107, 0, 2, 104, 105]), Ok(charlist));
+    assert_eq!(
+        decode(&[131, 108, 0, 0, 0, 1, 97, 1, 97, 2]),
+        Err(DecodeError::ImproperList)
+    );
+}
+
+#[test]
+fn decode_latin1_atoms_and_floats() {
+    assert_eq!(
+        decode(&[131, 100, 0, 2, b'o', b'k']),
+        Ok(Term::Atom("ok".to_string()))
+    );
+    assert_eq!(
+        decode(&[131, 115, 1, 0xe9]),
+        Ok(Term::Atom("é".to_string()))
+    );
+    let float = encode(&Term::Float(1.5));
+    assert_eq!(float, vec![131, 70, 63, 248, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(decode(&float), Ok(Term::Float(1.5)));
+    assert_eq!(decode(&[131, 80]), Err(DecodeError::UnsupportedTag(80)));
+    assert_eq!(decode(&[131, 97]), Err(DecodeError::UnexpectedEnd));
+}
+
+#[test]
+fn encode_variable() {
+    let var = Ast::Var("x".into(), vec![], None);
+    let bytes = encode_ast(&var);
+    assert_eq!(
+        bytes,
+        vec![131, 104, 3, 119, 1, b'x', 106, 119, 3, b'n', b'i', b'l']
+    );
+    assert_eq!(decode_ast(&bytes), Ok(var));
+}
+
+#[test]
+fn tokens_match_the_tokenizer() {
+    use lexer::Lexer;
+
+    let source = "0xf1:+";
+    let tokens: Vec<_> = Lexer::new(source).map(Result::unwrap).collect();
+    let bytes = encode_tokens(source, &tokens);
+    // [{:int, {1, 1, 241}, '0xf1'}, {:atom, {1, 5, nil}, :+}]
+    let expected = Term::List(vec![
+        Term::Tuple(vec![
+            Term::Atom("int".to_string()),
+            Term::Tuple(vec![
+                Term::Int(BigInt::from(1)),
+                Term::Int(BigInt::from(1)),
+                Term::Int(BigInt::from(241)),
+            ]),
+            Term::List(
+                "0xf1"
+                    .chars()
+                    .map(|c| Term::Int(BigInt::from(c as u32)))
+                    .collect(),
+            ),
+        ]),
+        Term::Tuple(vec![
+            Term::Atom("atom".to_string()),
+            Term::Tuple(vec![
+                Term::Int(BigInt::from(1)),
+                Term::Int(BigInt::from(5)),
+                Term::Atom("nil".to_string()),
+            ]),
+            Term::Atom("+".to_string()),
+        ]),
+    ]);
+    assert_eq!(decode(&bytes), Ok(expected));
+    let toks: Vec<Tok> = tokens.into_iter().map(|(_, tok, _)| tok).collect();
+    assert_eq!(decode_tokens(&bytes), Ok(toks));
+}
+
+#[test]
+fn deep_nesting() {
+    let nested = |prefix: &[u8], depth: usize, suffix: &[u8]| {
+        let mut bytes = vec![VERSION];
+        for _ in 0..depth {
+            bytes.extend_from_slice(prefix);
+        }
+        bytes.push(NIL_EXT);
+        for _ in 0..depth {
+            bytes.extend_from_slice(suffix);
+        }
+        bytes
+    };
+    // {{{...{[]}...}}} and [[[...[[]]...]]]
+    let tuples = nested(&[SMALL_TUPLE_EXT, 1], 100_000, &[]);
+    assert_eq!(decode(&tuples), Err(DecodeError::TooDeep));
+    let lists = nested(&[LIST_EXT, 0, 0, 0, 1], 100_000, &[NIL_EXT]);
+    assert_eq!(decode(&lists), Err(DecodeError::TooDeep));
+
+    let mut term = Term::List(vec![]);
+    for _ in 0..MAX_DEPTH {
+        term = Term::Tuple(vec![term]);
+    }
+    assert_eq!(
+        decode(&nested(&[SMALL_TUPLE_EXT, 1], MAX_DEPTH, &[])),
+        Ok(term)
+    );
+}
+
+#[test]
+fn long_atoms_are_not_interned() {
+    let name = "a".repeat(300);
+    let bytes = encode(&Term::Atom(name.clone()));
+    assert_eq!(decode(&bytes), Ok(Term::Atom(name.clone())));
+    assert_eq!(decode_ast(&bytes), Err(DecodeError::AtomLimit(name)));
+}
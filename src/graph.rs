@@ -0,0 +1,622 @@
+//! The dependencies between the modules of a project, from syntax alone,
+//! in the manner of `mix xref graph`.
+//!
+//! `Dependencies::new` reads what one file defines and refers to; it can
+//! be cached per file like any other analysis. `Graph::new` joins the
+//! files and keeps the edges between modules they define. Each edge has
+//! the strongest label of the references behind it, following xref:
+//!
+//! - compile: `require` and `use`, a `defimpl`, and remote calls in a
+//!   module body, which run while compiling. The module recompiles when
+//!   the other one changes.
+//! - export: `import`, structs and `@behaviour`, which depend on what the
+//!   other module exports. The module recompiles when that changes.
+//! - runtime: remote calls in functions and modules used as values.
+//! - alias: an `alias` and nothing more. Xref leaves these out, as they
+//!   recompile nothing, so they count toward neither cycles nor
+//!   `compile_connected`, but they show where a module is still named.
+//!
+//! Unlike xref, which knows after compiling which calls were to macros,
+//! a remote call in a function is taken to be a function call, and an
+//! unqualified call in a module body is a compile edge only when a single
+//! `import` could have provided it.
+
+use ast::Ast;
+#[cfg(test)]
+use builders::{alias, block_of, call, kw, remote, var};
+use outline::Outline;
+use resolve::{self, ModuleRef, Usage};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+use symbol::Symbol;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Label {
+    Alias,
+    Runtime,
+    Export,
+    Compile,
+}
+
+impl Label {
+    /// The label of the edge `reference` makes, if it makes one.
+    fn of(reference: &ModuleRef) -> Option<Self> {
+        match reference.usage {
+            Usage::Require | Usage::Use | Usage::Impl => Some(Label::Compile),
+            Usage::Call if !reference.in_function => Some(Label::Compile),
+            Usage::Import | Usage::Struct | Usage::Behaviour => Some(Label::Export),
+            Usage::Call | Usage::Reference => Some(Label::Runtime),
+            Usage::Alias => Some(Label::Alias),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Label::Alias => "alias",
+            Label::Runtime => "runtime",
+            Label::Export => "export",
+            Label::Compile => "compile",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Edge {
+    pub from: Symbol,
+    pub to: Symbol,
+    pub label: Label,
+}
+
+/// What one file defines and depends on.
+#[derive(Clone, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Dependencies {
+    pub modules: Vec<Symbol>,
+    /// The edges out of the modules of the file, to any module.
+    pub edges: Vec<Edge>,
+}
+
+impl Dependencies {
+    pub fn new(ast: &Ast) -> Self {
+        let modules = Outline::new(ast)
+            .modules
+            .iter()
+            .map(|module| module.name)
+            .collect();
+        let resolution = resolve::resolve(ast);
+        let mut labels = BTreeMap::new();
+        let mut add = |from: Option<Symbol>, to: Symbol, label: Label| {
+            if let Some(from) = from.filter(|&from| from != to) {
+                let strongest = labels.entry((from, to)).or_insert(label);
+                *strongest = (*strongest).max(label);
+            }
+        };
+        for reference in &resolution.modules {
+            if let Some(label) = Label::of(reference) {
+                add(reference.within, reference.module, label);
+            }
+        }
+        for call in &resolution.calls {
+            if let [module] = call.imports[..] {
+                if !call.in_function {
+                    add(call.within, module, Label::Compile);
+                }
+            }
+        }
+        Dependencies {
+            modules,
+            edges: labels
+                .into_iter()
+                .map(|((from, to), label)| Edge { from, to, label })
+                .collect(),
+        }
+    }
+}
+
+/// A set of modules that all depend on one another, directly or not.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Cycle {
+    pub modules: Vec<Symbol>,
+    /// The edges between the modules.
+    pub edges: Vec<Edge>,
+    /// The strongest label of the edges.
+    pub label: Label,
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Graph {
+    /// The modules the files define, sorted.
+    pub modules: Vec<Symbol>,
+    /// The edges between them, sorted.
+    pub edges: Vec<Edge>,
+}
+
+impl Graph {
+    pub fn new<I: IntoIterator<Item = Dependencies>>(files: I) -> Self {
+        let files: Vec<Dependencies> = files.into_iter().collect();
+        let modules: BTreeSet<Symbol> = files
+            .iter()
+            .flat_map(|file| file.modules.iter().cloned())
+            .collect();
+        let mut edges: Vec<Edge> = files
+            .into_iter()
+            .flat_map(|file| file.edges)
+            .filter(|edge| modules.contains(&edge.to))
+            .collect();
+        edges.sort();
+        // A module defined twice keeps its strongest edge to each other.
+        edges.dedup_by(|later, earlier| {
+            (later.from, later.to) == (earlier.from, earlier.to) && {
+                earlier.label = earlier.label.max(later.label);
+                true
+            }
+        });
+        Graph {
+            modules: modules.into_iter().collect(),
+            edges,
+        }
+    }
+
+    /// The edges out of `module`.
+    pub fn dependencies(&self, module: Symbol) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(move |edge| edge.from == module)
+    }
+
+    /// The edges out of `module` that are dependencies, leaving out aliases.
+    fn depends_on(&self, module: Symbol) -> impl Iterator<Item = &Edge> {
+        self.dependencies(module)
+            .filter(|edge| edge.label != Label::Alias)
+    }
+
+    /// The modules whose changes recompile `module`: those it depends on
+    /// at compile time and everything they depend on in turn, sorted.
+    pub fn compile_connected(&self, module: Symbol) -> Vec<Symbol> {
+        let mut reached = BTreeSet::new();
+        let mut stack: Vec<Symbol> = self
+            .dependencies(module)
+            .filter(|edge| edge.label == Label::Compile)
+            .map(|edge| edge.to)
+            .collect();
+        while let Some(next) = stack.pop() {
+            if reached.insert(next) {
+                stack.extend(self.depends_on(next).map(|edge| edge.to));
+            }
+        }
+        // Chains back through `module` count, but not the module itself.
+        reached.remove(&module);
+        reached.into_iter().collect()
+    }
+
+    /// The cycles, compile-time ones first, then the largest.
+    pub fn cycles(&self) -> Vec<Cycle> {
+        let mut cycles: Vec<Cycle> = self
+            .strongly_connected()
+            .into_iter()
+            .filter(|component| component.len() > 1)
+            .map(|mut modules| {
+                modules.sort();
+                let edges: Vec<Edge> = self
+                    .edges
+                    .iter()
+                    .filter(|edge| {
+                        edge.label != Label::Alias
+                            && modules.binary_search(&edge.from).is_ok()
+                            && modules.binary_search(&edge.to).is_ok()
+                    })
+                    .cloned()
+                    .collect();
+                let label = edges.iter().map(|edge| edge.label).max().unwrap();
+                Cycle {
+                    modules,
+                    edges,
+                    label,
+                }
+            })
+            .collect();
+        cycles.sort_by(|a, b| {
+            (b.label, b.modules.len())
+                .cmp(&(a.label, a.modules.len()))
+                .then_with(|| a.modules.cmp(&b.modules))
+        });
+        cycles
+    }
+
+    /// Tarjan's algorithm over the modules.
+    fn strongly_connected(&self) -> Vec<Vec<Symbol>> {
+        struct State<'a> {
+            graph: &'a Graph,
+            index: HashMap<Symbol, usize>,
+            low: HashMap<Symbol, usize>,
+            stack: Vec<Symbol>,
+            on_stack: BTreeSet<Symbol>,
+            components: Vec<Vec<Symbol>>,
+        }
+
+        fn visit(state: &mut State, module: Symbol) {
+            let index = state.index.len();
+            state.index.insert(module, index);
+            state.low.insert(module, index);
+            state.stack.push(module);
+            state.on_stack.insert(module);
+            let graph = state.graph;
+            for edge in graph.depends_on(module) {
+                if !state.index.contains_key(&edge.to) {
+                    visit(state, edge.to);
+                    let low = state.low[&module].min(state.low[&edge.to]);
+                    state.low.insert(module, low);
+                } else if state.on_stack.contains(&edge.to) {
+                    let low = state.low[&module].min(state.index[&edge.to]);
+                    state.low.insert(module, low);
+                }
+            }
+            if state.low[&module] == index {
+                let mut component = Vec::new();
+                while let Some(member) = state.stack.pop() {
+                    state.on_stack.remove(&member);
+                    component.push(member);
+                    if member == module {
+                        break;
+                    }
+                }
+                state.components.push(component);
+            }
+        }
+
+        let mut state = State {
+            graph: self,
+            index: HashMap::new(),
+            low: HashMap::new(),
+            stack: Vec::new(),
+            on_stack: BTreeSet::new(),
+            components: Vec::new(),
+        };
+        for &module in &self.modules {
+            if !state.index.contains_key(&module) {
+                visit(&mut state, module);
+            }
+        }
+        state.components
+    }
+
+    /// The graph in Graphviz's format, labelled like `mix xref graph
+    /// --format dot`.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph \"modules\" {\n");
+        for module in &self.modules {
+            let _ = writeln!(dot, "  {:?}", module.as_str());
+        }
+        for edge in &self.edges {
+            let _ = write!(dot, "  {:?} -> {:?}", edge.from.as_str(), edge.to.as_str());
+            if edge.label != Label::Runtime {
+                let _ = write!(dot, " [label=\"({})\"]", edge.label.as_str());
+            }
+            dot.push('\n');
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        ::serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// A report of the cycles and the edges that close them.
+    pub fn cycles_report(&self) -> String {
+        let cycles = self.cycles();
+        if cycles.is_empty() {
+            return "no cycles\n".to_string();
+        }
+        let compile = cycles
+            .iter()
+            .filter(|cycle| cycle.label == Label::Compile)
+            .count();
+        let mut report = format!(
+            "{} cycle{}, {} with compile-time edges\n",
+            cycles.len(),
+            if cycles.len() == 1 { "" } else { "s" },
+            compile
+        );
+        for cycle in &cycles {
+            let _ = writeln!(
+                report,
+                "\n{} modules, {}:",
+                cycle.modules.len(),
+                cycle.label.as_str()
+            );
+            for edge in &cycle.edges {
+                let _ = write!(report, "  {} -> {}", edge.from, edge.to);
+                if edge.label != Label::Runtime {
+                    let _ = write!(report, " ({})", edge.label.as_str());
+                }
+                report.push('\n');
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+fn defmodule(name: &str, body: Vec<Ast>) -> Ast {
+    call(
+        "defmodule",
+        0,
+        vec![alias(name, 0), kw(vec![("do", block_of(body))])],
+    )
+}
+
+#[cfg(test)]
+fn def(name: &str, body: Ast) -> Ast {
+    call("def", 0, vec![var(name, 0), kw(vec![("do", body)])])
+}
+
+/// defmodule A do
+///   use B
+///   def f, do: C.g() && Enum.count([])
+/// end
+/// defmodule B do
+///   def h, do: %A{}
+/// end
+/// defmodule C do
+///   @x D.k()
+///   def g, do: A.f()
+/// end
+/// defmodule D do
+///   def k, do: 1
+/// end
+/// defmodule E do
+///   def x, do: F.y()
+/// end
+/// defmodule F do
+///   import E
+///   def y, do: E.x()
+/// end
+#[cfg(test)]
+fn files() -> Vec<Ast> {
+    vec![
+        block_of(vec![
+            defmodule(
+                "A",
+                vec![
+                    call("use", 0, vec![alias("B", 0)]),
+                    def(
+                        "f",
+                        call(
+                            "&&",
+                            0,
+                            vec![
+                                remote(alias("C", 0), "g", 0, vec![]),
+                                remote(alias("Enum", 0), "count", 0, vec![]),
+                            ],
+                        ),
+                    ),
+                ],
+            ),
+            defmodule(
+                "B",
+                vec![def(
+                    "h",
+                    call("%", 0, vec![alias("A", 0), call("%{}", 0, vec![])]),
+                )],
+            ),
+        ]),
+        defmodule(
+            "C",
+            vec![
+                call(
+                    "@",
+                    0,
+                    vec![call("x", 0, vec![remote(alias("D", 0), "k", 0, vec![])])],
+                ),
+                def("g", remote(alias("A", 0), "f", 0, vec![])),
+            ],
+        ),
+        defmodule("D", vec![def("k", Ast::Int(1.into()))]),
+        block_of(vec![
+            defmodule("E", vec![def("x", remote(alias("F", 0), "y", 0, vec![]))]),
+            defmodule(
+                "F",
+                vec![
+                    call("import", 0, vec![alias("E", 0)]),
+                    def("y", remote(alias("E", 0), "x", 0, vec![])),
+                ],
+            ),
+        ]),
+    ]
+}
+
+#[cfg(test)]
+fn graph() -> Graph {
+    Graph::new(files().iter().map(Dependencies::new))
+}
+
+#[test]
+fn edges() {
+    let first = Dependencies::new(&files()[0]);
+    assert_eq!(first.modules, vec!["A", "B"]);
+    // Outside the project, Enum is dropped from the graph only.
+    assert!(first.edges.iter().any(|edge| edge.to == "Enum"));
+
+    let graph = graph();
+    assert_eq!(graph.modules, vec!["A", "B", "C", "D", "E", "F"]);
+    let edges: Vec<_> = graph
+        .edges
+        .iter()
+        .map(|edge| format!("{} -> {} {}", edge.from, edge.to, edge.label.as_str()))
+        .collect();
+    assert_eq!(
+        edges,
+        vec![
+            "A -> B compile",
+            "A -> C runtime",
+            "B -> A export",
+            "C -> A runtime",
+            "C -> D compile",
+            "E -> F runtime",
+            // Imported and called: the strongest wins.
+            "F -> E export",
+        ]
+    );
+    assert_eq!(graph.compile_connected("A".into()), vec!["B", "C", "D"]);
+    assert_eq!(graph.compile_connected("C".into()), vec!["D"]);
+    assert!(graph.compile_connected("D".into()).is_empty());
+}
+
+#[test]
+fn struct_patterns() {
+    // defmodule A do
+    //   def f(%B{} = b), do: b
+    // end
+    let head = call(
+        "f",
+        2,
+        vec![call(
+            "=",
+            2,
+            vec![
+                call("%", 2, vec![alias("B", 2), call("%{}", 2, vec![])]),
+                var("b", 2),
+            ],
+        )],
+    );
+    let a = call(
+        "defmodule",
+        1,
+        vec![
+            alias("A", 1),
+            kw(vec![(
+                "do",
+                call("def", 2, vec![head, kw(vec![("do", var("b", 2))])]),
+            )]),
+        ],
+    );
+    let b = call(
+        "defmodule",
+        1,
+        vec![alias("B", 1), kw(vec![("do", block_of(vec![]))])],
+    );
+    let graph = Graph::new(vec![Dependencies::new(&a), Dependencies::new(&b)]);
+    assert_eq!(
+        graph.edges,
+        vec![Edge {
+            from: "A".into(),
+            to: "B".into(),
+            label: Label::Export,
+        }]
+    );
+}
+
+#[test]
+fn cycles() {
+    let graph = graph();
+    let cycles = graph.cycles();
+    assert_eq!(cycles.len(), 2);
+    assert_eq!(cycles[0].modules, vec!["A", "B", "C"]);
+    assert_eq!(cycles[0].label, Label::Compile);
+    assert_eq!(cycles[1].modules, vec!["E", "F"]);
+    assert_eq!(cycles[1].label, Label::Export);
+    assert_eq!(
+        graph.cycles_report(),
+        "2 cycles, 1 with compile-time edges
+
+3 modules, compile:
+  A -> B (compile)
+  A -> C
+  B -> A (export)
+  C -> A
+
+2 modules, export:
+  E -> F
+  F -> E (export)
+"
+    );
+    assert_eq!(Graph::default().cycles_report(), "no cycles\n");
+
+    // An alias back is no cycle.
+    let edge = |from: &str, to: &str, label| Edge {
+        from: from.into(),
+        to: to.into(),
+        label,
+    };
+    let aliased = Graph {
+        modules: vec!["X".into(), "Y".into()],
+        edges: vec![edge("X", "Y", Label::Alias), edge("Y", "X", Label::Runtime)],
+    };
+    assert!(aliased.cycles().is_empty());
+}
+
+#[test]
+fn formats() {
+    let graph = Graph::new(vec![
+        Dependencies::new(&files()[0]),
+        Dependencies::new(&files()[1]),
+    ]);
+    assert_eq!(
+        graph.to_dot(),
+        r#"digraph "modules" {
+  "A"
+  "B"
+  "C"
+  "A" -> "B" [label="(compile)"]
+  "A" -> "C"
+  "B" -> "A" [label="(export)"]
+  "C" -> "A"
+}
+"#
+    );
+    #[cfg(feature = "serde")]
+    {
+        let json: ::serde_json::Value = ::serde_json::from_str(&graph.to_json()).unwrap();
+        assert_eq!(json["modules"], ::serde_json::json!(["A", "B", "C"]));
+        assert_eq!(
+            json["edges"][0],
+            ::serde_json::json!({"from": "A", "to": "B", "label": "compile"})
+        );
+    }
+}
+
+#[test]
+fn impls_and_aliases() {
+    // defmodule Size do
+    //   def size
+    // end
+    // defimpl Size, for: Map do
+    //   def size, do: map_size()
+    // end
+    // defmodule G do
+    //   alias Size
+    // end
+    let opts = kw(vec![
+        ("for", alias("Map", 0)),
+        ("do", def("size", call("map_size", 0, vec![]))),
+    ]);
+    let ast = block_of(vec![
+        defmodule("Size", vec![call("def", 0, vec![call("size", 0, vec![])])]),
+        call("defimpl", 0, vec![alias("Size", 0), opts]),
+        defmodule("G", vec![call("alias", 0, vec![alias("Size", 0)])]),
+    ]);
+    let dependencies = Dependencies::new(&ast);
+    assert_eq!(dependencies.modules, vec!["Size", "Size.Map", "G"]);
+    let graph = Graph::new(vec![dependencies]);
+    assert_eq!(
+        graph.edges,
+        vec![
+            Edge {
+                from: "G".into(),
+                to: "Size".into(),
+                label: Label::Alias,
+            },
+            Edge {
+                from: "Size.Map".into(),
+                to: "Size".into(),
+                label: Label::Compile,
+            },
+        ]
+    );
+    assert!(graph.compile_connected("G".into()).is_empty());
+}
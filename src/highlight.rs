@@ -0,0 +1,166 @@
+//! Syntax highlighting driven by the lexer, so the colors always agree with
+//! how the code tokenizes. Output covers every byte of the input: text the
+//! lexer does not handle yet is left plain rather than dropped, and
+//! highlighting picks up again right after it. Only input the lexer rejects
+//! is marked as an error.
+//! Strings, sigils, heredocs and interpolation get classes of their own as
+//! the lexer learns them.
+
+use cst::{self, SyntaxKind};
+
+const RESET: &str = "\x1b[0m";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Class {
+    Atom,
+    /// A keyword key such as `do:`.
+    Keyword,
+    Char,
+    Number,
+    Operator,
+    Comment,
+    Error,
+}
+
+impl Class {
+    /// The CSS class used in HTML output.
+    pub fn css_class(self) -> &'static str {
+        match self {
+            Class::Atom => "atom",
+            Class::Keyword => "keyword",
+            Class::Char => "char",
+            Class::Number => "number",
+            Class::Operator => "operator",
+            Class::Comment => "comment",
+            Class::Error => "error",
+        }
+    }
+
+    fn ansi(self) -> &'static str {
+        match self {
+            Class::Atom | Class::Keyword => "\x1b[36m",
+            Class::Char | Class::Number => "\x1b[33m",
+            Class::Operator => "\x1b[35m",
+            Class::Comment => "\x1b[90m",
+            Class::Error => "\x1b[4;31m",
+        }
+    }
+}
+
+/// The class of a token kind, `None` for whitespace, newlines and input
+/// the lexer does not handle yet.
+pub fn classify(kind: SyntaxKind) -> Option<Class> {
+    match kind {
+        SyntaxKind::Atom => Some(Class::Atom),
+        SyntaxKind::KwIdentifier => Some(Class::Keyword),
+        SyntaxKind::Char => Some(Class::Char),
+        SyntaxKind::Int => Some(Class::Number),
+        SyntaxKind::Star => Some(Class::Operator),
+        SyntaxKind::Comment => Some(Class::Comment),
+        SyntaxKind::Error => Some(Class::Error),
+        _ => None,
+    }
+}
+
+/// Splits `input` into consecutive pieces with their classes.
+pub fn highlight(input: &str) -> Vec<(&str, Option<Class>)> {
+    let file = cst::build(input);
+    let mut offset = 0;
+    file.descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .map(|token| {
+            let len = token.text().len();
+            let piece = &input[offset..offset + len];
+            offset += len;
+            (piece, classify(token.kind()))
+        })
+        .collect()
+}
+
+/// Colors `input` with ANSI escapes for a terminal.
+pub fn to_ansi(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for (text, class) in highlight(input) {
+        match class {
+            Some(class) => {
+                out.push_str(class.ansi());
+                out.push_str(text);
+                out.push_str(RESET);
+            }
+            None => out.push_str(text),
+        }
+    }
+    out
+}
+
+/// Renders `input` as an HTML `<pre>` block, with a `<span>` carrying the
+/// CSS class of each highlighted token.
+pub fn to_html(input: &str) -> String {
+    let mut out = String::from("<pre class=\"elixir\"><code>");
+    for (text, class) in highlight(input) {
+        match class {
+            Some(class) => out.push_str(&format!(
+                "<span class=\"{}\">{}</span>",
+                class.css_class(),
+                escape_html(text)
+            )),
+            None => out.push_str(&escape_html(text)),
+        }
+    }
+    out.push_str("</code></pre>\n");
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[test]
+fn html() {
+    assert_eq!(
+        to_html(":<<>> 0x1F # a & b\nfoo"),
+        concat!(
+            "<pre class=\"elixir\"><code>",
+            "<span class=\"atom\">:&lt;&lt;&gt;&gt;</span> ",
+            "<span class=\"number\">0x1F</span> ",
+            "<span class=\"comment\"># a &amp; b</span>\n",
+            "foo",
+            "</code></pre>\n"
+        )
+    );
+}
+
+#[test]
+fn ansi() {
+    assert_eq!(to_ansi("?a do: "), "\x1b[33m?a\x1b[0m do: ");
+    // A conflict marker is rejected rather than unsupported.
+    assert_eq!(to_ansi("<<<<<<< HEAD"), "\x1b[4;31m<<<<<<< HEAD\x1b[0m");
+    assert_eq!(to_ansi("%{}: *"), "\x1b[36m%{}:\x1b[0m \x1b[35m*\x1b[0m");
+}
+
+#[test]
+fn resumes_after_unsupported_input() {
+    let input = "defmodule Foo do\n  @ops [:+, 0x1F] # ok?\nend";
+    let classes: Vec<_> = highlight(input)
+        .into_iter()
+        .filter(|(_, class)| class.is_some())
+        .collect();
+    assert_eq!(
+        classes,
+        vec![
+            (":+", Some(Class::Atom)),
+            ("0x1F", Some(Class::Number)),
+            ("# ok?", Some(Class::Comment)),
+        ]
+    );
+}
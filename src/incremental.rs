@@ -0,0 +1,214 @@
+//! Re-lexing after an edit without lexing the whole file again.
+//!
+//! `Tokens` keeps a checkpoint of the lexer's state before each token. An
+//! edit re-lexes from the checkpoint of the first token it can affect and
+//! stops as soon as the new lexer reaches, in the same state, a checkpoint
+//! past the edit: from there the old tokens are reused, shifted by the
+//! change in length. The lexer keeps no state besides its position, so a
+//! checkpoint is enough to resume; strings, heredocs and interpolation will
+//! add theirs to `Checkpoint` as the lexer learns them. Without a parser the
+//! tokens are all there is to update.
+
+use lexer::{Checkpoint, Lexer, LexicalError, Spanned, Tok};
+use std::ops::Range;
+
+/// Replaces the bytes `start..end` of the source with `text`. Both ends must
+/// be on character boundaries.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Edit {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+pub struct Tokens {
+    source: String,
+    items: Vec<Spanned<Tok, usize, LexicalError>>,
+    /// The lexer's state before each item and, last, before it stopped.
+    checkpoints: Vec<Checkpoint>,
+    /// Where the lexer stopped.
+    stop: usize,
+}
+
+impl Tokens {
+    pub fn new(source: String) -> Self {
+        let mut tokens = Tokens {
+            source,
+            items: Vec::new(),
+            checkpoints: Vec::new(),
+            stop: 0,
+        };
+        let start = Lexer::new(&tokens.source).checkpoint();
+        tokens.relex(0, start, None);
+        tokens
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The tokens and errors, as `Lexer` yields them but owning their text,
+    /// as the source changes under them.
+    pub fn items(&self) -> &[Spanned<Tok, usize, LexicalError>] {
+        &self.items
+    }
+
+    /// The byte offset where the lexer stopped on input it does not handle,
+    /// or `None` if it reached the end.
+    pub fn stopped(&self) -> Option<usize> {
+        if self.stop < self.source.len() {
+            Some(self.stop)
+        } else {
+            None
+        }
+    }
+
+    /// Applies `edit` and returns the range of `items` that changed. Items
+    /// after the range are the old ones, shifted.
+    pub fn apply(&mut self, edit: &Edit) -> Range<usize> {
+        self.source.replace_range(edit.start..edit.end, &edit.text);
+
+        // A token looks one character past its end, so the first item that
+        // can change is the first ending at or after the edit.
+        let first = self.items.partition_point(|item| span(item).1 < edit.start);
+        let resume = self.checkpoints[first];
+        self.relex(first, resume, Some(edit))
+    }
+
+    /// Lexes from `resume` and replaces the items from `first` on, reusing
+    /// old items once the lexer catches up with them after `edit`.
+    fn relex(&mut self, first: usize, resume: Checkpoint, edit: Option<&Edit>) -> Range<usize> {
+        let delta = edit.map_or(0, |edit| {
+            edit.text.len() as isize - (edit.end - edit.start) as isize
+        });
+        let inserted_end = edit.map_or(0, |edit| edit.start + edit.text.len());
+
+        let mut items = Vec::new();
+        let mut checkpoints = Vec::new();
+        let mut lexer = Lexer::resume(&self.source, resume);
+        let mut old = first;
+        let mut synced = None;
+        loop {
+            let checkpoint = lexer.checkpoint();
+            if edit.is_some() && checkpoint.offset >= inserted_end {
+                // Skip old checkpoints the new lexer has moved past.
+                let old_offset = shift(checkpoint.offset, -delta);
+                while old < self.items.len() && self.checkpoints[old].offset < old_offset {
+                    old += 1;
+                }
+                if old < self.items.len() {
+                    let old_checkpoint = self.checkpoints[old];
+                    if old_checkpoint.offset == old_offset && old_checkpoint.col == checkpoint.col {
+                        synced = Some((old, checkpoint.line as i64 - old_checkpoint.line as i64));
+                        break;
+                    }
+                }
+            }
+            checkpoints.push(checkpoint);
+            match lexer.next() {
+                Some(item) => items.push(item),
+                None => break,
+            }
+        }
+
+        let changed = first..first + items.len();
+        match synced {
+            Some((old, lines)) => {
+                for item in &mut self.items[old..] {
+                    shift_item(item, delta);
+                }
+                for checkpoint in &mut self.checkpoints[old..] {
+                    checkpoint.offset = shift(checkpoint.offset, delta);
+                    checkpoint.line = (checkpoint.line as i64 + lines) as u32;
+                }
+                self.stop = shift(self.stop, delta);
+                self.items.splice(first..old, items);
+                self.checkpoints.splice(first..old, checkpoints);
+            }
+            None => {
+                self.stop = lexer.offset();
+                self.items.truncate(first);
+                self.items.extend(items);
+                self.checkpoints.truncate(first);
+                self.checkpoints.extend(checkpoints);
+            }
+        }
+        changed
+    }
+}
+
+fn span(item: &Spanned<Tok, usize, LexicalError>) -> (usize, usize) {
+    match item {
+        Ok((start, _, end)) => (*start, *end),
+        Err(err) => err.span(),
+    }
+}
+
+fn shift(offset: usize, delta: isize) -> usize {
+    (offset as isize + delta) as usize
+}
+
+fn shift_item(item: &mut Spanned<Tok, usize, LexicalError>, delta: isize) {
+    match item {
+        Ok((start, _, end)) => {
+            *start = shift(*start, delta);
+            *end = shift(*end, delta);
+        }
+        Err(LexicalError::VersionControlMarker { start, end })
+        | Err(LexicalError::InvalidSigil { start, end }) => {
+            *start = shift(*start, delta);
+            *end = shift(*end, delta);
+        }
+    }
+}
+
+#[cfg(test)]
+fn assert_matches_full_lex(tokens: &Tokens) {
+    let mut lexer = Lexer::new(tokens.source());
+    let items: Vec<_> = lexer.by_ref().collect();
+    assert_eq!(tokens.items(), &items[..], "source: {:?}", tokens.source());
+    assert_eq!(tokens.stop, lexer.offset());
+}
+
+#[test]
+fn edit_reuses_tokens_after_the_change() {
+    let mut tokens = Tokens::new(":+ :-\n:* 0x10\n:/ :<>".to_string());
+    let changed = tokens.apply(&Edit {
+        start: 3,
+        end: 5,
+        text: ":<<>> :|||".to_string(),
+    });
+    assert_eq!(tokens.source(), ":+ :<<>> :|||\n:* 0x10\n:/ :<>");
+    assert_matches_full_lex(&tokens);
+    // `:-` becomes two tokens. `:*` is lexed again as the checkpoint before
+    // it is on the edited line, where columns moved; the rest is reused.
+    assert_eq!(changed, 1..4);
+}
+
+#[test]
+fn edit_changes_later_tokens() {
+    let mut tokens = Tokens::new("0x1 :+\n<<<<<<< x\n:-".to_string());
+    tokens.apply(&Edit {
+        start: 3,
+        end: 3,
+        text: "F".to_string(),
+    });
+    assert_matches_full_lex(&tokens);
+
+    // Joining the lines moves the marker off column 0.
+    tokens.apply(&Edit {
+        start: 7,
+        end: 8,
+        text: " ".to_string(),
+    });
+    assert_matches_full_lex(&tokens);
+    assert_eq!(tokens.stopped(), Some(8));
+
+    tokens.apply(&Edit {
+        start: 7,
+        end: 8,
+        text: "\n".to_string(),
+    });
+    assert_matches_full_lex(&tokens);
+    assert_eq!(tokens.stopped(), None);
+}
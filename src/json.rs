@@ -0,0 +1,179 @@
+//! The JSON form of lexer output, behind the `serde` feature. This is what
+//! `elixir_parser tokenize --format json` and `check --format json` print,
+//! one object per line for each file:
+//!
+//! ```json
+//! {
+//!   "file": "lib/foo.ex",
+//!   "tokens": [
+//!     {"start": 0, "end": 5, "kind": "int", "value": "3866"},
+//!     {"start": 5, "end": 7, "kind": "atom", "value": "+"}
+//!   ],
+//!   "errors": [
+//!     {"start": 7, "end": 8, "line": 1, "column": 8, "message": "unsupported syntax"}
+//!   ]
+//! }
+//! ```
+//!
+//! `tokens` is left out by `check`. `start` and `end` are byte offsets into
+//! the file, `end` exclusive. `line` and `column` are one-based and columns
+//! count characters, as in Elixir.
+//!
+//! Each token has a `kind` and, except for `star`, a string `value`:
+//!
+//! | kind            | value                                             |
+//! |-----------------|---------------------------------------------------|
+//! | `atom`          | the atom without its leading colon, e.g. `"<<>>"` |
+//! | `kw_identifier` | the keyword without its trailing colon            |
+//! | `char`          | the character of a `?c` literal                   |
+//! | `int`           | the value in decimal, so any size survives        |
+//! | `star`          | none                                              |
+//!
+//! Kinds are never reused, so atoms, and later charlists and binaries, stay
+//! distinguishable. New kinds and fields may be added; existing ones keep
+//! their meaning. A `LexicalError` on its own serializes as its `kind`
+//! (`version_control_marker` or `invalid_sigil`) with `start` and `end`.
+//!
+//! An `Ast` uses the same `kind` and `value` pairs. `atom`, `string`, `int`
+//! (a decimal string) and `float` hold their value directly, `list` an array
+//! of nodes and `pair` an array of two nodes. `var` is `[name, meta,
+//! context]` with a `null` context for `nil`, and `call` is `[callee, meta,
+//! args]`. Metadata is an array of `[key, node]` pairs, so `foo(1)` is
+//!
+//! ```json
+//! {"kind": "call", "value": [
+//!   {"kind": "atom", "value": "foo"},
+//!   [["line", {"kind": "int", "value": "1"}]],
+//!   [{"kind": "int", "value": "1"}]
+//! ]}
+//! ```
+
+use diagnostics::{self, Diagnostic};
+use lexer::Tok;
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct File {
+    pub file: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<Vec<Token>>,
+    pub errors: Vec<Error>,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Token {
+    pub start: usize,
+    pub end: usize,
+    #[serde(flatten)]
+    pub tok: Tok,
+}
+
+impl Token {
+    pub fn new(start: usize, tok: Tok, end: usize) -> Self {
+        Token { start, end, tok }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Error {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Error {
+    pub fn new(source: &str, diagnostic: &Diagnostic) -> Self {
+        let label = &diagnostic.label;
+        let (line, column) = diagnostics::line_col(source, label.start);
+        Error {
+            start: label.start,
+            end: label.end,
+            line,
+            column,
+            message: diagnostic.message.clone(),
+        }
+    }
+}
+
+/// Serializes a `BigInt` as a decimal string, since JSON numbers lose
+/// precision past 2^53 in most readers.
+pub mod bigint {
+    use num_bigint::BigInt;
+    use serde::de::{Deserialize, Deserializer, Error};
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(i: &BigInt, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(i)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BigInt, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(D::Error::custom)
+    }
+}
+
+#[test]
+fn token_schema() {
+    use num_bigint::BigInt;
+    use serde_json;
+
+    let tokens = vec![
+        Token::new(0, Tok::Int(BigInt::from(1u64 << 60) * 16), 17),
+        Token::new(17, Tok::Atom("<<>>".into()), 22),
+        Token::new(22, Tok::KwIdentifier("%{}".into()), 26),
+        Token::new(26, Tok::Char('é'), 29),
+        Token::new(29, Tok::Star, 30),
+        Token::new(31, Tok::Atom("\\\\".into()), 34),
+    ];
+    let json = serde_json::to_string(&tokens).unwrap();
+    assert_eq!(
+        json,
+        concat!(
+            r#"[{"start":0,"end":17,"kind":"int","value":"18446744073709551616"},"#,
+            r#"{"start":17,"end":22,"kind":"atom","value":"<<>>"},"#,
+            r#"{"start":22,"end":26,"kind":"kw_identifier","value":"%{}"},"#,
+            r#"{"start":26,"end":29,"kind":"char","value":"é"},"#,
+            r#"{"start":29,"end":30,"kind":"star"},"#,
+            r#"{"start":31,"end":34,"kind":"atom","value":"\\\\"}]"#
+        )
+    );
+    assert_eq!(serde_json::from_str::<Vec<Token>>(&json).unwrap(), tokens);
+}
+
+#[test]
+fn ast_schema() {
+    use ast::Ast;
+    use num_bigint::BigInt;
+    use serde_json;
+
+    let ast = Ast::Call(
+        Box::new(Ast::Atom("foo".into())),
+        vec![("line".into(), Ast::Int(BigInt::from(1)))],
+        vec![
+            Ast::String("a".to_string()),
+            Ast::Var("x".into(), vec![], None),
+        ],
+    );
+    let json = serde_json::to_string(&ast).unwrap();
+    assert_eq!(
+        json,
+        concat!(
+            r#"{"kind":"call","value":[{"kind":"atom","value":"foo"},"#,
+            r#"[["line",{"kind":"int","value":"1"}]],"#,
+            r#"[{"kind":"string","value":"a"},{"kind":"var","value":["x",[],null]}]]}"#
+        )
+    );
+    assert_eq!(serde_json::from_str::<Ast>(&json).unwrap(), ast);
+}
+
+#[test]
+fn lexical_error_schema() {
+    use lexer::LexicalError;
+    use serde_json;
+
+    let err = LexicalError::InvalidSigil { start: 3, end: 6 };
+    let json = serde_json::to_string(&err).unwrap();
+    assert_eq!(json, r#"{"kind":"invalid_sigil","start":3,"end":6}"#);
+    assert_eq!(serde_json::from_str::<LexicalError>(&json).unwrap(), err);
+}
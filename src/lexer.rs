@@ -1,39 +1,209 @@
-use itertools::Itertools;
 use num_bigint::BigInt;
+use std::fmt;
+use symbol::Symbol;
+
+#[cfg(test)]
 use num_bigint::ToBigInt;
-use num_traits::Num;
 
 pub type Spanned<Tok, Loc, Error> = Result<(Loc, Tok, Loc), Error>;
 
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
 pub enum Tok {
-    Atom(String),
+    Atom(Symbol),
     Char(char),
+    #[cfg_attr(feature = "serde", serde(with = "::json::bigint"))]
     Int(BigInt),
-    KwIdentifier(String),
+    KwIdentifier(Symbol),
     Star,
 }
 
+/// Prints a token back as source text that lexes to the same token. Integers
+/// are always printed in hexadecimal and keyword identifiers must be followed
+/// by whitespace to lex again.
+impl fmt::Display for Tok {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Tok::Atom(s) => write!(f, ":{}", s),
+            Tok::Char(c) => write!(f, "?{}", c),
+            Tok::Int(i) => write!(f, "0x{:x}", i),
+            Tok::KwIdentifier(s) => write!(f, "{}:", s),
+            Tok::Star => write!(f, "*"),
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", rename_all = "snake_case"))]
 pub enum LexicalError {
-    VersionControlMarker,
-    InvalidSigil,
+    VersionControlMarker { start: usize, end: usize },
+    InvalidSigil { start: usize, end: usize },
+}
+
+impl LexicalError {
+    /// The byte offsets of the input that caused the error.
+    pub fn span(&self) -> (usize, usize) {
+        match *self {
+            LexicalError::VersionControlMarker { start, end } => (start, end),
+            LexicalError::InvalidSigil { start, end } => (start, end),
+        }
+    }
+}
+
+impl fmt::Display for LexicalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LexicalError::VersionControlMarker { .. } => write!(
+                f,
+                "found an unexpected version control marker, please resolve the conflicts"
+            ),
+            LexicalError::InvalidSigil { .. } => write!(f, "invalid sigil delimiter"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TriviaKind {
+    /// A run of spaces and tabs.
+    Whitespace,
+    /// `\n` or `\r\n`.
+    Newline,
+    /// A comment from `#` up to the end of the line, not including it.
+    Comment,
+}
+
+/// Whitespace or a comment between tokens, as a byte range of the input.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Trivia {
+    pub fn text<'a>(&self, input: &'a str) -> &'a str {
+        &input[self.start..self.end]
+    }
+}
+
+/// A token with the trivia around it. Trailing trivia runs up to the end of
+/// the token's line; everything after that leads the next token.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TriviaToken {
+    pub leading: Vec<Trivia>,
+    pub start: usize,
+    pub tok: Tok,
+    pub end: usize,
+    pub trailing: Vec<Trivia>,
 }
 
+/// A comment with its position, in the shape of the comments returned by
+/// Elixir's `Code.string_to_quoted_with_comments/2`. Lines and columns are
+/// one-based.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Comment {
+    pub line: usize,
+    pub column: usize,
+    /// Newlines between the previous token and the comment, or 1 if there
+    /// is no previous token.
+    pub previous_eol_count: usize,
+    /// Newlines after the comment before the next token or comment.
+    pub next_eol_count: usize,
+    pub text: String,
+}
+
+/// Where a lexer is in its input. A lexer resumed from a checkpoint
+/// produces the same tokens as the one the checkpoint was taken from.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Checkpoint {
+    pub offset: usize,
+    pub line: u32,
+    pub col: u32,
+}
+
+/// Operators that follow `:` in an atom or precede `:` in a keyword as
+/// they are, rather than by the operator rules.
+const SPECIAL_OPERATORS: &[&str] = &["...", "<<>>", "%{}", "%", "{}"];
+
+/// Atom and keyword names are interned as they are lexed.
+/// Tokens borrow their text from the input.
 pub struct Lexer<'input> {
-    chars: itertools::MultiPeek<std::str::Chars<'input>>,
+    input: &'input str,
+    bytes: &'input [u8],
+    pos: usize,
     line: u32,
     col: u32,
+    /// Trivia skipped since the last token, kept only by `TriviaLexer`.
+    trivia: Option<Vec<Trivia>>,
 }
 
 impl<'input> Lexer<'input> {
     pub fn new(input: &'input str) -> Self {
+        Lexer::resume(
+            input,
+            Checkpoint {
+                offset: 0,
+                line: 0,
+                col: 0,
+            },
+        )
+    }
+
+    /// A lexer that starts at `checkpoint`, taken from a lexer over the
+    /// same input up to at least the checkpoint's offset.
+    pub fn resume(input: &'input str, checkpoint: Checkpoint) -> Self {
         Lexer {
-            chars: itertools::multipeek(input.chars()),
-            line: 0,
-            col: 0,
+            input,
+            bytes: input.as_bytes(),
+            pos: checkpoint.offset,
+            line: checkpoint.line,
+            col: checkpoint.col,
+            trivia: None,
         }
     }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            offset: self.pos,
+            line: self.line,
+            col: self.col,
+        }
+    }
+
+    /// A lexer that keeps whitespace and comments, see `TriviaLexer`.
+    pub fn with_trivia(input: &'input str) -> TriviaLexer<'input> {
+        let start = Lexer::new(input).checkpoint();
+        Lexer::resume_with_trivia(input, start)
+    }
+
+    /// A lexer that keeps trivia, starting at `checkpoint` as `resume`
+    /// does.
+    pub fn resume_with_trivia(input: &'input str, checkpoint: Checkpoint) -> TriviaLexer<'input> {
+        let mut lexer = Lexer::resume(input, checkpoint);
+        lexer.trivia = Some(Vec::new());
+        TriviaLexer { lexer }
+    }
+
+    /// The byte offset the lexer has reached.
+    pub fn offset(&self) -> usize {
+        self.pos
+    }
+
+    /// The line and column the lexer has reached, both counted from zero.
+    pub fn location(&self) -> (u32, u32) {
+        (self.line, self.col)
+    }
+
+    /// Whether all of the input has been consumed. The lexer stops early on
+    /// input it does not handle yet.
+    pub fn at_end(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
 }
 
 impl<'input> Iterator for Lexer<'input> {
@@ -41,586 +211,759 @@ impl<'input> Iterator for Lexer<'input> {
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            // VC Merge Conflict
-            // tokenize(("<<<<<<<" ++ _) = Original, Line, 1, _Scope, Tokens) ->
-            if self.col == 0
-                && self.match_char('<')
-                && self.match_char('<')
-                && self.match_char('<')
-                && self.match_char('<')
-                && self.match_char('<')
-                && self.match_char('<')
-                && self.match_char('<')
-            {
-                self.consume(7);
-                self.consume_to_eol();
-                return Some(Err(LexicalError::VersionControlMarker));
-            }
-            self.chars.reset_peek();
-
-            // Base integers
-            // tokenize([$0, $x, H | T], Line, Column, Scope, Tokens) when ?is_hex(H) ->
-            if self.match_char('0') && self.match_char('x') && self.match_fn(&|ch| ch.is_digit(16))
-            {
-                self.consume(2);
-
-                let digits: String = self.chars.take_while_ref(|c| c.is_digit(16)).collect();
-                self.col += digits.len() as u32;
-                let val: BigInt = BigInt::from_str_radix(&digits, 16).unwrap();
-                return Some(Ok((1, Tok::Int(val), 1)));
-            }
-            self.chars.reset_peek();
-
-            // tokenize([$0, $b, H | T], Line, Column, Scope, Tokens) when ?is_bin(H) ->
-            if self.match_char('0') && self.match_char('b') && self.match_fn(&|ch| ch.is_digit(2)) {
-                self.consume(2);
-                let digits: String = self.chars.take_while_ref(|c| c.is_digit(2)).collect();
-                self.col += digits.len() as u32;
-                let val: BigInt = BigInt::from_str_radix(&digits, 2).unwrap();
-                return Some(Ok((1, Tok::Int(val), 1)));
-            }
-            self.chars.reset_peek();
-
-            // tokenize([$0, $o, H | T], Line, Column, Scope, Tokens) when ?is_octal(H) ->
-            if self.match_char('0') && self.match_char('o') && self.match_fn(&|ch| ch.is_digit(8)) {
-                self.consume(2);
-                let digits: String = self.chars.take_while_ref(|c| c.is_digit(8)).collect();
-                self.col += digits.len() as u32;
-                let val: BigInt = BigInt::from_str_radix(&digits, 8).unwrap();
-                return Some(Ok((1, Tok::Int(val), 1)));
-            }
-            self.chars.reset_peek();
-
-            // Comments
-
-            // tokenize([$# | String], Line, Column, Scope, Tokens) ->
-            if self.match_char('#') {
-                self.consume(1);
-                self.consume_to_eol();
-                continue;
-            }
-            self.chars.reset_peek();
-
-            // Sigils
-
-            // tokenize([$~, S, H, H, H | T] = Original, Line, Column, Scope, Tokens) when ?is_quote(H), ?is_upcase(S) orelse ?is_downcase(S) ->
-            if self.match_char('~') {
-                if let Some(&s) = &self.chars.peek() {
-                    if let Some(&h1) = &self.chars.peek() {
-                        if let Some(&h2) = &self.chars.peek() {
-                            if let Some(&h3) = &self.chars.peek() {
-                                if s.is_ascii_alphabetic()
-                                    && is_quote(&h1)
-                                    && is_quote(&h2)
-                                    && is_quote(&h3)
-                                    && h1 == h2
-                                    && h2 == h3
-                                {
-                                    self.consume(5);
-                                    // TODO: extract_heredoc_with_interpolation...
-                                    return None;
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-            self.chars.reset_peek();
-
-            // tokenize([$~, S, H | T] = Original, Line, Column, Scope, Tokens) when ?is_sigil(H), ?is_upcase(S) orelse ?is_downcase(S) ->
-            if self.match_char('~') {
-                if let Some(&s) = &self.chars.peek() {
-                    if let Some(&h) = &self.chars.peek() {
-                        if s.is_ascii_alphabetic() && is_sigil(&h) {
-                            self.consume(3);
-                            // TODO: elixir_interpolation:extract
-                            return None;
-                        }
-                    }
-                }
-            }
-            self.chars.reset_peek();
-
-            // tokenize([$~, S, H | _] = Original, Line, Column, _Scope, Tokens) when ?is_upcase(S) orelse ?is_downcase(S) ->
-            if self.match_char('~') {
-                if let Some(&s) = &self.chars.peek() {
-                    if let Some(&_h) = &self.chars.peek() {
-                        if s.is_ascii_alphabetic() {
-                            self.consume(3);
-                            return Some(Err(LexicalError::InvalidSigil));
-                        }
+            let start = self.pos;
+            let tok = match *self.rest().first()? {
+                // tokenize([$# | String], Line, Column, Scope, Tokens) ->
+                b' ' | b'\t' | b'\r' | b'\n' | b'#' => {
+                    let trivia = self.lex_trivia(true)?;
+                    if let Some(skipped) = &mut self.trivia {
+                        skipped.push(trivia);
                     }
+                    continue;
                 }
-            }
-            self.chars.reset_peek();
 
-            // Char tokens
-            // tokenize([$?, $\\, H | T], Line, Column, Scope, Tokens) ->
-            if self.match_char('?') && self.match_char('\\') {
-                if let Some(&_h) = self.chars.peek() {
-                    // TODO: elixir_interpolation:unescape_map(H)
-                    // return Tok::Char..
-                    return None;
+                // VC Merge Conflict
+                // tokenize(("<<<<<<<" ++ _) = Original, Line, 1, _Scope, Tokens) ->
+                b'<' if self.col == 0 && self.rest().starts_with(b"<<<<<<<") => {
+                    self.skip_line();
+                    return Some(Err(LexicalError::VersionControlMarker {
+                        start,
+                        end: self.pos,
+                    }));
                 }
-            }
-            self.chars.reset_peek();
 
-            // tokenize([$?, Char | T], Line, Column, Scope, Tokens) ->
-            if self.match_char('?') {
-                if let Some(&ch) = self.chars.peek() {
-                    return Some(Ok((1, Tok::Char(ch), 1)));
+                b'0' => self.lex_base_int()?,
+                b'~' => return self.lex_sigil(),
+                b'?' => self.lex_char()?,
+                b'.' | b'<' | b'%' | b'{' => self.lex_kw_identifier()?,
+                b':' => self.lex_operator_atom()?,
+
+                // flag for testing
+                b'*' => {
+                    self.bump(1);
+                    Tok::Star
                 }
-            }
-            self.chars.reset_peek();
 
-            // Heredocs
+                // Heredocs, strings, identifiers and the rest are not lexed
+                // yet.
+                // TODO: handle_heredocs(T, Line, Column, $", Scope, Tokens);
+                // TODO: handle_strings(T, Line, Column + 1, $", Scope, Tokens);
+                _ => return None,
+            };
+            return Some(Ok((start, tok, self.pos)));
+        }
+    }
+}
 
-            // tokenize("\"\"\"" ++ T, Line, Column, Scope, Tokens) ->
-            if self.match_char('"') && self.match_char('"') && self.match_char('"') {
-                self.consume(3);
-                // TODO: andle_heredocs(T, Line, Column, $", Scope, Tokens);
-                return None;
+/// Everything lexing a whole input gives.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Lexed {
+    pub tokens: Vec<(usize, Tok, usize)>,
+    pub errors: Vec<LexicalError>,
+    /// The byte offset where the lexer stopped on input it does not handle
+    /// yet.
+    pub stopped: Option<usize>,
+}
+
+impl Lexed {
+    pub fn new(input: &str) -> Self {
+        let mut lexer = Lexer::new(input);
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        for item in &mut lexer {
+            match item {
+                Ok(token) => tokens.push(token),
+                Err(err) => errors.push(err),
             }
-            self.chars.reset_peek();
+        }
+        let stopped = if lexer.at_end() {
+            None
+        } else {
+            Some(lexer.offset())
+        };
+        Lexed {
+            tokens,
+            errors,
+            stopped,
+        }
+    }
 
-            // tokenize("'''" ++ T, Line, Column, Scope, Tokens) ->
-            if self.match_char('\'') && self.match_char('\'') && self.match_char('\'') {
-                self.consume(3);
-                // TODO: handle_heredocs(T, Line, Column, $', Scope, Tokens);
-            }
-            self.chars.reset_peek();
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty() && self.stopped.is_none()
+    }
+}
 
-            // Strings
+/// A lexer that attaches whitespace and comments to the tokens around them,
+/// created by `Lexer::with_trivia`. Joining each token's leading trivia, its
+/// text and its trailing trivia, then `remaining_trivia`, gives back the
+/// input the lexer consumed. Errors carry no trivia: the trivia before an
+/// error leads the next token, and the error's span covers its own text.
+pub struct TriviaLexer<'input> {
+    lexer: Lexer<'input>,
+}
 
-            // tokenize([$" | T], Line, Column, Scope, Tokens) ->
-            if self.match_char('"') {
-                self.consume(1);
-                // TODO: handle_strings(T, Line, Column + 1, $", Scope, Tokens);
-            }
-            self.chars.reset_peek();
+impl<'input> TriviaLexer<'input> {
+    /// The trivia after the last token, once the lexer is exhausted.
+    pub fn remaining_trivia(&mut self) -> Vec<Trivia> {
+        self.lexer
+            .trivia
+            .as_mut()
+            .map_or(Vec::new(), ::std::mem::take)
+    }
 
-            // tokenize([$' | T], Line, Column, Scope, Tokens) ->
-            if self.match_char('\'') {
-                self.consume(1);
-                // TODO: handle_strings(T, Line, Column + 1, $', Scope, Tokens);
-            }
-            self.chars.reset_peek();
+    pub fn offset(&self) -> usize {
+        self.lexer.offset()
+    }
 
-            // Operator atoms
+    pub fn checkpoint(&self) -> Checkpoint {
+        self.lexer.checkpoint()
+    }
 
-            // tokenize("...:" ++ Rest, Line, Column, Scope, Tokens) when ?is_space(hd(Rest)) ->
-            if self.match_char('.')
-                && self.match_char('.')
-                && self.match_char('.')
-                && self.match_char(':')
-                && self.match_fn(&|c| is_space(c))
-            {
-                self.consume(4);
-                return Some(Ok((1, Tok::KwIdentifier("...".to_string()), 1)));
-            }
-            self.chars.reset_peek();
-
-            // tokenize("<<>>:" ++ Rest, Line, Column, Scope, Tokens) when ?is_space(hd(Rest)) ->
-            if self.match_char('<')
-                && self.match_char('<')
-                && self.match_char('>')
-                && self.match_char('>')
-                && self.match_char(':')
-                && self.match_fn(&|c| is_space(c))
-            {
-                self.consume(5);
-                return Some(Ok((1, Tok::KwIdentifier("<<>>".to_string()), 1)));
-            }
-            self.chars.reset_peek();
-
-            // tokenize("%{}:" ++ Rest, Line, Column, Scope, Tokens) when ?is_space(hd(Rest)) ->
-            if self.match_char('%')
-                && self.match_char('{')
-                && self.match_char('}')
-                && self.match_char(':')
-                && self.match_fn(&|c| is_space(c))
-            {
-                self.consume(4);
-                return Some(Ok((1, Tok::KwIdentifier("%{}".to_string()), 1)));
-            }
-            self.chars.reset_peek();
+    pub fn at_end(&self) -> bool {
+        self.lexer.at_end()
+    }
+}
 
-            // tokenize("%:" ++ Rest, Line, Column, Scope, Tokens) when ?is_space(hd(Rest)) ->
-            if self.match_char('%') && self.match_char(':') && self.match_fn(&|c| is_space(c)) {
-                self.consume(2);
-                return Some(Ok((1, Tok::KwIdentifier("%".to_string()), 1)));
-            }
-            self.chars.reset_peek();
+impl<'input> Iterator for TriviaLexer<'input> {
+    type Item = Result<TriviaToken, LexicalError>;
 
-            // tokenize("{}:" ++ Rest, Line, Column, Scope, Tokens) when ?is_space(hd(Rest)) ->
-            if self.match_char('{')
-                && self.match_char('}')
-                && self.match_char(':')
-                && self.match_fn(&|c| is_space(c))
-            {
-                self.consume(3);
-                return Some(Ok((1, Tok::KwIdentifier("{}".to_string()), 1)));
-            }
-            self.chars.reset_peek();
+    fn next(&mut self) -> Option<Self::Item> {
+        let (start, tok, end) = match self.lexer.next()? {
+            Ok(token) => token,
+            Err(err) => return Some(Err(err)),
+        };
+        let leading = self.remaining_trivia();
+        let mut trailing = Vec::new();
+        while let Some(trivia) = self.lexer.lex_trivia(false) {
+            trailing.push(trivia);
+        }
+        Some(Ok(TriviaToken {
+            leading,
+            start,
+            tok,
+            end,
+            trailing,
+        }))
+    }
+}
 
-            // tokenize(":..." ++ Rest, Line, Column, Scope, Tokens) ->
-            if self.match_char(':')
-                && self.match_char('.')
-                && self.match_char('.')
-                && self.match_char('.')
-            {
-                self.consume(4);
-                return Some(Ok((1, Tok::Atom("...".to_string()), 1)));
-            }
-            self.chars.reset_peek();
-
-            // tokenize(":<<>>" ++ Rest, Line, Column, Scope, Tokens) ->
-            if self.match_char(':')
-                && self.match_char('<')
-                && self.match_char('<')
-                && self.match_char('>')
-                && self.match_char('>')
-            {
-                self.consume(5);
-                return Some(Ok((1, Tok::Atom("<<>>".to_string()), 1)));
+/// The comments of `input`, up to where the lexer stops.
+pub fn comments(input: &str) -> Vec<Comment> {
+    // Each token is `None`, so comments can count the newlines around them.
+    let mut pieces: Vec<Option<Trivia>> = Vec::new();
+    let mut lexer = Lexer::with_trivia(input);
+    for token in lexer.by_ref().filter_map(Result::ok) {
+        pieces.extend(token.leading.into_iter().map(Some));
+        pieces.push(None);
+        pieces.extend(token.trailing.into_iter().map(Some));
+    }
+    pieces.extend(lexer.remaining_trivia().into_iter().map(Some));
+
+    let mut comments = Vec::new();
+    let (mut line, mut column, mut offset) = (1, 1, 0);
+    for (i, piece) in pieces.iter().enumerate() {
+        let trivia = match piece {
+            Some(trivia) if trivia.kind == TriviaKind::Comment => trivia,
+            _ => continue,
+        };
+        for c in input[offset..trivia.start].chars() {
+            if c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
             }
-            self.chars.reset_peek();
+        }
+        offset = trivia.start;
+
+        let before = pieces[..i]
+            .iter()
+            .rev()
+            .take_while(|piece| piece.is_some())
+            .collect::<Vec<_>>();
+        let previous_eol_count = if before.len() == i {
+            1
+        } else {
+            before
+                .iter()
+                .filter(|piece| {
+                    matches!(
+                        piece,
+                        Some(Trivia {
+                            kind: TriviaKind::Newline,
+                            ..
+                        })
+                    )
+                })
+                .count()
+        };
+        let next_eol_count = pieces[i + 1..]
+            .iter()
+            .take_while(|piece| match piece {
+                Some(trivia) => trivia.kind != TriviaKind::Comment,
+                None => false,
+            })
+            .filter(|piece| {
+                matches!(
+                    piece,
+                    Some(Trivia {
+                        kind: TriviaKind::Newline,
+                        ..
+                    })
+                )
+            })
+            .count();
+        comments.push(Comment {
+            line,
+            column,
+            previous_eol_count,
+            next_eol_count,
+            text: trivia.text(input).to_string(),
+        });
+    }
+    comments
+}
 
-            // tokenize(":%{}" ++ Rest, Line, Column, Scope, Tokens) ->
-            if self.match_char(':')
-                && self.match_char('%')
-                && self.match_char('{')
-                && self.match_char('}')
-            {
-                self.consume(4);
-                return Some(Ok((1, Tok::Atom("%{}".to_string()), 1)));
-            }
-            self.chars.reset_peek();
+impl<'input> Lexer<'input> {
+    fn rest(&self) -> &'input [u8] {
+        &self.bytes[self.pos..]
+    }
 
-            // tokenize(":%" ++ Rest, Line, Column, Scope, Tokens) ->
-            if self.match_char(':') && self.match_char('%') {
-                self.consume(2);
-                return Some(Ok((1, Tok::Atom("%".to_string()), 1)));
-            }
-            self.chars.reset_peek();
+    fn peek(&self, n: usize) -> Option<u8> {
+        self.bytes.get(self.pos + n).cloned()
+    }
 
-            // tokenize(":{}" ++ Rest, Line, Column, Scope, Tokens) ->
-            if self.match_char(':') && self.match_char('{') && self.match_char('}') {
-                self.consume(3);
-                return Some(Ok((1, Tok::Atom("{}".to_string()), 1)));
-            }
-            self.chars.reset_peek();
-
-            // Three Token Operators
-
-            // tokenize([$:, T1, T2, T3 | Rest], Line, Column, Scope, Tokens) when
-            // ?unary_op3(T1, T2, T3); ?comp_op3(T1, T2, T3); ?and_op3(T1, T2, T3); ?or_op3(T1, T2, T3);
-            // ?arrow_op3(T1, T2, T3); ?three_op(T1, T2, T3) ->
-            if self.match_char(':') {
-                if let Some(&t1) = self.chars.peek() {
-                    if let Some(&t2) = self.chars.peek() {
-                        if let Some(&t3) = self.chars.peek() {
-                            if is_unary_op3(&t1, &t2, &t3)
-                                || is_comp_op3(&t1, &t2, &t3)
-                                || is_and_op3(&t1, &t2, &t3)
-                                || is_or_op3(&t1, &t2, &t3)
-                                || is_arrow_op3(&t1, &t2, &t3)
-                                || is_three_op(&t1, &t2, &t3)
-                            {
-                                self.consume(4);
-                                return Some(Ok((
-                                    (self.col - 4) as usize,
-                                    Tok::Atom([t1, t2, t3].into_iter().collect()),
-                                    self.col as usize,
-                                )));
-                            }
-                        }
-                    }
-                }
-            }
-            self.chars.reset_peek();
-
-            // Two Token Operators
-            // tokenize([$:, T1, T2 | Rest], Line, Column, Scope, Tokens) when
-            //     ?comp_op2(T1, T2); ?rel_op2(T1, T2); ?and_op(T1, T2); ?or_op(T1, T2);
-            //     ?arrow_op(T1, T2); ?in_match_op(T1, T2); ?two_op(T1, T2); ?list_op(T1, T2);
-            // ?stab_op(T1, T2); ?type_op(T1, T2) ->
-            if self.match_char(':') {
-                if let Some(&t1) = self.chars.peek() {
-                    if let Some(&t2) = self.chars.peek() {
-                        if is_comp_op2(&t1, &t2)
-                            || is_rel_op2(&t1, &t2)
-                            || is_and_op(&t1, &t2)
-                            || is_or_op(&t1, &t2)
-                            || is_arrow_op(&t1, &t2)
-                            || is_in_match_op(&t1, &t2)
-                            || is_two_op(&t1, &t2)
-                            || is_list_op(&t1, &t2)
-                            || is_stab_op(&t1, &t2)
-                            || is_type_op(&t1, &t2)
-                        {
-                            self.consume(3);
-                            return Some(Ok((
-                                (self.col - 3) as usize,
-                                Tok::Atom([t1, t2].into_iter().collect()),
-                                self.col as usize,
-                            )));
-                        }
-                    }
-                }
-            }
-            self.chars.reset_peek();
-
-            //  ## Single Token Operators
-            // tokenize([$:, T | Rest], Line, Column, Scope, Tokens) when
-            //     ?at_op(T); ?unary_op(T); ?capture_op(T); ?dual_op(T); ?mult_op(T);
-            // ?rel_op(T); ?match_op(T); ?pipe_op(T); T == $. ->
-            if self.match_char(':') {
-                if let Some(&t) = self.chars.peek() {
-                    if is_at_op(&t)
-                        || is_unary_op(&t)
-                        || is_capture_op(&t)
-                        || is_dual_op(&t)
-                        || is_mult_op(&t)
-                        || is_rel_op(&t)
-                        || is_match_op(&t)
-                        || is_pipe_op(&t)
-                        || &t == &'.'
-                    {
-                        self.consume(2);
-                        return Some(Ok((
-                            (self.col - 2) as usize,
-                            Tok::Atom([t].into_iter().collect()),
-                            self.col as usize,
-                        )));
-                    }
-                }
-            }
-            self.chars.reset_peek();
+    /// Moves past `n` bytes that do not include a newline or a multibyte
+    /// character.
+    fn bump(&mut self, n: usize) {
+        self.pos += n;
+        self.col += n as u32;
+    }
 
-            // flag for testing
-            if self.match_char('*') {
-                self.consume(1);
-                return Some(Ok((1, Tok::Star, 1)));
+    /// Moves past the character at the current position.
+    fn bump_char(&mut self) -> char {
+        let c = self.input[self.pos..].chars().next().unwrap();
+        self.pos += c.len_utf8();
+        self.col += 1;
+        c
+    }
+
+    /// Moves past the rest of the line, including the newline.
+    fn skip_line(&mut self) {
+        match self.rest().iter().position(|&b| b == b'\n') {
+            Some(len) => {
+                self.pos += len + 1;
+                self.line += 1;
+                self.col = 0;
+            }
+            None => {
+                self.col += char_count(self.rest());
+                self.pos = self.bytes.len();
             }
-            self.chars.reset_peek();
+        }
+    }
 
+    // tokenize([$0, $x, H | T], Line, Column, Scope, Tokens) when ?is_hex(H) ->
+    // tokenize([$0, $b, H | T], Line, Column, Scope, Tokens) when ?is_bin(H) ->
+    // tokenize([$0, $o, H | T], Line, Column, Scope, Tokens) when ?is_octal(H) ->
+    fn lex_base_int(&mut self) -> Option<Tok> {
+        let radix = match self.peek(1)? {
+            b'x' => 16,
+            b'b' => 2,
+            b'o' => 8,
+            _ => return None,
+        };
+        let len = self.rest()[2..]
+            .iter()
+            .take_while(|&&b| (b as char).is_digit(radix))
+            .count();
+        if len == 0 {
             return None;
         }
+        self.bump(2);
+        let digits = &self.rest()[..len];
+        self.bump(len);
+        Some(Tok::Int(BigInt::parse_bytes(digits, radix).unwrap()))
     }
-}
 
-impl<'input> Lexer<'input> {
-    fn consume(&mut self, n: u32) {
-        for _ in 0..n {
-            self.chars.next();
+    fn lex_sigil(&mut self) -> Option<Spanned<Tok, usize, LexicalError>> {
+        let start = self.pos;
+        let rest = self.rest();
+        if !rest.get(1)?.is_ascii_alphabetic() {
+            return None;
+        }
+        let h = *rest.get(2)?;
+
+        // tokenize([$~, S, H, H, H | T] = Original, Line, Column, Scope, Tokens) when ?is_quote(H), ?is_upcase(S) orelse ?is_downcase(S) ->
+        if is_quote(h) && rest.get(3) == Some(&h) && rest.get(4) == Some(&h) {
+            // TODO: extract_heredoc_with_interpolation...
+            return None;
         }
-        self.col += n;
-        self.chars.reset_peek();
+
+        // tokenize([$~, S, H | T] = Original, Line, Column, Scope, Tokens) when ?is_sigil(H), ?is_upcase(S) orelse ?is_downcase(S) ->
+        if is_sigil(h) {
+            // TODO: elixir_interpolation:extract
+            return None;
+        }
+
+        // tokenize([$~, S, H | _] = Original, Line, Column, _Scope, Tokens) when ?is_upcase(S) orelse ?is_downcase(S) ->
+        self.bump(2);
+        self.bump_char();
+        Some(Err(LexicalError::InvalidSigil {
+            start,
+            end: self.pos,
+        }))
     }
 
-    fn consume_to_eol(&mut self) {
-        loop {
-            match &self.chars.next() {
-                Some('\n') => {
-                    self.col = 0;
-                    self.line += 1;
-                    break;
-                }
-                None => {
-                    self.col += 1;
-                    break;
-                }
-                _ => {
-                    self.col += 1;
-                    continue;
-                }
+    fn lex_char(&mut self) -> Option<Tok> {
+        match self.peek(1)? {
+            // tokenize([$?, $\\, H | T], Line, Column, Scope, Tokens) ->
+            // TODO: elixir_interpolation:unescape_map(H)
+            b'\\' if self.peek(2).is_some() => None,
+
+            // tokenize([$?, Char | T], Line, Column, Scope, Tokens) ->
+            _ => {
+                self.bump(1);
+                Some(Tok::Char(self.bump_char()))
             }
         }
     }
 
-    fn match_char(&mut self, c: char) -> bool {
-        self.chars.peek() == Some(&c)
+    // tokenize("...:" ++ Rest, Line, Column, Scope, Tokens) when ?is_space(hd(Rest)) ->
+    // tokenize("<<>>:" ++ Rest, Line, Column, Scope, Tokens) when ?is_space(hd(Rest)) ->
+    // tokenize("%{}:" ++ Rest, Line, Column, Scope, Tokens) when ?is_space(hd(Rest)) ->
+    // tokenize("%:" ++ Rest, Line, Column, Scope, Tokens) when ?is_space(hd(Rest)) ->
+    // tokenize("{}:" ++ Rest, Line, Column, Scope, Tokens) when ?is_space(hd(Rest)) ->
+    fn lex_kw_identifier(&mut self) -> Option<Tok> {
+        let rest = self.rest();
+        let op = SPECIAL_OPERATORS.iter().find(|op| {
+            rest.starts_with(op.as_bytes())
+                && rest.get(op.len()) == Some(&b':')
+                && rest.get(op.len() + 1).is_some_and(|&b| is_space(b))
+        })?;
+        let len = op.len();
+        let name = &self.input[self.pos..self.pos + len];
+        self.bump(len + 1);
+        Some(Tok::KwIdentifier(Symbol::intern(name)))
     }
 
-    fn match_fn(&mut self, f: &Fn(&char) -> bool) -> bool {
-        self.chars.peek().map_or(false, |c| f(c))
+    // tokenize(":..." ++ Rest, Line, Column, Scope, Tokens) ->
+    // tokenize(":<<>>" ++ Rest, Line, Column, Scope, Tokens) ->
+    // tokenize(":%{}" ++ Rest, Line, Column, Scope, Tokens) ->
+    // tokenize(":%" ++ Rest, Line, Column, Scope, Tokens) ->
+    // tokenize(":{}" ++ Rest, Line, Column, Scope, Tokens) ->
+    //
+    // tokenize([$:, T1, T2, T3 | Rest], Line, Column, Scope, Tokens) when
+    // ?unary_op3(T1, T2, T3); ?comp_op3(T1, T2, T3); ?and_op3(T1, T2, T3); ?or_op3(T1, T2, T3);
+    // ?arrow_op3(T1, T2, T3); ?three_op(T1, T2, T3) ->
+    //
+    // tokenize([$:, T1, T2 | Rest], Line, Column, Scope, Tokens) when
+    //     ?comp_op2(T1, T2); ?rel_op2(T1, T2); ?and_op(T1, T2); ?or_op(T1, T2);
+    //     ?arrow_op(T1, T2); ?in_match_op(T1, T2); ?two_op(T1, T2); ?list_op(T1, T2);
+    // ?stab_op(T1, T2); ?type_op(T1, T2) ->
+    //
+    // tokenize([$:, T | Rest], Line, Column, Scope, Tokens) when
+    //     ?at_op(T); ?unary_op(T); ?capture_op(T); ?dual_op(T); ?mult_op(T);
+    // ?rel_op(T); ?match_op(T); ?pipe_op(T); T == $. ->
+    fn lex_operator_atom(&mut self) -> Option<Tok> {
+        let rest = &self.rest()[1..];
+        let special = SPECIAL_OPERATORS
+            .iter()
+            .find(|op| rest.starts_with(op.as_bytes()));
+        let len = match (special, rest) {
+            (Some(op), _) => op.len(),
+            (None, &[t1, t2, t3, ..])
+                if is_unary_op3(t1, t2, t3)
+                    || is_comp_op3(t1, t2, t3)
+                    || is_and_op3(t1, t2, t3)
+                    || is_or_op3(t1, t2, t3)
+                    || is_arrow_op3(t1, t2, t3)
+                    || is_three_op(t1, t2, t3) =>
+            {
+                3
+            }
+            (None, &[t1, t2, ..])
+                if is_comp_op2(t1, t2)
+                    || is_rel_op2(t1, t2)
+                    || is_and_op(t1, t2)
+                    || is_or_op(t1, t2)
+                    || is_arrow_op(t1, t2)
+                    || is_in_match_op(t1, t2)
+                    || is_two_op(t1, t2)
+                    || is_list_op(t1, t2)
+                    || is_stab_op(t1, t2)
+                    || is_type_op(t1, t2) =>
+            {
+                2
+            }
+            (None, &[t, ..])
+                if is_at_op(t)
+                    || is_unary_op(t)
+                    || is_capture_op(t)
+                    || is_dual_op(t)
+                    || is_mult_op(t)
+                    || is_rel_op(t)
+                    || is_match_op(t)
+                    || is_pipe_op(t)
+                    || t == b'.' =>
+            {
+                1
+            }
+            _ => return None,
+        };
+        let name = &self.input[self.pos + 1..self.pos + 1 + len];
+        self.bump(len + 1);
+        Some(Tok::Atom(Symbol::intern(name)))
+    }
+
+    /// Lexes a run of whitespace, a newline or a comment. Newlines are left
+    /// alone unless `newlines` is set.
+    fn lex_trivia(&mut self, newlines: bool) -> Option<Trivia> {
+        let start = self.pos;
+        let kind = match self.peek(0)? {
+            b'#' => {
+                let rest = self.rest();
+                let mut len = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+                if len < rest.len() && rest[len - 1] == b'\r' {
+                    len -= 1;
+                }
+                self.col += char_count(&rest[..len]);
+                self.pos += len;
+                TriviaKind::Comment
+            }
+            b' ' | b'\t' => {
+                let len = self
+                    .rest()
+                    .iter()
+                    .take_while(|&&b| is_horizontal_space(b))
+                    .count();
+                self.bump(len);
+                TriviaKind::Whitespace
+            }
+            b'\n' if newlines => {
+                self.pos += 1;
+                self.line += 1;
+                self.col = 0;
+                TriviaKind::Newline
+            }
+            b'\r' if newlines && self.peek(1) == Some(b'\n') => {
+                self.pos += 2;
+                self.line += 1;
+                self.col = 0;
+                TriviaKind::Newline
+            }
+            _ => return None,
+        };
+        Some(Trivia {
+            kind,
+            start,
+            end: self.pos,
+        })
     }
 }
 
-fn is_quote(c: &char) -> bool {
-    c == &'\'' || c == &'"'
+/// The number of characters in UTF-8 `bytes`, counting the bytes that do
+/// not continue a character.
+fn char_count(bytes: &[u8]) -> u32 {
+    bytes.iter().filter(|&&b| b & 0xC0 != 0x80).count() as u32
 }
 
-fn is_sigil(c: &char) -> bool {
-    c == &'/'
-        || c == &'<'
-        || c == &'"'
-        || c == &'\''
-        || c == &'['
-        || c == &'('
-        || c == &'{'
-        || c == &'|'
+fn is_quote(c: u8) -> bool {
+    c == b'\'' || c == b'"'
 }
 
-fn is_horizontal_space(s: &char) -> bool {
-    s == &' ' || s == &'\t'
+fn is_sigil(c: u8) -> bool {
+    c == b'/'
+        || c == b'<'
+        || c == b'"'
+        || c == b'\''
+        || c == b'['
+        || c == b'('
+        || c == b'{'
+        || c == b'|'
 }
 
-fn is_vertical_space(s: &char) -> bool {
-    s == &'\r' || s == &'\n'
+fn is_horizontal_space(s: u8) -> bool {
+    s == b' ' || s == b'\t'
 }
 
-fn is_space(s: &char) -> bool {
+fn is_vertical_space(s: u8) -> bool {
+    s == b'\r' || s == b'\n'
+}
+
+fn is_space(s: u8) -> bool {
     is_horizontal_space(s) || is_vertical_space(s)
 }
 
-fn is_comp_op2(t1: &char, t2: &char) -> bool {
-    (t1 == &'=' && t2 == &'=') || (t1 == &'=' && t2 == &'~') || (t1 == &'!' && t2 == &'=')
+fn is_comp_op2(t1: u8, t2: u8) -> bool {
+    matches!((t1, t2), (b'=', b'=') | (b'=', b'~') | (b'!', b'='))
 }
 
-fn is_rel_op2(t1: &char, t2: &char) -> bool {
-    (t1 == &'<' && t2 == &'=') || (t1 == &'>' && t2 == &'~')
+fn is_rel_op2(t1: u8, t2: u8) -> bool {
+    matches!((t1, t2), (b'<', b'=') | (b'>', b'='))
 }
 
-fn is_and_op(t1: &char, t2: &char) -> bool {
-    (t1 == &'&' && t2 == &'&')
+fn is_and_op(t1: u8, t2: u8) -> bool {
+    t1 == b'&' && t2 == b'&'
 }
 
-fn is_or_op(t1: &char, t2: &char) -> bool {
-    (t1 == &'|' && t2 == &'|')
+fn is_or_op(t1: u8, t2: u8) -> bool {
+    t1 == b'|' && t2 == b'|'
 }
 
-fn is_arrow_op(t1: &char, t2: &char) -> bool {
-    (t1 == &'|' && t2 == &'>') || (t1 == &'~' && t2 == &'>') || (t1 == &'<' && t2 == &'~')
+fn is_arrow_op(t1: u8, t2: u8) -> bool {
+    matches!((t1, t2), (b'|', b'>') | (b'~', b'>') | (b'<', b'~'))
 }
 
-fn is_in_match_op(t1: &char, t2: &char) -> bool {
-    (t1 == &'<' && t2 == &'-') || (t1 == &'\\' && t2 == &'\\')
+fn is_in_match_op(t1: u8, t2: u8) -> bool {
+    (t1 == b'<' && t2 == b'-') || (t1 == b'\\' && t2 == b'\\')
 }
 
-fn is_two_op(t1: &char, t2: &char) -> bool {
-    (t1 == &'<' && t2 == &'>') || (t1 == &'.' && t2 == &'.')
+fn is_two_op(t1: u8, t2: u8) -> bool {
+    (t1 == b'<' && t2 == b'>') || (t1 == b'.' && t2 == b'.')
 }
 
-fn is_list_op(t1: &char, t2: &char) -> bool {
-    (t1 == &'+' && t2 == &'+') || (t1 == &'-' && t2 == &'-')
+fn is_list_op(t1: u8, t2: u8) -> bool {
+    (t1 == b'+' && t2 == b'+') || (t1 == b'-' && t2 == b'-')
 }
 
-fn is_stab_op(t1: &char, t2: &char) -> bool {
-    (t1 == &'-' && t2 == &'>')
+fn is_stab_op(t1: u8, t2: u8) -> bool {
+    t1 == b'-' && t2 == b'>'
 }
 
-fn is_type_op(t1: &char, t2: &char) -> bool {
-    (t1 == &':' && t2 == &':')
+fn is_type_op(t1: u8, t2: u8) -> bool {
+    t1 == b':' && t2 == b':'
 }
 
-fn is_unary_op3(t1: &char, t2: &char, t3: &char) -> bool {
-    t1 == &'~' && t2 == &'~' && t3 == &'~'
+fn is_unary_op3(t1: u8, t2: u8, t3: u8) -> bool {
+    t1 == b'~' && t2 == b'~' && t3 == b'~'
 }
 
-fn is_comp_op3(t1: &char, t2: &char, t3: &char) -> bool {
-    (t1 == &'=' && t2 == &'=' && t3 == &'=') || (t1 == &'!' && t2 == &'=' && t3 == &'=')
+fn is_comp_op3(t1: u8, t2: u8, t3: u8) -> bool {
+    matches!((t1, t2, t3), (b'=', b'=', b'=') | (b'!', b'=', b'='))
 }
 
-fn is_and_op3(t1: &char, t2: &char, t3: &char) -> bool {
-    t1 == &'&' && t2 == &'&' && t3 == &'&'
+fn is_and_op3(t1: u8, t2: u8, t3: u8) -> bool {
+    t1 == b'&' && t2 == b'&' && t3 == b'&'
 }
 
-fn is_or_op3(t1: &char, t2: &char, t3: &char) -> bool {
-    t1 == &'|' && t2 == &'|' && t3 == &'|'
+fn is_or_op3(t1: u8, t2: u8, t3: u8) -> bool {
+    t1 == b'|' && t2 == b'|' && t3 == b'|'
 }
 
-fn is_arrow_op3(t1: &char, t2: &char, t3: &char) -> bool {
-    (t1 == &'<' && t2 == &'<' && t3 == &'<')
-        || (t1 == &'>' && t2 == &'>' && t3 == &'>')
-        || (t1 == &'~' && t2 == &'>' && t3 == &'>')
-        || (t1 == &'<' && t2 == &'<' && t3 == &'~')
-        || (t1 == &'<' && t2 == &'~' && t3 == &'>')
-        || (t1 == &'<' && t2 == &'|' && t3 == &'>')
+fn is_arrow_op3(t1: u8, t2: u8, t3: u8) -> bool {
+    (t1 == b'<' && t2 == b'<' && t3 == b'<')
+        || (t1 == b'>' && t2 == b'>' && t3 == b'>')
+        || (t1 == b'~' && t2 == b'>' && t3 == b'>')
+        || (t1 == b'<' && t2 == b'<' && t3 == b'~')
+        || (t1 == b'<' && t2 == b'~' && t3 == b'>')
+        || (t1 == b'<' && t2 == b'|' && t3 == b'>')
 }
 
-fn is_three_op(t1: &char, t2: &char, t3: &char) -> bool {
-    t1 == &'^' && t2 == &'^' && t3 == &'^'
+fn is_three_op(t1: u8, t2: u8, t3: u8) -> bool {
+    t1 == b'^' && t2 == b'^' && t3 == b'^'
 }
 
-fn is_at_op(t: &char) -> bool {
-    t == &'@'
+fn is_at_op(t: u8) -> bool {
+    t == b'@'
 }
 
-fn is_unary_op(t: &char) -> bool {
-    t == &'!' || t == &'^'
+fn is_unary_op(t: u8) -> bool {
+    t == b'!' || t == b'^'
 }
 
-fn is_capture_op(t: &char) -> bool {
-    t == &'&'
+fn is_capture_op(t: u8) -> bool {
+    t == b'&'
 }
 
-fn is_dual_op(t: &char) -> bool {
-    t == &'+' || t == &'-'
+fn is_dual_op(t: u8) -> bool {
+    t == b'+' || t == b'-'
 }
 
-fn is_mult_op(t: &char) -> bool {
-    t == &'*' || t == &'/'
+fn is_mult_op(t: u8) -> bool {
+    t == b'*' || t == b'/'
 }
 
-fn is_rel_op(t: &char) -> bool {
-    t == &'<' || t == &'>'
+fn is_rel_op(t: u8) -> bool {
+    t == b'<' || t == b'>'
 }
 
-fn is_match_op(t: &char) -> bool {
-    t == &'='
+fn is_match_op(t: u8) -> bool {
+    t == b'='
 }
 
-fn is_pipe_op(t: &char) -> bool {
-    t == &'|'
+fn is_pipe_op(t: u8) -> bool {
+    t == b'|'
 }
 
 #[test]
 fn lex1() {
     let mut lexer = Lexer::new("<<<<<<< VC conflict\n*");
-    assert!(lexer.next() == Some(Err(LexicalError::VersionControlMarker)));
-    assert!(lexer.next() == Some(Ok((1, Tok::Star, 1))));
-    assert!(lexer.next() == None);
+    assert!(
+        lexer.next()
+            == Some(Err(LexicalError::VersionControlMarker {
+                start: 0,
+                end: 20
+            }))
+    );
+    assert!(lexer.next() == Some(Ok((20, Tok::Star, 21))));
+    assert!(lexer.next().is_none());
 }
 
 #[test]
 fn lex2() {
     let mut lexer = Lexer::new("0xf1A*0b110*0o73*");
-    assert!(lexer.next() == Some(Ok((1, Tok::Int(0xf1a.to_bigint().unwrap()), 1))));
-    assert!(lexer.next() == Some(Ok((1, Tok::Star, 1))));
-    assert!(lexer.next() == Some(Ok((1, Tok::Int(0b110.to_bigint().unwrap()), 1))));
-    assert!(lexer.next() == Some(Ok((1, Tok::Star, 1))));
-    assert!(lexer.next() == Some(Ok((1, Tok::Int(0o73.to_bigint().unwrap()), 1))));
-    assert!(lexer.next() == Some(Ok((1, Tok::Star, 1))));
+    assert!(lexer.next() == Some(Ok((0, Tok::Int(0xf1a.to_bigint().unwrap()), 5))));
+    assert!(lexer.next() == Some(Ok((5, Tok::Star, 6))));
+    assert!(lexer.next() == Some(Ok((6, Tok::Int(0b110.to_bigint().unwrap()), 11))));
+    assert!(lexer.next() == Some(Ok((11, Tok::Star, 12))));
+    assert!(lexer.next() == Some(Ok((12, Tok::Int(0o73.to_bigint().unwrap()), 16))));
+    assert!(lexer.next() == Some(Ok((16, Tok::Star, 17))));
 }
 
 #[test]
 fn lex3() {
     let mut lexer = Lexer::new("# this is a comment\n*");
-    assert!(lexer.next() == Some(Ok((1, Tok::Star, 1))));
+    assert!(lexer.next() == Some(Ok((20, Tok::Star, 21))));
+}
+
+#[test]
+fn lex_char() {
+    let mut lexer = Lexer::new("?a?:");
+    assert!(lexer.next() == Some(Ok((0, Tok::Char('a'), 2))));
+    assert!(lexer.next() == Some(Ok((2, Tok::Char(':'), 4))));
+    assert!(lexer.next().is_none());
+
+    let mut lexer = Lexer::new("?é:+");
+    assert!(lexer.next() == Some(Ok((0, Tok::Char('é'), 3))));
+    assert!(lexer.next() == Some(Ok((3, Tok::Atom("+".into()), 5))));
+}
+
+#[test]
+fn at_end() {
+    let mut lexer = Lexer::new(":+ foo");
+    assert!(lexer.next() == Some(Ok((0, Tok::Atom("+".into()), 2))));
+    assert!(lexer.next().is_none());
+    assert!(!lexer.at_end());
+    assert!(lexer.offset() == 3);
+    assert!(lexer.location() == (0, 3));
+}
+
+#[test]
+fn whitespace() {
+    let mut lexer = Lexer::new(" :+\t\r\n\n  :- # done");
+    assert!(lexer.next() == Some(Ok((1, Tok::Atom("+".into()), 3))));
+    assert!(lexer.next() == Some(Ok((9, Tok::Atom("-".into()), 11))));
+    assert!(lexer.next().is_none());
+    assert!(lexer.at_end());
+    assert!(lexer.location() == (2, 11));
 }
 
 #[test]
-fn consume() {
-    let mut lexer = Lexer::new("123456");
-    assert!(lexer.chars.peek() == Some(&'1'));
-    lexer.chars.reset_peek();
-    lexer.consume(2);
-    assert!(lexer.chars.peek() == Some(&'3'));
-    lexer.consume(2);
-    assert!(lexer.chars.peek() == Some(&'5'));
+fn trivia() {
+    let input = "# head\n:+ # plus\n\n  :- ";
+    let mut lexer = Lexer::with_trivia(input);
+    let plus = lexer.next().unwrap().unwrap();
+    assert!(
+        plus.leading
+            == vec![
+                Trivia {
+                    kind: TriviaKind::Comment,
+                    start: 0,
+                    end: 6
+                },
+                Trivia {
+                    kind: TriviaKind::Newline,
+                    start: 6,
+                    end: 7
+                },
+            ]
+    );
+    assert!((plus.start, plus.end) == (7, 9));
+    assert!(
+        plus.trailing.iter().map(|t| t.kind).collect::<Vec<_>>()
+            == vec![TriviaKind::Whitespace, TriviaKind::Comment]
+    );
+    let minus = lexer.next().unwrap().unwrap();
+    assert!(
+        minus
+            .leading
+            .iter()
+            .map(|t| t.text(input))
+            .collect::<Vec<_>>()
+            == vec!["\n", "\n", "  "]
+    );
+    assert!(lexer.next().is_none());
+    assert!(lexer.remaining_trivia().is_empty());
+    assert!(
+        minus
+            .trailing
+            .iter()
+            .map(|t| t.text(input))
+            .collect::<String>()
+            == " "
+    );
 }
 
 #[test]
-fn match_char() {
-    let mut lexer = Lexer::new("abc");
-    assert!(lexer.match_char('a'));
-    assert!(lexer.match_char('b'));
+fn comment_positions() {
+    let comments = comments("# one\r\n\n:+ # two\n  # three");
+    let summary: Vec<_> = comments
+        .iter()
+        .map(|c| {
+            (
+                c.line,
+                c.column,
+                c.previous_eol_count,
+                c.next_eol_count,
+                c.text.as_str(),
+            )
+        })
+        .collect();
+    assert!(
+        summary
+            == vec![
+                (1, 1, 1, 2, "# one"),
+                (3, 4, 0, 1, "# two"),
+                (4, 3, 1, 0, "# three"),
+            ]
+    );
 }
 
 #[test]
-fn match_fn() {
-    let mut lexer = Lexer::new("abc");
-    assert!(lexer.match_fn(&|ch| ch == &'a'));
+fn interns_names() {
+    let tokens: Vec<_> = Lexer::new(":<<>> %{}: ").map(Result::unwrap).collect();
+    assert_eq!(
+        tokens,
+        vec![
+            (0, Tok::Atom(Symbol::intern("<<>>")), 5),
+            (6, Tok::KwIdentifier(Symbol::intern("%{}")), 10),
+        ]
+    );
+}
+
+#[test]
+fn bump() {
+    let mut lexer = Lexer::new("12é456");
+    assert!(lexer.peek(0) == Some(b'1'));
+    lexer.bump(2);
+    assert!(lexer.bump_char() == 'é');
+    assert!(lexer.peek(0) == Some(b'4'));
+    assert!(lexer.location() == (0, 3));
+    assert!(lexer.offset() == 4);
+    assert!(lexer.peek(3).is_none());
+}
+
+#[test]
+fn stops_on_unsupported_input() {
+    for input in &[
+        "~s(x)", "~s\"\"\"", "?\\n", "\"x\"", "'''", "0z", ":a", "\r:+",
+    ] {
+        let mut lexer = Lexer::new(input);
+        assert!(lexer.next().is_none(), "{:?}", input);
+        assert!(lexer.offset() == 0, "{:?}", input);
+    }
+    let mut lexer = Lexer::new("~ab");
+    assert!(lexer.next() == Some(Err(LexicalError::InvalidSigil { start: 0, end: 3 })));
+    assert!(Lexer::new("?\\").next() == Some(Ok((0, Tok::Char('\\'), 2))));
 }
@@ -0,0 +1,49 @@
+#[cfg(feature = "project")]
+extern crate ignore;
+// #[macro_use]
+extern crate lalrpop_util;
+#[cfg(feature = "lsp")]
+extern crate lsp_server;
+#[cfg(feature = "lsp")]
+extern crate lsp_types;
+extern crate num_bigint;
+extern crate num_traits;
+#[cfg(feature = "project")]
+extern crate rayon;
+extern crate rowan;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "serde")]
+extern crate serde_json;
+#[cfg(feature = "project")]
+extern crate xxhash_rust;
+// lalrpop_mod!(pub elixir); // synthesized by LALRPOP
+
+pub mod arena;
+pub mod ast;
+#[cfg(test)]
+mod builders;
+pub mod cst;
+pub mod diagnostics;
+pub mod etf;
+pub mod graph;
+pub mod highlight;
+pub mod incremental;
+#[cfg(feature = "serde")]
+pub mod json;
+pub mod lexer;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+pub mod outline;
+pub mod pattern;
+pub mod printer;
+#[cfg(feature = "project")]
+pub mod project;
+pub mod resolve;
+pub mod rewrite;
+pub mod symbol;
+pub mod variables;
+pub mod visit;
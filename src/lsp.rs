@@ -0,0 +1,426 @@
+//! A syntax-only language server, behind the `lsp` feature, run by
+//! `elixir_parser lsp`. It speaks LSP over stdio and keeps no state beyond
+//! the open documents, so it starts instantly on any project.
+//!
+//! It publishes lexer errors as diagnostics and answers semantic tokens,
+//! folding ranges for runs of comment lines and selection ranges. Document
+//! symbols, `do`/`end` and heredoc folding and formatting need the parser
+//! and are not advertised until it exists.
+
+use cst;
+use diagnostics;
+use highlight::{self, Class};
+use lexer::Lexed;
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{
+    FoldingRangeRequest, Request as RequestTrait, SelectionRangeRequest, SemanticTokensFullRequest,
+};
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, FoldingRange, FoldingRangeKind, FoldingRangeParams,
+    FoldingRangeProviderCapability, Position, PublishDiagnosticsParams, Range, SelectionRange,
+    SelectionRangeParams, SelectionRangeProviderCapability, SemanticToken, SemanticTokenType,
+    SemanticTokens, SemanticTokensFullOptions, SemanticTokensLegend, SemanticTokensOptions,
+    SemanticTokensParams, SemanticTokensResult, SemanticTokensServerCapabilities,
+    ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use std::collections::HashMap;
+use std::error::Error;
+
+/// The semantic token types, indexed by `token_type`.
+const TOKEN_TYPES: &[SemanticTokenType] = &[
+    SemanticTokenType::ENUM_MEMBER,
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::OPERATOR,
+    SemanticTokenType::COMMENT,
+];
+
+pub fn run() -> Result<(), Box<dyn Error + Sync + Send>> {
+    let (connection, io_threads) = Connection::stdio();
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+
+    let mut documents: HashMap<Url, String> = HashMap::new();
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    break;
+                }
+                let response = respond(&documents, request);
+                connection.sender.send(Message::Response(response))?;
+            }
+            Message::Notification(notification) => {
+                if let Some(uri) = update(&mut documents, notification) {
+                    let diagnostics = documents
+                        .get(&uri)
+                        .map_or(Vec::new(), |text| lex_diagnostics(text));
+                    let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+                    connection
+                        .sender
+                        .send(Message::Notification(Notification::new(
+                            PublishDiagnostics::METHOD.to_string(),
+                            params,
+                        )))?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+    // The writer thread runs until the connection's sender is gone.
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
+
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+            SemanticTokensOptions {
+                legend: SemanticTokensLegend {
+                    token_types: TOKEN_TYPES.to_vec(),
+                    token_modifiers: vec![],
+                },
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                ..SemanticTokensOptions::default()
+            },
+        )),
+        folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+        selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
+        ..ServerCapabilities::default()
+    }
+}
+
+/// Applies a document notification and returns the document whose
+/// diagnostics should be published.
+fn update(documents: &mut HashMap<Url, String>, notification: Notification) -> Option<Url> {
+    match notification.method.as_str() {
+        DidOpenTextDocument::METHOD => {
+            let params = notification
+                .extract::<<DidOpenTextDocument as NotificationTrait>::Params>(
+                    DidOpenTextDocument::METHOD,
+                )
+                .ok()?;
+            let document = params.text_document;
+            documents.insert(document.uri.clone(), document.text);
+            Some(document.uri)
+        }
+        DidChangeTextDocument::METHOD => {
+            let params = notification
+                .extract::<<DidChangeTextDocument as NotificationTrait>::Params>(
+                    DidChangeTextDocument::METHOD,
+                )
+                .ok()?;
+            // Sync is full, so the last change holds the whole text.
+            let text = params.content_changes.into_iter().last()?.text;
+            documents.insert(params.text_document.uri.clone(), text);
+            Some(params.text_document.uri)
+        }
+        DidCloseTextDocument::METHOD => {
+            let params = notification
+                .extract::<<DidCloseTextDocument as NotificationTrait>::Params>(
+                    DidCloseTextDocument::METHOD,
+                )
+                .ok()?;
+            documents.remove(&params.text_document.uri);
+            Some(params.text_document.uri)
+        }
+        _ => None,
+    }
+}
+
+fn respond(documents: &HashMap<Url, String>, request: Request) -> Response {
+    let id = request.id.clone();
+    match request.method.as_str() {
+        SemanticTokensFullRequest::METHOD => with_document(
+            documents,
+            request,
+            |params: &SemanticTokensParams| &params.text_document.uri,
+            |text, _| {
+                Some(SemanticTokensResult::Tokens(SemanticTokens {
+                    result_id: None,
+                    data: semantic_tokens(text),
+                }))
+            },
+        ),
+        FoldingRangeRequest::METHOD => with_document(
+            documents,
+            request,
+            |params: &FoldingRangeParams| &params.text_document.uri,
+            |text, _| Some(folding_ranges(text)),
+        ),
+        SelectionRangeRequest::METHOD => with_document(
+            documents,
+            request,
+            |params: &SelectionRangeParams| &params.text_document.uri,
+            |text, params| {
+                Some(
+                    params
+                        .positions
+                        .iter()
+                        .map(|&position| selection_range(text, position))
+                        .collect::<Vec<_>>(),
+                )
+            },
+        ),
+        _ => Response::new_err(
+            id,
+            lsp_server::ErrorCode::MethodNotFound as i32,
+            format!("unsupported request: {}", request.method),
+        ),
+    }
+}
+
+/// Answers a request about one open document, with `null` if it is not
+/// open.
+fn with_document<P, R>(
+    documents: &HashMap<Url, String>,
+    request: Request,
+    uri: impl Fn(&P) -> &Url,
+    answer: impl Fn(&str, &P) -> Option<R>,
+) -> Response
+where
+    P: serde::de::DeserializeOwned,
+    R: serde::Serialize,
+{
+    let id = request.id.clone();
+    let method = request.method.clone();
+    let (id, params): (RequestId, P) = match request.extract(&method) {
+        Ok(extracted) => extracted,
+        Err(err) => {
+            return Response::new_err(
+                id,
+                lsp_server::ErrorCode::InvalidParams as i32,
+                err.to_string(),
+            )
+        }
+    };
+    let result = documents
+        .get(uri(&params))
+        .and_then(|text| answer(text, &params));
+    Response::new_ok(id, result)
+}
+
+/// Diagnostics for the input the lexer rejected. Where it stopped on input
+/// it does not handle yet is left out, as that is nearly every valid file.
+fn lex_diagnostics(text: &str) -> Vec<Diagnostic> {
+    let index = LineIndex::new(text);
+    let lexed = Lexed::new(text);
+    diagnostics::lexing(text, &lexed.errors, None)
+        .into_iter()
+        .map(|diagnostic| Diagnostic {
+            severity: Some(DiagnosticSeverity::ERROR),
+            source: Some("elixir_parser".to_string()),
+            ..Diagnostic::new_simple(
+                Range::new(
+                    index.position(diagnostic.label.start),
+                    index.position(diagnostic.label.end),
+                ),
+                diagnostic.message,
+            )
+        })
+        .collect()
+}
+
+fn semantic_tokens(text: &str) -> Vec<SemanticToken> {
+    let index = LineIndex::new(text);
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+    let mut previous = Position::new(0, 0);
+    for (piece, class) in highlight::highlight(text) {
+        let start = offset;
+        offset += piece.len();
+        let token_type = match class {
+            Some(Class::Atom) | Some(Class::Keyword) => 0,
+            Some(Class::Char) | Some(Class::Number) => 1,
+            Some(Class::Operator) => 2,
+            Some(Class::Comment) => 3,
+            Some(Class::Error) | None => continue,
+        };
+        let position = index.position(start);
+        let delta_start = if position.line == previous.line {
+            position.character - previous.character
+        } else {
+            position.character
+        };
+        tokens.push(SemanticToken {
+            delta_line: position.line - previous.line,
+            delta_start,
+            length: piece.encode_utf16().count() as u32,
+            token_type,
+            token_modifiers_bitset: 0,
+        });
+        previous = position;
+    }
+    tokens
+}
+
+/// Folds runs of two or more lines holding only a comment.
+fn folding_ranges(text: &str) -> Vec<FoldingRange> {
+    let mut ranges = Vec::new();
+    let mut run: Option<(u32, u32)> = None;
+    for (line, content) in text.lines().enumerate() {
+        let line = line as u32;
+        if content.trim_start().starts_with('#') {
+            run = Some(run.map_or((line, line), |(start, _)| (start, line)));
+            continue;
+        }
+        if let Some((start, end)) = run.take() {
+            ranges.extend(comment_fold(start, end));
+        }
+    }
+    if let Some((start, end)) = run {
+        ranges.extend(comment_fold(start, end));
+    }
+    ranges
+}
+
+fn comment_fold(start_line: u32, end_line: u32) -> Option<FoldingRange> {
+    if start_line == end_line {
+        return None;
+    }
+    Some(FoldingRange {
+        start_line,
+        start_character: None,
+        end_line,
+        end_character: None,
+        kind: Some(FoldingRangeKind::Comment),
+        collapsed_text: None,
+    })
+}
+
+/// Expands from the token at `position` to its line without surrounding
+/// whitespace, then to the whole document.
+fn selection_range(text: &str, position: Position) -> SelectionRange {
+    let index = LineIndex::new(text);
+    let document = SelectionRange {
+        range: Range::new(Position::new(0, 0), index.position(text.len())),
+        parent: None,
+    };
+
+    let offset = index.offset(position);
+    let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[offset..].find('\n').map_or(text.len(), |i| offset + i);
+    let line_text = &text[line_start..line_end];
+    let trimmed_start = line_start + (line_text.len() - line_text.trim_start().len());
+    let trimmed_end = line_start + line_text.trim_end().len();
+    let line = SelectionRange {
+        range: Range::new(index.position(trimmed_start), index.position(trimmed_end)),
+        parent: Some(Box::new(document.clone())),
+    };
+
+    let file = cst::build(text);
+    let token = file
+        .token_at_offset((offset as u32).into())
+        .find(|token| !token.kind().is_trivia() && !token.kind().is_error());
+    match token {
+        Some(token) => {
+            let range = token.text_range();
+            SelectionRange {
+                range: Range::new(
+                    index.position(u32::from(range.start()) as usize),
+                    index.position(u32::from(range.end()) as usize),
+                ),
+                parent: Some(Box::new(line)),
+            }
+        }
+        None if trimmed_start < trimmed_end => line,
+        None => document,
+    }
+}
+
+/// Converts between byte offsets and LSP positions, whose characters are
+/// UTF-16 code units.
+struct LineIndex<'a> {
+    text: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(text: &'a str) -> Self {
+        let line_starts = ::std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        LineIndex { text, line_starts }
+    }
+
+    fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.line_starts[line];
+        let character = self.text[start..offset].encode_utf16().count();
+        Position::new(line as u32, character as u32)
+    }
+
+    fn offset(&self, position: Position) -> usize {
+        let start = match self.line_starts.get(position.line as usize) {
+            Some(&start) => start,
+            None => return self.text.len(),
+        };
+        let mut units = 0;
+        for (i, c) in self.text[start..].char_indices() {
+            if units >= position.character as usize || c == '\n' {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        self.text.len()
+    }
+}
+
+#[test]
+fn positions_count_utf16() {
+    let text = ":+\n?😀 :-\n";
+    let index = LineIndex::new(text);
+    assert_eq!(index.position(0), Position::new(0, 0));
+    assert_eq!(index.position(8), Position::new(1, 3));
+    assert_eq!(index.offset(Position::new(1, 3)), 8);
+    assert_eq!(index.offset(Position::new(1, 99)), 11);
+    assert_eq!(index.position(text.len()), Position::new(2, 0));
+}
+
+#[test]
+fn diagnostics_skip_unsupported_input() {
+    assert!(lex_diagnostics("defmodule Foo do\nend\n").is_empty());
+    let reported = lex_diagnostics("<<<<<<< HEAD\n");
+    assert_eq!(reported.len(), 1);
+    assert_eq!(
+        reported[0].range,
+        Range::new(Position::new(0, 0), Position::new(1, 0))
+    );
+}
+
+#[test]
+fn semantic_token_deltas() {
+    let tokens = semantic_tokens(":+ 0x1\n  # hi");
+    let summary: Vec<_> = tokens
+        .iter()
+        .map(|t| (t.delta_line, t.delta_start, t.length, t.token_type))
+        .collect();
+    assert_eq!(summary, vec![(0, 0, 2, 0), (0, 3, 3, 1), (1, 2, 4, 3)]);
+}
+
+#[test]
+fn comment_folding() {
+    let ranges = folding_ranges("# a\n# b\n:+\n# c\n  # d\n  # e\n");
+    let lines: Vec<_> = ranges.iter().map(|r| (r.start_line, r.end_line)).collect();
+    assert_eq!(lines, vec![(0, 1), (3, 5)]);
+}
+
+#[test]
+fn selection_expands_to_line() {
+    let range = selection_range("  :+ :- \n:*", Position::new(0, 6));
+    assert_eq!(
+        range.range,
+        Range::new(Position::new(0, 5), Position::new(0, 7))
+    );
+    let line = range.parent.unwrap();
+    assert_eq!(
+        line.range,
+        Range::new(Position::new(0, 2), Position::new(0, 7))
+    );
+    assert_eq!(line.parent.unwrap().range.end, Position::new(1, 2));
+}
@@ -1,16 +1,474 @@
-// #[macro_use]
-extern crate itertools;
-extern crate lalrpop_util;
-extern crate num_bigint;
-extern crate num_traits;
-// lalrpop_mod!(pub elixir); // synthesized by LALRPOP
+extern crate elixir_parser;
+#[cfg(feature = "serde")]
+extern crate serde_json;
 
-pub mod lexer;
+use elixir_parser::diagnostics;
+use elixir_parser::highlight;
+#[cfg(feature = "serde")]
+use elixir_parser::json;
+use elixir_parser::lexer::{Lexed, Tok};
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::process;
 
-// use std::fs;
+const USAGE: &str = "usage: elixir_parser <tokenize|check|highlight>
+                     [--format text|json|html] [PATH...]
+       elixir_parser project [--format text|json] [--cache DIR|--no-cache]
+                             [ROOT]
+       elixir_parser graph [--format dot|json|cycles] FILE...
+       elixir_parser lsp
+
+Paths may be files or directories, which are searched for .ex and .exs
+files. With no paths, or with -, the source is read from stdin.
+
+commands:
+    tokenize    print the tokens of each file with their spans
+    check       report files that fail to lex and exit non-zero
+    highlight   print each file with ANSI colors, or as HTML with
+                --format html
+    project     check every .ex and .exs file of the Mix project at ROOT,
+                or the current directory, in parallel, leaving out _build,
+                deps and ignored files; results are cached by content in
+                ROOT/_build/elixir_parser or DIR; needs the project feature
+    graph       print the dependencies between the modules defined in each
+                FILE, as Graphviz dot, JSON or a report of the cycles; each
+                FILE holds the quoted form Code.string_to_quoted/2 returns,
+                in the external term format, until there is a parser
+    lsp         run a language server over stdio; needs the lsp feature";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Command {
+    Tokenize,
+    Check,
+    Highlight,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Text,
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    Json,
+    Html,
+}
+
+struct Source {
+    name: String,
+    contents: String,
+}
 
 fn main() {
-    println!("Hello, world!");
+    let mut args = env::args().skip(1);
+    let command = match args.next().as_deref() {
+        Some("tokenize") => Command::Tokenize,
+        Some("check") => Command::Check,
+        Some("highlight") => Command::Highlight,
+        Some("project") => project(args),
+        Some("graph") => graph(args),
+        Some("lsp") => lsp(),
+        Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            return;
+        }
+        _ => usage_error(),
+    };
+
+    let mut format = Format::Text;
+    let mut paths = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                format = match args.next().as_deref() {
+                    Some("text") => Format::Text,
+                    Some("json") => json_format(),
+                    Some("html") => Format::Html,
+                    _ => usage_error(),
+                }
+            }
+            "--format=text" => format = Format::Text,
+            "--format=json" => format = json_format(),
+            "--format=html" => format = Format::Html,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ => paths.push(arg),
+        }
+    }
+
+    // HTML is only for highlighting, which has no JSON form.
+    let valid_format = match format {
+        Format::Text => true,
+        Format::Json => command != Command::Highlight,
+        Format::Html => command == Command::Highlight,
+    };
+    if !valid_format {
+        usage_error();
+    }
+
+    let sources = match read_sources(&paths) {
+        Ok(sources) => sources,
+        Err(err) => {
+            eprintln!("elixir_parser: {}", err);
+            process::exit(2);
+        }
+    };
+
+    let mut failed = false;
+    for source in &sources {
+        let lexed = Lexed::new(&source.contents);
+        failed |= !lexed.is_ok();
+        match command {
+            Command::Tokenize => print_tokens(source, &lexed, format),
+            Command::Check => print_diagnostics(source, &lexed, format),
+            Command::Highlight => print_highlighted(source, format),
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
+
+#[cfg(feature = "project")]
+fn project<I: Iterator<Item = String>>(mut args: I) -> ! {
+    use elixir_parser::project::{self, Cache, ContentHash, Summary};
+
+    let mut format = Format::Text;
+    let mut cache_dir = None;
+    let mut use_cache = true;
+    let mut root = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                format = match args.next().as_deref() {
+                    Some("text") => Format::Text,
+                    Some("json") => Format::Json,
+                    _ => usage_error(),
+                }
+            }
+            "--format=text" => format = Format::Text,
+            "--format=json" => format = Format::Json,
+            "--cache" => {
+                cache_dir = Some(PathBuf::from(args.next().unwrap_or_else(|| usage_error())))
+            }
+            "--no-cache" => use_cache = false,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if root.is_none() => root = Some(PathBuf::from(arg)),
+            _ => usage_error(),
+        }
+    }
+
+    let root = root.unwrap_or_else(|| PathBuf::from("."));
+    let cache = if use_cache {
+        let dir = cache_dir.unwrap_or_else(|| Cache::default_dir(&root));
+        Some(Cache::new(dir, "check"))
+    } else {
+        None
+    };
+    let (files, errors) = project::check(&root, cache.as_ref());
+    for err in &errors {
+        eprintln!("elixir_parser: {}", err);
+    }
+
+    let color = io::stderr().is_terminal();
+    let mut cached = 0;
+    let mut failed = errors.len();
+    for file in &files {
+        let name = file.path.display().to_string();
+        cached += file.cached as usize;
+        let summary = match &file.result {
+            Ok(summary) => summary,
+            Err(err) => {
+                eprintln!("elixir_parser: {}: {}", name, err);
+                failed += 1;
+                continue;
+            }
+        };
+        // Only failing files are read again, to show where they fail.
+        let (contents, diagnostics) = if summary.is_ok() {
+            (String::new(), Vec::new())
+        } else {
+            failed += 1;
+            let contents = match fs::read_to_string(&file.path) {
+                Ok(contents) => contents,
+                Err(err) => {
+                    eprintln!("elixir_parser: {}: {}", name, err);
+                    continue;
+                }
+            };
+            // The file may have changed since it was checked, and the
+            // offsets in the summary only fit the contents it was made from.
+            let diagnostics = if file.hash == Some(ContentHash::of(&contents)) {
+                summary.diagnostics(&contents)
+            } else {
+                Summary::new(&contents).diagnostics(&contents)
+            };
+            (contents, diagnostics)
+        };
+        match format {
+            Format::Json => {
+                let file = json::File {
+                    file: name,
+                    tokens: None,
+                    errors: diagnostics
+                        .iter()
+                        .map(|diagnostic| json::Error::new(&contents, diagnostic))
+                        .collect(),
+                };
+                println!("{}", serde_json::to_string(&file).unwrap());
+            }
+            _ => {
+                for diagnostic in diagnostics {
+                    eprint!("{}", diagnostic.render(&name, &contents, color));
+                }
+            }
+        }
+    }
+
+    eprintln!(
+        "elixir_parser: {} files, {} from the cache, {} failed",
+        files.len(),
+        cached,
+        failed
+    );
+    process::exit(if failed > 0 { 1 } else { 0 });
+}
+
+#[cfg(not(feature = "project"))]
+fn project<I: Iterator<Item = String>>(_: I) -> ! {
+    eprintln!("elixir_parser: project needs the project feature");
+    process::exit(2);
+}
+
+fn graph<I: Iterator<Item = String>>(mut args: I) -> ! {
+    use elixir_parser::etf;
+    use elixir_parser::graph::{Dependencies, Graph};
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Output {
+        Dot,
+        Json,
+        Cycles,
+    }
+
+    let mut output = Output::Dot;
+    let mut paths = Vec::new();
+    while let Some(arg) = args.next() {
+        let format = match arg.as_str() {
+            "--format" => args.next().unwrap_or_else(|| usage_error()),
+            _ if arg.starts_with("--format=") => arg["--format=".len()..].to_string(),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ => {
+                paths.push(PathBuf::from(arg));
+                continue;
+            }
+        };
+        output = match format.as_str() {
+            "dot" => Output::Dot,
+            "json" if json_format() == Format::Json => Output::Json,
+            "cycles" => Output::Cycles,
+            _ => usage_error(),
+        };
+    }
+    if paths.is_empty() {
+        usage_error();
+    }
+
+    let mut files = Vec::new();
+    for path in &paths {
+        let ast = fs::read(path)
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| etf::decode_ast(&bytes).map_err(|err| err.to_string()));
+        match ast {
+            Ok(ast) => files.push(Dependencies::new(&ast)),
+            Err(err) => {
+                eprintln!("elixir_parser: {}: {}", path.display(), err);
+                process::exit(2);
+            }
+        }
+    }
+    let graph = Graph::new(files);
+    match output {
+        Output::Dot => print!("{}", graph.to_dot()),
+        #[cfg(feature = "serde")]
+        Output::Json => println!("{}", graph.to_json()),
+        #[cfg(not(feature = "serde"))]
+        Output::Json => unreachable!("--format json is rejected without the serde feature"),
+        Output::Cycles => print!("{}", graph.cycles_report()),
+    }
+    process::exit(0);
+}
+
+#[cfg(feature = "lsp")]
+fn lsp() -> ! {
+    match elixir_parser::lsp::run() {
+        Ok(()) => process::exit(0),
+        Err(err) => {
+            eprintln!("elixir_parser: {}", err);
+            process::exit(2);
+        }
+    }
+}
+
+#[cfg(not(feature = "lsp"))]
+fn lsp() -> ! {
+    eprintln!("elixir_parser: lsp needs the lsp feature");
+    process::exit(2);
+}
+
+#[cfg(feature = "serde")]
+fn json_format() -> Format {
+    Format::Json
+}
+
+#[cfg(not(feature = "serde"))]
+fn json_format() -> Format {
+    eprintln!("elixir_parser: --format json needs the serde feature");
+    process::exit(2);
+}
+
+fn usage_error() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+fn read_sources(paths: &[String]) -> io::Result<Vec<Source>> {
+    if paths.is_empty() {
+        return Ok(vec![read_stdin()?]);
+    }
+
+    let mut sources = Vec::new();
+    for path in paths {
+        if path == "-" {
+            sources.push(read_stdin()?);
+            continue;
+        }
+        let mut files = Vec::new();
+        collect_files(Path::new(path), &mut files)?;
+        for file in files {
+            let contents = fs::read_to_string(&file).map_err(|err| {
+                io::Error::new(err.kind(), format!("{}: {}", file.display(), err))
+            })?;
+            sources.push(Source {
+                name: file.display().to_string(),
+                contents,
+            });
+        }
+    }
+    Ok(sources)
+}
+
+fn read_stdin() -> io::Result<Source> {
+    let mut contents = String::new();
+    io::stdin().read_to_string(&mut contents)?;
+    Ok(Source {
+        name: "stdin".to_string(),
+        contents,
+    })
+}
+
+/// Expands directories into the `.ex` and `.exs` files beneath them, in a
+/// stable order. Files named explicitly are kept whatever their extension.
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            collect_files(&entry, files)?;
+        } else if entry
+            .extension()
+            .is_some_and(|ext| ext == "ex" || ext == "exs")
+        {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+fn print_tokens(source: &Source, lexed: &Lexed, format: Format) {
+    match format {
+        Format::Text => {
+            for (start, tok, end) in &lexed.tokens {
+                let (kind, value) = kind_and_value(tok);
+                println!("{}:{}..{} {} {}", source.name, start, end, kind, value);
+            }
+            print_diagnostics(source, lexed, format);
+        }
+        Format::Json => print_json(source, lexed, true),
+        Format::Html => unreachable!("--format html is only for highlight"),
+    }
+}
+
+/// Names tokens the same way as the JSON output, see `elixir_parser::json`.
+fn kind_and_value(tok: &Tok) -> (&'static str, String) {
+    match tok {
+        Tok::Atom(s) => ("atom", s.to_string()),
+        Tok::Char(c) => ("char", c.to_string()),
+        Tok::Int(i) => ("int", i.to_string()),
+        Tok::KwIdentifier(s) => ("kw_identifier", s.to_string()),
+        Tok::Star => ("star", String::new()),
+    }
+}
+
+fn print_diagnostics(source: &Source, lexed: &Lexed, format: Format) {
+    match format {
+        Format::Text => {
+            let color = io::stderr().is_terminal();
+            for diagnostic in diagnostics::lexing(&source.contents, &lexed.errors, lexed.stopped) {
+                eprint!(
+                    "{}",
+                    diagnostic.render(&source.name, &source.contents, color)
+                );
+            }
+        }
+        Format::Json => print_json(source, lexed, false),
+        Format::Html => unreachable!("--format html is only for highlight"),
+    }
+}
+
+fn print_highlighted(source: &Source, format: Format) {
+    match format {
+        Format::Html => print!("{}", highlight::to_html(&source.contents)),
+        _ => print!("{}", highlight::to_ansi(&source.contents)),
+    }
+}
+
+#[cfg(feature = "serde")]
+fn print_json(source: &Source, lexed: &Lexed, with_tokens: bool) {
+    let tokens = lexed
+        .tokens
+        .iter()
+        .map(|(start, tok, end)| json::Token::new(*start, tok.clone(), *end))
+        .collect();
+    let file = json::File {
+        file: source.name.clone(),
+        tokens: if with_tokens { Some(tokens) } else { None },
+        errors: diagnostics::lexing(&source.contents, &lexed.errors, lexed.stopped)
+            .iter()
+            .map(|diagnostic| json::Error::new(&source.contents, diagnostic))
+            .collect(),
+    };
+    println!("{}", serde_json::to_string(&file).unwrap());
+}
+
+#[cfg(not(feature = "serde"))]
+fn print_json(_: &Source, _: &Lexed, _: bool) {
+    unreachable!("--format json is rejected without the serde feature")
 }
 
 #[test]
@@ -0,0 +1,1124 @@
+//! What a file defines: its modules, protocols and implementations, and
+//! their functions, macros, guards, structs and behaviours, with where each
+//! one is.
+//!
+//! `Outline::new` reads the quoted form the way the compiler does, going
+//! through the bodies of modules but not into function bodies, quotes or
+//! other calls. Module names are resolved against the modules nested
+//! before them, so `defmodule Item` inside `Shop` is `Shop.Item` and makes
+//! `Item` an alias for it in `Shop`, and against the `alias` directives and
+//! `require ..., as:` before them in the module or the ones enclosing it.
+//! Spans come from the `line`, `column` and `end` metadata of the defining
+//! call, when it has them.
+
+use ast::{Ast, Meta};
+#[cfg(test)]
+use builders::{alias, at, atom, block_of, call, kw, var};
+use num_traits::ToPrimitive;
+use resolve::expand_alias;
+use symbol::Symbol;
+
+#[derive(Clone, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Outline {
+    /// Every module in the order its definition starts, nested ones after
+    /// the module they are in.
+    pub modules: Vec<Module>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Module {
+    /// The full name, such as `Shop.Cart`, without the `Elixir.` prefix.
+    /// Erlang-style names keep their colon, as in `:shop_cart`.
+    pub name: Symbol,
+    pub kind: ModuleKind,
+    /// The index in `Outline::modules` of the module this one is nested in.
+    pub parent: Option<usize>,
+    /// The alias a nested module defines in its parent.
+    pub alias: Option<Alias>,
+    pub span: Option<Span>,
+    /// The `@behaviour`s the module declares.
+    pub behaviours: Vec<Symbol>,
+    /// The fields of `defstruct` or `defexception`.
+    pub fields: Option<Vec<Field>>,
+    /// Whether the struct is an exception, defined with `defexception`.
+    pub exception: bool,
+    pub functions: Vec<Function>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ModuleKind {
+    Module,
+    Protocol,
+    /// A `defimpl` of `protocol` for `target`, whose module is named
+    /// `protocol.target`.
+    Impl {
+        protocol: Symbol,
+        target: Symbol,
+    },
+}
+
+/// `name` stands for `target`, as `Item` does for `Shop.Item`.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Alias {
+    pub name: Symbol,
+    pub target: Symbol,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Span {
+    pub line: usize,
+    pub column: Option<usize>,
+    /// The line of the closing `end`.
+    pub end_line: Option<usize>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Field {
+    pub name: Symbol,
+    /// The default value, `None` for a field given without one, which
+    /// defaults to `nil`.
+    pub default: Option<Ast>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Kind {
+    Def,
+    Defp,
+    Defmacro,
+    Defmacrop,
+    Defguard,
+    Defguardp,
+    Defdelegate,
+}
+
+impl Kind {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "def" => Kind::Def,
+            "defp" => Kind::Defp,
+            "defmacro" => Kind::Defmacro,
+            "defmacrop" => Kind::Defmacrop,
+            "defguard" => Kind::Defguard,
+            "defguardp" => Kind::Defguardp,
+            "defdelegate" => Kind::Defdelegate,
+            _ => return None,
+        })
+    }
+
+    pub fn is_public(self) -> bool {
+        match self {
+            Kind::Def | Kind::Defmacro | Kind::Defguard | Kind::Defdelegate => true,
+            Kind::Defp | Kind::Defmacrop | Kind::Defguardp => false,
+        }
+    }
+
+    pub fn is_macro(self) -> bool {
+        match self {
+            Kind::Defmacro | Kind::Defmacrop | Kind::Defguard | Kind::Defguardp => true,
+            Kind::Def | Kind::Defp | Kind::Defdelegate => false,
+        }
+    }
+}
+
+/// A function, macro or guard: all the clauses of one name and arity.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Function {
+    pub kind: Kind,
+    pub name: Symbol,
+    pub arity: usize,
+    /// How many arguments have defaults. The function can also be called
+    /// with that many fewer, each arity from `arities().start()` up.
+    pub defaults: usize,
+    pub clauses: Vec<Clause>,
+    /// The `@impl` before the first clause.
+    pub implements: Option<Impl>,
+    /// Where a `defdelegate` sends calls.
+    pub delegate: Option<Delegate>,
+}
+
+impl Function {
+    pub fn arities(&self) -> ::std::ops::RangeInclusive<usize> {
+        self.arity - self.defaults..=self.arity
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Clause {
+    pub span: Option<Span>,
+    /// The `when` guard, for a `defguard` its definition.
+    pub guard: Option<Ast>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Impl {
+    /// `@impl true`.
+    True,
+    /// `@impl false`.
+    False,
+    /// `@impl Behaviour`.
+    Behaviour(Symbol),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Delegate {
+    pub module: Symbol,
+    /// The function called, from `as:`, or else the delegate's own name.
+    pub function: Symbol,
+}
+
+impl Outline {
+    pub fn new(ast: &Ast) -> Self {
+        let mut outline = Outline::default();
+        let mut aliases = Vec::new();
+        for expr in block(ast) {
+            if !outline.definition(None, expr, &aliases) {
+                outline.directive(None, expr, &mut aliases);
+            }
+        }
+        outline
+    }
+
+    /// The module named `name`.
+    pub fn module(&self, name: &str) -> Option<&Module> {
+        self.modules.iter().find(|module| module.name == name)
+    }
+
+    /// The modules nested directly in the module at `index`.
+    pub fn children(&self, index: usize) -> impl Iterator<Item = &Module> {
+        self.modules
+            .iter()
+            .filter(move |module| module.parent == Some(index))
+    }
+
+    /// Reads a module-level definition, returning whether it was one that
+    /// defines modules.
+    fn definition(&mut self, parent: Option<usize>, expr: &Ast, aliases: &[Alias]) -> bool {
+        let (name, meta, args) = match local_call(expr) {
+            Some(call) => call,
+            None => return false,
+        };
+        match name.as_str() {
+            "defmodule" | "defprotocol" => {
+                let kind = if name == "defmodule" {
+                    ModuleKind::Module
+                } else {
+                    ModuleKind::Protocol
+                };
+                let written = match args
+                    .first()
+                    .and_then(|alias| self.expand(parent, alias, aliases))
+                {
+                    Some(written) => written,
+                    None => return true,
+                };
+                // Nesting prefixes the name with the parent's and aliases
+                // the first part of it, unless that part is an alias
+                // already or the name starts with `Elixir`.
+                let first = match args.first() {
+                    Some(Ast::Call(_, _, parts)) => match parts.first() {
+                        Some(Ast::Atom(first))
+                            if *first != "Elixir"
+                                && self.lookup(parent, *first, aliases).is_none() =>
+                        {
+                            Some(*first)
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                let (name, alias) = match (parent, first) {
+                    (Some(parent), Some(first)) => {
+                        let prefix = self.modules[parent].name;
+                        let alias = Alias {
+                            name: first,
+                            target: format!("{}.{}", prefix, first).into(),
+                        };
+                        (format!("{}.{}", prefix, written).into(), Some(alias))
+                    }
+                    _ => (written, None),
+                };
+                let index = self.push(name, kind, parent, alias, meta);
+                if let Some(body) = args.last().and_then(|opts| keyword(opts, "do")) {
+                    self.module_body(index, body, aliases);
+                }
+            }
+            "defimpl" => {
+                let protocol = match args
+                    .first()
+                    .and_then(|alias| self.expand(parent, alias, aliases))
+                {
+                    Some(protocol) => protocol,
+                    None => return true,
+                };
+                let targets = match args.iter().find_map(|opts| keyword(opts, "for")) {
+                    Some(Ast::List(targets)) => targets
+                        .iter()
+                        .filter_map(|target| self.expand(parent, target, aliases))
+                        .collect(),
+                    Some(target) => self.expand(parent, target, aliases).into_iter().collect(),
+                    // Without `for:`, the module the `defimpl` is in.
+                    None => parent
+                        .map(|parent| vec![self.modules[parent].name])
+                        .unwrap_or_default(),
+                };
+                let body = args.iter().rev().find_map(|opts| keyword(opts, "do"));
+                for target in targets {
+                    let name = format!("{}.{}", protocol, target);
+                    let kind = ModuleKind::Impl { protocol, target };
+                    let index = self.push(name.into(), kind, parent, None, meta);
+                    if let Some(body) = body {
+                        self.module_body(index, body, aliases);
+                    }
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn push(
+        &mut self,
+        name: Symbol,
+        kind: ModuleKind,
+        parent: Option<usize>,
+        alias: Option<Alias>,
+        meta: &Meta,
+    ) -> usize {
+        self.modules.push(Module {
+            name,
+            kind,
+            parent,
+            alias,
+            span: span(meta),
+            behaviours: Vec::new(),
+            fields: None,
+            exception: false,
+            functions: Vec::new(),
+        });
+        self.modules.len() - 1
+    }
+
+    fn module_body(&mut self, index: usize, body: &Ast, aliases: &[Alias]) {
+        let mut aliases = aliases.to_vec();
+        let mut implements = None;
+        for expr in block(body) {
+            if self.definition(Some(index), expr, &aliases)
+                || self.directive(Some(index), expr, &mut aliases)
+            {
+                continue;
+            }
+            let (name, meta, args) = match local_call(expr) {
+                Some(call) => call,
+                None => continue,
+            };
+            if let Some(kind) = Kind::from_name(&name) {
+                self.function(index, kind, meta, args, implements.take(), &aliases);
+                continue;
+            }
+            match (name.as_str(), args) {
+                ("defstruct", [fields]) | ("defexception", [fields]) => {
+                    let module = &mut self.modules[index];
+                    module.fields = Some(struct_fields(fields));
+                    module.exception = name == "defexception";
+                }
+                ("@", [attribute]) => match local_call(attribute) {
+                    Some((name, _, [value])) if name == "behaviour" => {
+                        if let Some(behaviour) = self.expand(Some(index), value, &aliases) {
+                            self.modules[index].behaviours.push(behaviour);
+                        }
+                    }
+                    Some((name, _, [value])) if name == "impl" => {
+                        implements = match value {
+                            Ast::Atom(value) if *value == "true" => Some(Impl::True),
+                            Ast::Atom(value) if *value == "false" => Some(Impl::False),
+                            value => self
+                                .expand(Some(index), value, &aliases)
+                                .map(Impl::Behaviour),
+                        }
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+    }
+
+    fn function(
+        &mut self,
+        index: usize,
+        kind: Kind,
+        meta: &Meta,
+        args: &[Ast],
+        implements: Option<Impl>,
+        aliases: &[Alias],
+    ) {
+        let (head, guard) = match args.first() {
+            Some(Ast::Call(callee, _, when))
+                if **callee == Ast::Atom("when".into()) && when.len() == 2 =>
+            {
+                (&when[0], Some(when[1].clone()))
+            }
+            Some(head) => (head, None),
+            None => return,
+        };
+        let (name, params) = match head {
+            Ast::Call(callee, _, params) => match **callee {
+                Ast::Atom(name) => (name, &params[..]),
+                _ => return,
+            },
+            Ast::Var(name, _, _) => (*name, &[][..]),
+            _ => return,
+        };
+        let defaults = params
+            .iter()
+            .filter(|param| matches!(local_call(param), Some((name, _, [_, _])) if name == "\\\\"))
+            .count();
+        let delegate = if kind == Kind::Defdelegate {
+            let opts = args.get(1);
+            let module = opts
+                .and_then(|opts| keyword(opts, "to"))
+                .and_then(|to| self.expand(Some(index), to, aliases));
+            let function = match opts.and_then(|opts| keyword(opts, "as")) {
+                Some(Ast::Atom(function)) => *function,
+                _ => name,
+            };
+            module.map(|module| Delegate { module, function })
+        } else {
+            None
+        };
+        let clause = Clause {
+            span: span(meta),
+            guard,
+        };
+
+        let functions = &mut self.modules[index].functions;
+        let arity = params.len();
+        match functions.iter_mut().find(|function| {
+            function.kind == kind && function.name == name && function.arity == arity
+        }) {
+            Some(function) => {
+                function.defaults = function.defaults.max(defaults);
+                function.clauses.push(clause);
+                if function.implements.is_none() {
+                    function.implements = implements;
+                }
+            }
+            None => functions.push(Function {
+                kind,
+                name,
+                arity,
+                defaults,
+                clauses: vec![clause],
+                implements,
+                delegate,
+            }),
+        }
+    }
+
+    /// Reads an `alias` or `require` directive, adding the aliases it
+    /// defines to `aliases` and returning whether it was one.
+    fn directive(&self, module: Option<usize>, expr: &Ast, aliases: &mut Vec<Alias>) -> bool {
+        let (name, target, opts) = match local_call(expr) {
+            Some((name, _, [target, rest @ ..])) if name == "alias" || name == "require" => {
+                (name, target, rest.first())
+            }
+            _ => return false,
+        };
+        let as_ = match opts.and_then(|opts| keyword(opts, "as")) {
+            Some(Ast::Call(callee, _, parts)) if **callee == Ast::Atom("__aliases__".into()) => {
+                match &parts[..] {
+                    [Ast::Atom(as_)] => Some(*as_),
+                    _ => return true,
+                }
+            }
+            _ => None,
+        };
+        // `alias Shop.{Cart, Item}` aliases each of the modules in braces.
+        let targets = match target {
+            Ast::Call(callee, _, nested) => match &**callee {
+                Ast::Call(dot, _, base) if **dot == Ast::Atom(".".into()) => match &base[..] {
+                    [base, Ast::Atom(braces)] if *braces == "{}" => {
+                        let base = match self.expand(module, base, aliases) {
+                            Some(base) => base,
+                            None => return true,
+                        };
+                        nested
+                            .iter()
+                            .filter_map(|alias| expand_alias(alias, None, |_| None))
+                            .map(|name| Symbol::from(format!("{}.{}", base, name)))
+                            .collect()
+                    }
+                    _ => vec![],
+                },
+                _ => self.expand(module, target, aliases).into_iter().collect(),
+            },
+            _ => vec![],
+        };
+        for target in targets {
+            // An Erlang module needs `as:` to be aliased.
+            let name = match as_ {
+                Some(as_) => as_,
+                None if name == "alias" && !target.starts_with(':') => {
+                    target.rsplit('.').next().unwrap().into()
+                }
+                None => continue,
+            };
+            aliases.push(Alias { name, target });
+        }
+        true
+    }
+
+    /// The module `ast` names, resolved against `__MODULE__`, the explicit
+    /// `aliases` in scope and the aliases of modules nested in `module` or
+    /// the modules enclosing it.
+    fn expand(&self, module: Option<usize>, ast: &Ast, aliases: &[Alias]) -> Option<Symbol> {
+        let current = module.map(|index| self.modules[index].name);
+        expand_alias(ast, current, |name| self.lookup(module, name, aliases))
+    }
+
+    /// The module `name` is an alias for: the latest explicit one in
+    /// `aliases`, or else that of a nested module.
+    fn lookup(&self, module: Option<usize>, name: Symbol, aliases: &[Alias]) -> Option<Symbol> {
+        aliases
+            .iter()
+            .rev()
+            .find(|alias| alias.name == name)
+            .map(|alias| alias.target)
+            .or_else(|| self.resolve_alias(module, name))
+    }
+
+    fn resolve_alias(&self, mut module: Option<usize>, name: Symbol) -> Option<Symbol> {
+        while let Some(index) = module {
+            let alias = self.modules.iter().find_map(|nested| match &nested.alias {
+                Some(alias) if nested.parent == Some(index) && alias.name == name => {
+                    Some(alias.target)
+                }
+                _ => None,
+            });
+            if alias.is_some() {
+                return alias;
+            }
+            module = self.modules[index].parent;
+        }
+        None
+    }
+}
+
+/// A call to a local function or macro, with its name, metadata and
+/// arguments.
+fn local_call(ast: &Ast) -> Option<(Symbol, &Meta, &[Ast])> {
+    match ast {
+        Ast::Call(callee, meta, args) => match **callee {
+            Ast::Atom(name) => Some((name, meta, args)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The expressions of a body: those of a `__block__`, or else the one.
+fn block(ast: &Ast) -> &[Ast] {
+    match local_call(ast) {
+        Some((name, _, exprs)) if name == "__block__" => exprs,
+        _ => ::std::slice::from_ref(ast),
+    }
+}
+
+/// The value of `key` in a keyword list.
+fn keyword<'a>(list: &'a Ast, key: &str) -> Option<&'a Ast> {
+    match list {
+        Ast::List(pairs) => pairs.iter().find_map(|pair| match pair {
+            Ast::Pair(k, value) if **k == Ast::Atom(key.into()) => Some(&**value),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+fn struct_fields(fields: &Ast) -> Vec<Field> {
+    match fields {
+        Ast::List(fields) => fields
+            .iter()
+            .filter_map(|field| match field {
+                Ast::Atom(name) => Some(Field {
+                    name: *name,
+                    default: None,
+                }),
+                Ast::Pair(name, default) => match **name {
+                    Ast::Atom(name) => Some(Field {
+                        name,
+                        default: Some((**default).clone()),
+                    }),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn span(meta: &Meta) -> Option<Span> {
+    let end_line = meta
+        .iter()
+        .find(|(key, _)| *key == "end")
+        .and_then(|(_, end)| meta_int(end, "line"));
+    Some(Span {
+        line: meta_value(meta, "line")?,
+        column: meta_value(meta, "column"),
+        end_line,
+    })
+}
+
+fn meta_value(meta: &Meta, key: &str) -> Option<usize> {
+    match meta.iter().find(|(k, _)| *k == key) {
+        Some((_, Ast::Int(i))) => i.to_usize(),
+        _ => None,
+    }
+}
+
+/// An integer in a keyword list within metadata, such as the line in
+/// `end: [line: 5, column: 1]`.
+fn meta_int(list: &Ast, key: &str) -> Option<usize> {
+    match keyword(list, key) {
+        Some(Ast::Int(i)) => i.to_usize(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+fn attribute(name: &str, line: usize, value: Ast) -> Ast {
+    call("@", line, vec![call(name, line, vec![value])])
+}
+
+/// defmodule Shop.Cart do
+///   @behaviour Access
+///   defstruct items: [], total: 0
+///
+///   defmodule Item do
+///     defstruct [:sku, qty: 1]
+///   end
+///
+///   @impl Access
+///   def fetch(cart, key), do: :error
+///   def add(cart, item, qty \\ 1) when qty > 0 do cart end
+///   def add(cart, _item, _qty), do: cart
+///   defp total(cart), do: 0
+///   defmacro __using__(_opts), do: nil
+///   defguard is_cart(c) when is_map(c)
+///   defdelegate count(cart), to: Item, as: :size
+///   defimpl Size do def size(_), do: 0 end
+/// end
+///
+/// defprotocol Size do
+///   def size(data)
+/// end
+///
+/// defimpl Size, for: [Shop.Cart.Item, Map] do
+///   def size(_), do: 1
+/// end
+///
+/// defmodule Shop.Error do
+///   defexception message: "oops"
+/// end
+#[cfg(test)]
+fn sample() -> Ast {
+    let do_ = |body| kw(vec![("do", body)]);
+    let mut defmodule_meta = at(1);
+    defmodule_meta.push(("end".into(), kw(vec![("line", Ast::Int(18.into()))])));
+    let cart = Ast::Call(
+        Box::new(atom("defmodule")),
+        defmodule_meta,
+        vec![
+            alias("Shop.Cart", 0),
+            do_(block_of(vec![
+                attribute("behaviour", 2, alias("Access", 0)),
+                call(
+                    "defstruct",
+                    3,
+                    vec![kw(vec![
+                        ("items", Ast::List(vec![])),
+                        ("total", Ast::Int(0.into())),
+                    ])],
+                ),
+                call(
+                    "defmodule",
+                    5,
+                    vec![
+                        alias("Item", 0),
+                        do_(call(
+                            "defstruct",
+                            6,
+                            vec![Ast::List(vec![
+                                atom("sku"),
+                                Ast::Pair(Box::new(atom("qty")), Box::new(Ast::Int(1.into()))),
+                            ])],
+                        )),
+                    ],
+                ),
+                attribute("impl", 9, alias("Access", 0)),
+                call(
+                    "def",
+                    10,
+                    vec![
+                        call("fetch", 10, vec![var("cart", 0), var("key", 0)]),
+                        do_(atom("error")),
+                    ],
+                ),
+                call(
+                    "def",
+                    11,
+                    vec![
+                        call(
+                            "when",
+                            11,
+                            vec![
+                                call(
+                                    "add",
+                                    11,
+                                    vec![
+                                        var("cart", 0),
+                                        var("item", 0),
+                                        call("\\\\", 11, vec![var("qty", 0), Ast::Int(1.into())]),
+                                    ],
+                                ),
+                                call(">", 11, vec![var("qty", 0), Ast::Int(0.into())]),
+                            ],
+                        ),
+                        do_(var("cart", 0)),
+                    ],
+                ),
+                call(
+                    "def",
+                    12,
+                    vec![
+                        call(
+                            "add",
+                            12,
+                            vec![var("cart", 0), var("_item", 0), var("_qty", 0)],
+                        ),
+                        do_(var("cart", 0)),
+                    ],
+                ),
+                call(
+                    "defp",
+                    13,
+                    vec![
+                        call("total", 13, vec![var("cart", 0)]),
+                        do_(Ast::Int(0.into())),
+                    ],
+                ),
+                call(
+                    "defmacro",
+                    14,
+                    vec![
+                        call("__using__", 14, vec![var("_opts", 0)]),
+                        do_(atom("nil")),
+                    ],
+                ),
+                call(
+                    "defguard",
+                    15,
+                    vec![call(
+                        "when",
+                        15,
+                        vec![
+                            call("is_cart", 15, vec![var("c", 0)]),
+                            call("is_map", 15, vec![var("c", 0)]),
+                        ],
+                    )],
+                ),
+                call(
+                    "defdelegate",
+                    16,
+                    vec![
+                        call("count", 16, vec![var("cart", 0)]),
+                        kw(vec![("to", alias("Item", 0)), ("as", atom("size"))]),
+                    ],
+                ),
+                call(
+                    "defimpl",
+                    17,
+                    vec![
+                        alias("Size", 0),
+                        do_(call(
+                            "def",
+                            17,
+                            vec![call("size", 17, vec![var("_", 0)]), do_(Ast::Int(0.into()))],
+                        )),
+                    ],
+                ),
+            ])),
+        ],
+    );
+    let size = call(
+        "defprotocol",
+        20,
+        vec![
+            alias("Size", 0),
+            do_(call(
+                "def",
+                21,
+                vec![call("size", 21, vec![var("data", 0)])],
+            )),
+        ],
+    );
+    let impls = call(
+        "defimpl",
+        24,
+        vec![
+            alias("Size", 0),
+            kw(vec![
+                (
+                    "for",
+                    Ast::List(vec![alias("Shop.Cart.Item", 0), alias("Map", 0)]),
+                ),
+                (
+                    "do",
+                    call(
+                        "def",
+                        25,
+                        vec![call("size", 25, vec![var("_", 0)]), do_(Ast::Int(1.into()))],
+                    ),
+                ),
+            ]),
+        ],
+    );
+    let error = call(
+        "defmodule",
+        28,
+        vec![
+            alias("Shop.Error", 0),
+            do_(call(
+                "defexception",
+                29,
+                vec![kw(vec![("message", Ast::String("oops".into()))])],
+            )),
+        ],
+    );
+    block_of(vec![cart, size, impls, error])
+}
+
+#[test]
+fn modules() {
+    let outline = Outline::new(&sample());
+    let names: Vec<_> = outline.modules.iter().map(|module| module.name).collect();
+    assert_eq!(
+        names,
+        vec![
+            "Shop.Cart",
+            "Shop.Cart.Item",
+            "Size.Shop.Cart",
+            "Size",
+            "Size.Shop.Cart.Item",
+            "Size.Map",
+            "Shop.Error",
+        ]
+    );
+
+    let cart = &outline.modules[0];
+    assert_eq!(cart.kind, ModuleKind::Module);
+    assert_eq!(
+        cart.span,
+        Some(Span {
+            line: 1,
+            column: None,
+            end_line: Some(18),
+        })
+    );
+    assert_eq!(cart.behaviours, vec!["Access"]);
+    assert!(!cart.exception);
+    let fields = cart.fields.as_ref().unwrap();
+    assert_eq!(fields[0].name, "items");
+    assert_eq!(fields[1].default, Some(Ast::Int(0.into())));
+
+    let item = outline.module("Shop.Cart.Item").unwrap();
+    assert_eq!(item.parent, Some(0));
+    assert_eq!(
+        item.alias,
+        Some(Alias {
+            name: "Item".into(),
+            target: "Shop.Cart.Item".into(),
+        })
+    );
+    assert_eq!(
+        item.fields,
+        Some(vec![
+            Field {
+                name: "sku".into(),
+                default: None,
+            },
+            Field {
+                name: "qty".into(),
+                default: Some(Ast::Int(1.into())),
+            },
+        ])
+    );
+    assert_eq!(
+        outline
+            .children(0)
+            .map(|module| module.name)
+            .collect::<Vec<_>>(),
+        vec!["Shop.Cart.Item", "Size.Shop.Cart"]
+    );
+
+    // An implementation inside a module is for that module by default.
+    assert_eq!(
+        outline.modules[2].kind,
+        ModuleKind::Impl {
+            protocol: "Size".into(),
+            target: "Shop.Cart".into(),
+        }
+    );
+    assert_eq!(outline.module("Size").unwrap().kind, ModuleKind::Protocol);
+    let map = outline.module("Size.Map").unwrap();
+    assert_eq!(map.parent, None);
+    assert_eq!(map.functions[0].clauses[0].span.unwrap().line, 25);
+
+    let error = outline.module("Shop.Error").unwrap();
+    assert!(error.exception);
+    assert_eq!(error.fields.as_ref().unwrap()[0].name, "message");
+}
+
+#[test]
+fn functions() {
+    let outline = Outline::new(&sample());
+    let cart = &outline.modules[0];
+    let signatures: Vec<_> = cart
+        .functions
+        .iter()
+        .map(|function| format!("{:?} {}/{}", function.kind, function.name, function.arity))
+        .collect();
+    assert_eq!(
+        signatures,
+        vec![
+            "Def fetch/2",
+            "Def add/3",
+            "Defp total/1",
+            "Defmacro __using__/1",
+            "Defguard is_cart/1",
+            "Defdelegate count/1",
+        ]
+    );
+
+    let fetch = &cart.functions[0];
+    assert_eq!(fetch.implements, Some(Impl::Behaviour("Access".into())));
+    assert_eq!(fetch.arities(), 2..=2);
+
+    let add = &cart.functions[1];
+    assert_eq!(add.defaults, 1);
+    assert_eq!(add.arities(), 2..=3);
+    assert_eq!(add.clauses.len(), 2);
+    assert_eq!(add.implements, None);
+    assert_eq!(
+        add.clauses[0].guard,
+        Some(call(">", 11, vec![var("qty", 0), Ast::Int(0.into())]))
+    );
+    assert_eq!(add.clauses[1].span.unwrap().line, 12);
+    assert_eq!(add.clauses[1].guard, None);
+
+    assert!(!cart.functions[2].kind.is_public());
+    assert!(cart.functions[3].kind.is_macro());
+    let guard = &cart.functions[4];
+    assert!(guard.kind.is_macro() && guard.kind.is_public());
+    assert_eq!(
+        guard.clauses[0].guard,
+        Some(call("is_map", 15, vec![var("c", 0)]))
+    );
+
+    // `Item` is the nested module.
+    assert_eq!(
+        cart.functions[5].delegate,
+        Some(Delegate {
+            module: "Shop.Cart.Item".into(),
+            function: "size".into(),
+        })
+    );
+
+    // A protocol's functions are heads without bodies.
+    let size = outline.module("Size").unwrap();
+    assert_eq!(size.functions[0].name, "size");
+    assert_eq!(size.functions[0].arity, 1);
+}
+
+#[test]
+fn nested_names() {
+    // defmodule A do
+    //   defmodule B do end
+    //   defmodule B.C do end
+    //   defmodule __MODULE__.D do end
+    //   defmodule :e do end
+    //   defmodule Elixir.F do end
+    // end
+    let empty = || kw(vec![("do", block_of(vec![]))]);
+    let module_d = call("__aliases__", 0, vec![var("__MODULE__", 0), atom("D")]);
+    let ast = call(
+        "defmodule",
+        1,
+        vec![
+            alias("A", 0),
+            kw(vec![(
+                "do",
+                block_of(vec![
+                    call("defmodule", 2, vec![alias("B", 0), empty()]),
+                    call("defmodule", 3, vec![alias("B.C", 0), empty()]),
+                    call("defmodule", 4, vec![module_d, empty()]),
+                    call("defmodule", 5, vec![atom("e"), empty()]),
+                    call("defmodule", 6, vec![alias("Elixir.F", 0), empty()]),
+                ]),
+            )]),
+        ],
+    );
+    let outline = Outline::new(&ast);
+    let names: Vec<_> = outline
+        .modules
+        .iter()
+        .map(|module| (module.name.as_str(), module.alias.is_some()))
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            ("A", false),
+            ("A.B", true),
+            // `B` is an alias for `A.B` by now.
+            ("A.B.C", false),
+            ("A.D", false),
+            (":e", false),
+            ("F", false),
+        ]
+    );
+
+    // defmodule Elixir.G do end
+    let outline = Outline::new(&call("defmodule", 1, vec![alias("Elixir.G", 0), empty()]));
+    assert_eq!(outline.modules[0].name, "G");
+}
+
+#[test]
+fn explicit_aliases() {
+    // alias Shop.Cart.Item
+    //
+    // defmodule Shop.Checkout do
+    //   alias Shop.Payments, as: Pay
+    //   alias Shop.{Cart, Error}
+    //   @behaviour Pay.Gateway
+    //   @impl Pay.Gateway
+    //   def charge(order), do: order
+    //   defdelegate total(cart), to: Cart
+    //   defimpl Size, for: Item do end
+    //   defmodule Error.Report do end
+    // end
+    //
+    // defmodule Shop.Other do
+    //   @behaviour Pay
+    // end
+    let empty = || kw(vec![("do", block_of(vec![]))]);
+    let braces = Ast::Call(
+        Box::new(call(".", 3, vec![alias("Shop", 0), atom("{}")])),
+        at(3),
+        vec![alias("Cart", 0), alias("Error", 0)],
+    );
+    let checkout = call(
+        "defmodule",
+        3,
+        vec![
+            alias("Shop.Checkout", 0),
+            kw(vec![(
+                "do",
+                block_of(vec![
+                    call(
+                        "alias",
+                        4,
+                        vec![alias("Shop.Payments", 0), kw(vec![("as", alias("Pay", 0))])],
+                    ),
+                    call("alias", 5, vec![braces]),
+                    attribute("behaviour", 6, alias("Pay.Gateway", 0)),
+                    attribute("impl", 7, alias("Pay.Gateway", 0)),
+                    call(
+                        "def",
+                        8,
+                        vec![
+                            call("charge", 8, vec![var("order", 0)]),
+                            kw(vec![("do", var("order", 0))]),
+                        ],
+                    ),
+                    call(
+                        "defdelegate",
+                        9,
+                        vec![
+                            call("total", 9, vec![var("cart", 0)]),
+                            kw(vec![("to", alias("Cart", 0))]),
+                        ],
+                    ),
+                    call(
+                        "defimpl",
+                        10,
+                        vec![
+                            alias("Size", 0),
+                            kw(vec![("for", alias("Item", 0)), ("do", block_of(vec![]))]),
+                        ],
+                    ),
+                    call("defmodule", 11, vec![alias("Error.Report", 0), empty()]),
+                ]),
+            )]),
+        ],
+    );
+    let other = call(
+        "defmodule",
+        14,
+        vec![
+            alias("Shop.Other", 0),
+            kw(vec![("do", attribute("behaviour", 15, alias("Pay", 0)))]),
+        ],
+    );
+    let ast = block_of(vec![
+        call("alias", 1, vec![alias("Shop.Cart.Item", 0)]),
+        checkout,
+        other,
+    ]);
+    let outline = Outline::new(&ast);
+    let names: Vec<_> = outline.modules.iter().map(|module| module.name).collect();
+    assert_eq!(
+        names,
+        vec![
+            "Shop.Checkout",
+            "Size.Shop.Cart.Item",
+            "Shop.Error.Report",
+            "Shop.Other",
+        ]
+    );
+
+    let checkout = outline.module("Shop.Checkout").unwrap();
+    assert_eq!(checkout.behaviours, vec!["Shop.Payments.Gateway"]);
+    assert_eq!(
+        checkout.functions[0].implements,
+        Some(Impl::Behaviour("Shop.Payments.Gateway".into()))
+    );
+    assert_eq!(
+        checkout.functions[1].delegate.as_ref().map(|to| to.module),
+        Some("Shop.Cart".into())
+    );
+    assert_eq!(
+        outline.modules[1].kind,
+        ModuleKind::Impl {
+            protocol: "Size".into(),
+            target: "Shop.Cart.Item".into(),
+        }
+    );
+    // Aliases end with the module that defines them.
+    assert_eq!(
+        outline.module("Shop.Other").unwrap().behaviours,
+        vec!["Pay"]
+    );
+}
@@ -0,0 +1,406 @@
+//! Structural search: patterns are ASTs matched against ASTs, ignoring
+//! metadata, in the manner of ast-grep.
+//!
+//! A pattern is Elixir code in which some variables are metavariables: `_`
+//! matches any node, `$X` matches any node and binds it to `X`, and
+//! `$$$ARGS` matches any run of arguments or list elements and binds them
+//! to `ARGS`. A metavariable used twice must match the same code both times.
+//! Patterns will be written as source once the parser exists; until then
+//! they are built from an `Ast` with `Pattern::new`.
+
+use ast::Ast;
+#[cfg(test)]
+use builders::{alias, call, remote, var};
+use num_traits::ToPrimitive;
+use symbol::Symbol;
+use visit;
+
+/// A pattern, matched with `Pattern::matches` or found anywhere in a tree
+/// with `Pattern::search`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Pattern {
+    ast: Ast,
+}
+
+/// What a metavariable matched.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Binding<'ast> {
+    /// The node a `$X` matched.
+    Node(&'ast Ast),
+    /// The arguments or elements a `$$$X` matched.
+    Nodes(&'ast [Ast]),
+}
+
+/// The bindings of a match, in the order the metavariables were matched.
+pub type Bindings<'ast> = Vec<(Symbol, Binding<'ast>)>;
+
+/// A node that matched, found by `Pattern::search`.
+#[derive(Clone, Debug)]
+pub struct Match<'ast> {
+    pub node: &'ast Ast,
+    pub bindings: Bindings<'ast>,
+    /// The nodes enclosing `node`, outermost first.
+    pub ancestors: Vec<&'ast Ast>,
+}
+
+enum Metavariable {
+    Wildcard,
+    Node(Symbol),
+    Nodes(Symbol),
+}
+
+fn metavariable(pattern: &Ast) -> Option<Metavariable> {
+    let name = match pattern {
+        Ast::Var(name, _, _) => name.as_str(),
+        _ => return None,
+    };
+    if name == "_" {
+        Some(Metavariable::Wildcard)
+    } else if let Some(name) = name.strip_prefix("$$$") {
+        Some(Metavariable::Nodes(name.into()))
+    } else {
+        name.strip_prefix('$')
+            .map(|name| Metavariable::Node(name.into()))
+    }
+}
+
+impl Pattern {
+    pub fn new(ast: Ast) -> Self {
+        Pattern { ast }
+    }
+
+    pub fn ast(&self) -> &Ast {
+        &self.ast
+    }
+
+    /// Matches `ast` itself against the pattern.
+    pub fn matches<'ast>(&self, ast: &'ast Ast) -> Option<Bindings<'ast>> {
+        let mut bindings = Vec::new();
+        if match_node(&self.ast, ast, &mut bindings) {
+            Some(bindings)
+        } else {
+            None
+        }
+    }
+
+    /// Every node of `ast` that matches, in the order of a prewalk. Matches
+    /// may be nested in one another.
+    pub fn search<'ast>(&self, ast: &'ast Ast) -> Vec<Match<'ast>> {
+        let mut matches = Vec::new();
+        self.search_node(ast, &mut Vec::new(), &mut matches);
+        matches
+    }
+
+    fn search_node<'ast>(
+        &self,
+        ast: &'ast Ast,
+        ancestors: &mut Vec<&'ast Ast>,
+        matches: &mut Vec<Match<'ast>>,
+    ) {
+        if let Some(bindings) = self.matches(ast) {
+            matches.push(Match {
+                node: ast,
+                bindings,
+                ancestors: ancestors.clone(),
+            });
+        }
+        ancestors.push(ast);
+        for child in visit::children(ast) {
+            self.search_node(child, ancestors, matches);
+        }
+        ancestors.pop();
+    }
+}
+
+impl<'ast> Match<'ast> {
+    pub fn get(&self, name: &str) -> Option<Binding<'ast>> {
+        self.bindings
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|&(_, binding)| binding)
+    }
+
+    /// Whether any node enclosing the match matches `pattern`, as in "a
+    /// `Repo.get!` inside a `def`".
+    pub fn inside(&self, pattern: &Pattern) -> bool {
+        self.ancestors
+            .iter()
+            .any(|ancestor| pattern.matches(ancestor).is_some())
+    }
+
+    /// The line of the match: that of the node, or failing that of the
+    /// nearest enclosing node with one, since literals have no metadata.
+    pub fn line(&self) -> Option<usize> {
+        self.position("line")
+    }
+
+    pub fn column(&self) -> Option<usize> {
+        self.position("column")
+    }
+
+    fn position(&self, key: &str) -> Option<usize> {
+        ::std::iter::once(self.node)
+            .chain(self.ancestors.iter().rev().cloned())
+            .find_map(|ast| {
+                let meta = match ast {
+                    Ast::Var(_, meta, _) | Ast::Call(_, meta, _) => meta,
+                    _ => return None,
+                };
+                meta.iter()
+                    .find(|(k, _)| *k == key)
+                    .and_then(|(_, value)| match value {
+                        Ast::Int(i) => i.to_usize(),
+                        _ => None,
+                    })
+            })
+    }
+}
+
+fn match_node<'ast>(pattern: &Ast, ast: &'ast Ast, bindings: &mut Bindings<'ast>) -> bool {
+    match metavariable(pattern) {
+        Some(Metavariable::Wildcard) => return true,
+        Some(Metavariable::Node(name)) => return bind(name, Binding::Node(ast), bindings),
+        // Outside arguments and lists a run can only be the one node.
+        Some(Metavariable::Nodes(name)) => {
+            return bind(name, Binding::Nodes(::std::slice::from_ref(ast)), bindings)
+        }
+        None => {}
+    }
+    let mark = bindings.len();
+    let matched = match (pattern, ast) {
+        (Ast::Atom(a), Ast::Atom(b)) => a == b,
+        (Ast::Int(a), Ast::Int(b)) => a == b,
+        (Ast::Float(a), Ast::Float(b)) => a == b,
+        (Ast::String(a), Ast::String(b)) => a == b,
+        (Ast::List(patterns), Ast::List(elems)) => match_all(patterns, elems, bindings),
+        (Ast::Pair(pa, pb), Ast::Pair(a, b)) => {
+            match_node(pa, a, bindings) && match_node(pb, b, bindings)
+        }
+        (Ast::Var(pattern, _, _), Ast::Var(name, _, _)) => pattern == name,
+        (Ast::Call(pattern, _, patterns), Ast::Call(callee, _, args)) => {
+            match_node(pattern, callee, bindings) && match_all(patterns, args, bindings)
+        }
+        _ => false,
+    };
+    if !matched {
+        bindings.truncate(mark);
+    }
+    matched
+}
+
+/// Matches a sequence, trying every length for each `$$$` run from the
+/// shortest.
+fn match_all<'ast>(patterns: &[Ast], asts: &'ast [Ast], bindings: &mut Bindings<'ast>) -> bool {
+    let (first, rest) = match patterns.split_first() {
+        Some(split) => split,
+        None => return asts.is_empty(),
+    };
+    let mark = bindings.len();
+    if let Some(Metavariable::Nodes(name)) = metavariable(first) {
+        for len in 0..=asts.len() {
+            if bind(name, Binding::Nodes(&asts[..len]), bindings)
+                && match_all(rest, &asts[len..], bindings)
+            {
+                return true;
+            }
+            bindings.truncate(mark);
+        }
+        return false;
+    }
+    let matched = match asts.split_first() {
+        Some((ast, asts)) => match_node(first, ast, bindings) && match_all(rest, asts, bindings),
+        None => false,
+    };
+    if !matched {
+        bindings.truncate(mark);
+    }
+    matched
+}
+
+/// Binds `name`, or checks that what it was bound to before is the same
+/// code.
+fn bind<'ast>(name: Symbol, binding: Binding<'ast>, bindings: &mut Bindings<'ast>) -> bool {
+    if name == "_" {
+        return true;
+    }
+    match bindings.iter().find(|(bound, _)| *bound == name) {
+        Some(&(_, bound)) => same_binding(bound, binding),
+        None => {
+            bindings.push((name, binding));
+            true
+        }
+    }
+}
+
+fn same_binding(a: Binding, b: Binding) -> bool {
+    match (a, b) {
+        (Binding::Node(a), Binding::Node(b)) => same(a, b),
+        (Binding::Nodes(a), Binding::Nodes(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same(a, b))
+        }
+        _ => false,
+    }
+}
+
+/// Whether two trees are the same code, whatever their metadata.
+fn same(a: &Ast, b: &Ast) -> bool {
+    match (a, b) {
+        (Ast::List(a), Ast::List(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same(a, b))
+        }
+        (Ast::Pair(a1, a2), Ast::Pair(b1, b2)) => same(a1, b1) && same(a2, b2),
+        (Ast::Var(a, _, a_context), Ast::Var(b, _, b_context)) => a == b && a_context == b_context,
+        (Ast::Call(a, _, a_args), Ast::Call(b, _, b_args)) => {
+            same(a, b)
+                && a_args.len() == b_args.len()
+                && a_args.iter().zip(b_args).all(|(a, b)| same(a, b))
+        }
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+fn def(name: &str, line: usize, body: Ast) -> Ast {
+    let body = Ast::List(vec![Ast::Pair(
+        Box::new(Ast::Atom("do".into())),
+        Box::new(body),
+    )]);
+    call(
+        "def",
+        line,
+        vec![call(name, line, vec![var("id", line)]), body],
+    )
+}
+
+#[test]
+fn metavariables() {
+    // Repo.get!($X, $X)
+    let pattern = Pattern::new(remote(
+        alias("Repo", 0),
+        "get!",
+        0,
+        vec![var("$X", 0), var("$X", 0)],
+    ));
+    let same = remote(
+        alias("Repo", 0),
+        "get!",
+        3,
+        vec![var("id", 3), var("id", 4)],
+    );
+    let bindings = pattern.matches(&same).unwrap();
+    assert_eq!(bindings.len(), 1);
+    assert_eq!(bindings[0].0, "X");
+    assert_eq!(bindings[0].1, Binding::Node(&same_args(&same)[0]));
+    let different = remote(
+        alias("Repo", 0),
+        "get!",
+        3,
+        vec![var("user", 3), var("id", 3)],
+    );
+    assert!(pattern.matches(&different).is_none());
+    assert!(pattern
+        .matches(&remote(
+            alias("Repo", 0),
+            "get",
+            3,
+            vec![var("id", 3), var("id", 3)]
+        ))
+        .is_none());
+
+    // foo(_, $$$REST, :last)
+    let pattern = Pattern::new(call(
+        "foo",
+        0,
+        vec![var("_", 0), var("$$$REST", 0), Ast::Atom("last".into())],
+    ));
+    let args = vec![
+        Ast::Int(1.into()),
+        Ast::Int(2.into()),
+        Ast::Int(3.into()),
+        Ast::Atom("last".into()),
+    ];
+    let ast = call("foo", 1, args.clone());
+    let bindings = pattern.matches(&ast).unwrap();
+    assert_eq!(
+        bindings,
+        vec![("REST".into(), Binding::Nodes(&same_args(&ast)[1..3]))]
+    );
+    assert!(pattern
+        .matches(&call("foo", 1, vec![Ast::Int(1.into())]))
+        .is_none());
+    let short = call("foo", 1, vec![Ast::Int(1.into()), Ast::Atom("last".into())]);
+    match pattern.matches(&short).unwrap()[0].1 {
+        Binding::Nodes(nodes) => assert!(nodes.is_empty()),
+        binding => panic!("{:?}", binding),
+    }
+}
+
+#[cfg(test)]
+fn same_args(ast: &Ast) -> &[Ast] {
+    match ast {
+        Ast::Call(_, _, args) => args,
+        ast => panic!("{:?}", ast),
+    }
+}
+
+#[test]
+fn search_with_context() {
+    // def show(id) do Repo.get!(User, id) end
+    // def edit(id) do with {:ok, user} <- Repo.get!(User, id), do: user end
+    let get = |line| {
+        remote(
+            alias("Repo", 0),
+            "get!",
+            line,
+            vec![alias("User", 0), var("id", line)],
+        )
+    };
+    let with = call(
+        "with",
+        5,
+        vec![
+            call(
+                "<-",
+                5,
+                vec![
+                    Ast::Pair(Box::new(Ast::Atom("ok".into())), Box::new(var("user", 5))),
+                    get(5),
+                ],
+            ),
+            Ast::List(vec![Ast::Pair(
+                Box::new(Ast::Atom("do".into())),
+                Box::new(var("user", 5)),
+            )]),
+        ],
+    );
+    let module = Ast::List(vec![def("show", 1, get(2)), def("edit", 4, with)]);
+
+    let pattern = Pattern::new(remote(
+        alias("Repo", 0),
+        "get!",
+        0,
+        vec![var("_", 0), var("_", 0)],
+    ));
+    let matches = pattern.search(&module);
+    assert_eq!(
+        matches.iter().map(|m| m.line()).collect::<Vec<_>>(),
+        vec![Some(2), Some(5)]
+    );
+
+    let in_def = Pattern::new(call("def", 0, vec![var("_", 0), var("_", 0)]));
+    let in_with = Pattern::new(call("with", 0, vec![var("$$$_", 0)]));
+    let unwrapped: Vec<_> = matches
+        .iter()
+        .filter(|m| m.inside(&in_def) && !m.inside(&in_with))
+        .collect();
+    assert_eq!(unwrapped.len(), 1);
+    assert_eq!(unwrapped[0].line(), Some(2));
+    assert_eq!(unwrapped[0].column(), None);
+
+    // Literals take the line of what encloses them.
+    let user = Pattern::new(Ast::Atom("User".into()));
+    let lines: Vec<_> = user.search(&module).iter().map(|m| m.line()).collect();
+    assert_eq!(lines, vec![Some(2), Some(5)]);
+    let ok = Pattern::new(Ast::Atom("ok".into()));
+    assert_eq!(ok.search(&module)[0].line(), Some(5));
+}
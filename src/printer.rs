@@ -0,0 +1,1035 @@
+//! Turns an `Ast` back into Elixir source, like `Macro.to_string/1`.
+//!
+//! Operators get parentheses only where the precedence and associativity
+//! of Elixir's grammar require them. Calls keep their parentheses, except
+//! that a trailing keyword list loses its brackets and a trailing `do:`
+//! keyword list becomes a `do`/`end` block.
+
+use ast::Ast;
+#[cfg(test)]
+use builders::{call as local, kw, var};
+use std::fmt;
+use symbol::Symbol;
+
+const INDENT: &str = "  ";
+
+/// Keywords that may follow `do` in a block, in the order they are printed.
+const BLOCK_KEYWORDS: &[&str] = &["do", "else", "after", "rescue", "catch"];
+
+/// Operator atoms that are written without quotes, such as `:+` or `:<<>>`.
+const OPERATOR_ATOMS: &[&str] = &[
+    "...", "<<>>", "%{}", "%", "{}", "~~~", "===", "!==", "&&&", "|||", "<<<", ">>>", "~>>", "<<~",
+    "<~>", "<|>", "^^^", "==", "!=", "=~", "<=", ">=", "&&", "||", "|>", "~>", "<~", "<-", "\\\\",
+    "<>", "..", "++", "--", "->", "::", "@", "!", "^", "&", "+", "-", "*", "/", "<", ">", "=", "|",
+    ".",
+];
+
+/// Operator atoms the lexer accepts as keyword keys, such as `%{}: 1`.
+const OPERATOR_KEYWORDS: &[&str] = &["...", "<<>>", "%{}", "%", "{}"];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Assoc {
+    Left,
+    Right,
+}
+
+pub fn to_string(ast: &Ast) -> String {
+    statements(ast)
+}
+
+impl fmt::Display for Ast {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&to_string(self))
+    }
+}
+
+/// Precedence and associativity of binary operators, from
+/// `Code.Identifier.binary_op/1`.
+fn binary_op(op: &str) -> Option<(Assoc, u32)> {
+    use self::Assoc::*;
+    Some(match op {
+        "<-" | "\\\\" => (Left, 40),
+        "when" => (Right, 50),
+        "::" => (Right, 60),
+        "|" => (Right, 70),
+        "=>" => (Right, 80),
+        "=" => (Right, 100),
+        "||" | "|||" | "or" => (Left, 120),
+        "&&" | "&&&" | "and" => (Left, 130),
+        "==" | "!=" | "=~" | "===" | "!==" => (Left, 140),
+        "<" | "<=" | ">=" | ">" => (Left, 150),
+        "|>" | "<<<" | ">>>" | "<~" | "~>" | "<<~" | "~>>" | "<~>" | "<|>" => (Left, 160),
+        "in" => (Left, 170),
+        "^^^" => (Left, 180),
+        "//" => (Right, 190),
+        "++" | "--" | ".." | "<>" | "+++" | "---" => (Right, 200),
+        "+" | "-" => (Left, 210),
+        "*" | "/" => (Left, 220),
+        "**" => (Left, 230),
+        _ => return None,
+    })
+}
+
+/// Precedence of unary operators, from `Code.Identifier.unary_op/1`.
+fn unary_op(op: &str) -> Option<u32> {
+    match op {
+        "&" => Some(90),
+        "!" | "^" | "not" | "+" | "-" | "~~~" => Some(300),
+        "@" => Some(320),
+        _ => None,
+    }
+}
+
+/// The precedence of an operator expression, or `None` for anything that
+/// binds tighter than every operator.
+fn precedence(ast: &Ast) -> Option<u32> {
+    match ast {
+        Ast::Call(callee, _, args) => match (&**callee, args.len()) {
+            (Ast::Atom(op), 1) if op == "not" && is_call_to(&args[0], "in", 2) => Some(170),
+            // `&1` is an argument reference, not an operator.
+            (Ast::Atom(op), 1) if op == "&" && matches!(args[0], Ast::Int(_)) => None,
+            (Ast::Atom(op), 1) if unary_op(op).is_some() => unary_op(op),
+            (Ast::Atom(op), 2) if binary_op(op).is_some() => binary_op(op).map(|(_, prec)| prec),
+            (Ast::Atom(op), 3) if op == "..//" => Some(200),
+            _ if is_no_parens_call(ast) => Some(0),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn is_call_to(ast: &Ast, name: &str, arity: usize) -> bool {
+    match ast {
+        Ast::Call(callee, _, args) => **callee == Ast::Atom(name.into()) && args.len() == arity,
+        _ => false,
+    }
+}
+
+/// Calls printed without parentheses around their arguments, which must be
+/// wrapped when they appear as an operand or argument.
+fn is_no_parens_call(ast: &Ast) -> bool {
+    match ast {
+        Ast::Call(_, _, args) => args.last().is_some_and(|last| do_block(last).is_some()),
+        _ => false,
+    }
+}
+
+/// Prints an expression in statement position, where a block is a sequence
+/// of lines.
+fn statements(ast: &Ast) -> String {
+    match block(ast) {
+        Some(exprs) => exprs.iter().map(expr).collect::<Vec<_>>().join("\n"),
+        None => expr(ast),
+    }
+}
+
+fn block(ast: &Ast) -> Option<&[Ast]> {
+    match ast {
+        Ast::Call(callee, _, exprs) if **callee == Ast::Atom("__block__".into()) => Some(exprs),
+        _ => None,
+    }
+}
+
+fn expr(ast: &Ast) -> String {
+    match ast {
+        Ast::Atom(s) => atom(s),
+        Ast::Int(i) => i.to_string(),
+        Ast::Float(f) => float(*f),
+        Ast::String(s) => format!("\"{}\"", escape(s, '"')),
+        Ast::List(elems) => list(elems),
+        Ast::Pair(a, b) => format!("{{{}, {}}}", expr(a), expr(b)),
+        Ast::Var(name, _, _) => name.to_string(),
+        Ast::Call(callee, meta, args) => call(callee, meta, args),
+    }
+}
+
+fn call(callee: &Ast, meta: &[(Symbol, Ast)], args: &[Ast]) -> String {
+    if let Ast::Atom(name) = callee {
+        if let Some(s) = special_form(name, args) {
+            return s;
+        }
+        if let Some(s) = operator(name, args) {
+            return s;
+        }
+        if let Some(s) = sigil(name, meta, args) {
+            return s;
+        }
+    }
+
+    if let Some(s) = dot_call(callee, meta, args) {
+        return s;
+    }
+
+    let head = match callee {
+        Ast::Atom(name) if is_identifier(name) => name.to_string(),
+        Ast::Atom(name) => format!("unquote({})", atom(name)),
+        _ => operand(callee, 310, Assoc::Left),
+    };
+    call_with_args(&head, args)
+}
+
+/// Prints `head(args)`, or `head args do ... end` when the last argument is
+/// a `do` block.
+fn call_with_args(head: &str, args: &[Ast]) -> String {
+    if let Some((blocks, rest)) = args
+        .split_last()
+        .and_then(|(last, rest)| do_block(last).map(|blocks| (blocks, rest)))
+    {
+        let mut s = head.to_string();
+        if !rest.is_empty() {
+            s.push(' ');
+            s.push_str(&arguments(rest));
+        }
+        for (keyword, body) in blocks {
+            if keyword == "do" {
+                s.push_str(" do");
+            } else {
+                s.push('\n');
+                s.push_str(keyword);
+            }
+            let body = block_body(body);
+            if !body.is_empty() {
+                s.push('\n');
+                s.push_str(&indent(&body));
+            }
+        }
+        s.push_str("\nend");
+        return s;
+    }
+    format!("{}({})", head, arguments(args))
+}
+
+/// Arguments separated by commas, with the brackets of a trailing keyword
+/// list left off.
+fn arguments(args: &[Ast]) -> String {
+    let mut printed: Vec<String> = args.iter().map(argument).collect();
+    if let Some(Ast::List(last)) = args.last() {
+        if is_keyword(last) {
+            printed.pop();
+            printed.push(keyword(last));
+        }
+    }
+    printed.join(", ")
+}
+
+fn argument(ast: &Ast) -> String {
+    match precedence(ast) {
+        // Arguments are separated by commas, which bind looser than any
+        // operator but `->` and the `do` of a call without parentheses.
+        Some(0) => format!("({})", expr(ast)),
+        _ if block(ast).is_some_and(|exprs| exprs.len() != 1) => {
+            format!("({})", block_inline(ast))
+        }
+        _ => expr(ast),
+    }
+}
+
+/// The `do`, `else` and other blocks of a trailing keyword list, if it is
+/// one.
+fn do_block(ast: &Ast) -> Option<Vec<(&str, &Ast)>> {
+    let pairs = match ast {
+        Ast::List(elems) if !elems.is_empty() => elems,
+        _ => return None,
+    };
+    let mut blocks = Vec::new();
+    for pair in pairs {
+        match pair {
+            Ast::Pair(key, value) => match **key {
+                Ast::Atom(ref key) if BLOCK_KEYWORDS.contains(&key.as_str()) => {
+                    blocks.push((key.as_str(), &**value))
+                }
+                _ => return None,
+            },
+            _ => return None,
+        }
+    }
+    if blocks[0].0 == "do" {
+        Some(blocks)
+    } else {
+        None
+    }
+}
+
+/// The lines of a `do` block: stab clauses such as those of `case`, or
+/// statements.
+fn block_body(ast: &Ast) -> String {
+    match ast {
+        Ast::List(clauses) if !clauses.is_empty() && clauses.iter().all(is_stab) => clauses
+            .iter()
+            .map(stab_clause)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ if block(ast).is_some_and(|exprs| exprs.is_empty()) => String::new(),
+        _ => statements(ast),
+    }
+}
+
+fn is_stab(ast: &Ast) -> bool {
+    is_call_to(ast, "->", 2)
+}
+
+/// Prints `args -> body`, with the body on its own lines when it has more
+/// than one.
+fn stab_clause(ast: &Ast) -> String {
+    let (args, body) = match ast {
+        Ast::Call(_, _, args) => (&args[0], &args[1]),
+        _ => unreachable!(),
+    };
+    let head = match args {
+        Ast::List(args) => stab_args(args),
+        args => expr(args),
+    };
+    let body = statements(body);
+    let arrow = if head.is_empty() {
+        "->".to_string()
+    } else {
+        format!("{} ->", head)
+    };
+    if body.contains('\n') {
+        format!("{}\n{}", arrow, indent(&body))
+    } else {
+        format!("{} {}", arrow, body)
+    }
+}
+
+/// Clause arguments, where a guard is a single `when` wrapping all of them.
+fn stab_args(args: &[Ast]) -> String {
+    if let [Ast::Call(callee, _, when_args)] = args {
+        if **callee == Ast::Atom("when".into()) && when_args.len() > 2 {
+            let (guard, params) = when_args.split_last().unwrap();
+            return format!(
+                "{} when {}",
+                arguments(params),
+                operand(guard, 50, Assoc::Right)
+            );
+        }
+    }
+    arguments(args)
+}
+
+fn special_form(name: &str, args: &[Ast]) -> Option<String> {
+    Some(match name {
+        "__block__" if args.len() == 1 => expr(&args[0]),
+        "__block__" => format!("({})", block_inline_exprs(args)),
+        "__aliases__" => args
+            .iter()
+            .enumerate()
+            .map(|(i, segment)| match segment {
+                Ast::Atom(s) => s.to_string(),
+                _ if i == 0 => operand(segment, 310, Assoc::Left),
+                _ => expr(segment),
+            })
+            .collect::<Vec<_>>()
+            .join("."),
+        "{}" => format!(
+            "{{{}}}",
+            args.iter().map(argument).collect::<Vec<_>>().join(", ")
+        ),
+        "%{}" => format!("%{{{}}}", map_fields(args)),
+        "%" if args.len() == 2 => match args[1] {
+            Ast::Call(ref callee, _, ref fields) if **callee == Ast::Atom("%{}".into()) => {
+                format!(
+                    "%{}{{{}}}",
+                    operand(&args[0], 310, Assoc::Left),
+                    map_fields(fields)
+                )
+            }
+            _ => return None,
+        },
+        "<<>>" => bitstring(args),
+        "fn" if !args.is_empty() && args.iter().all(is_stab) => {
+            if args.len() == 1 {
+                let clause = stab_clause(&args[0]);
+                if !clause.contains('\n') {
+                    return Some(format!("fn {} end", clause));
+                }
+            }
+            let clauses: Vec<String> = args.iter().map(stab_clause).collect();
+            format!("fn\n{}\nend", indent(&clauses.join("\n")))
+        }
+        _ => return None,
+    })
+}
+
+fn block_inline(ast: &Ast) -> String {
+    block_inline_exprs(block(ast).unwrap_or(&[]))
+}
+
+fn block_inline_exprs(exprs: &[Ast]) -> String {
+    exprs.iter().map(expr).collect::<Vec<_>>().join("; ")
+}
+
+fn map_fields(args: &[Ast]) -> String {
+    match args {
+        [Ast::Call(callee, _, update)]
+            if **callee == Ast::Atom("|".into()) && update.len() == 2 =>
+        {
+            let fields = match update[1] {
+                Ast::List(ref pairs) => pairs_or_keyword(pairs),
+                ref other => expr(other),
+            };
+            format!("{} | {}", operand(&update[0], 70, Assoc::Left), fields)
+        }
+        _ => pairs_or_keyword(args),
+    }
+}
+
+/// Map or struct fields: keyword syntax when every key is an atom, `=>`
+/// otherwise.
+fn pairs_or_keyword(pairs: &[Ast]) -> String {
+    if is_keyword(pairs) {
+        return keyword(pairs);
+    }
+    pairs
+        .iter()
+        .map(|pair| match pair {
+            Ast::Pair(key, value) => format!(
+                "{} => {}",
+                operand(key, 80, Assoc::Left),
+                operand(value, 80, Assoc::Right)
+            ),
+            other => argument(other),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn bitstring(args: &[Ast]) -> String {
+    if let Some(s) = interpolated(args, '"') {
+        return format!("\"{}\"", s);
+    }
+    let segments: Vec<String> = args
+        .iter()
+        .map(|segment| match segment {
+            // Type specifications are written without spaces.
+            Ast::Call(callee, _, spec) if **callee == Ast::Atom("::".into()) && spec.len() == 2 => {
+                format!(
+                    "{}::{}",
+                    operand(&spec[0], 60, Assoc::Left),
+                    operand(&spec[1], 60, Assoc::Right)
+                )
+            }
+            other => argument(other),
+        })
+        .collect();
+    let s = segments.join(", ");
+    // `<<<` would lex as an operator.
+    if s.starts_with('<') || s.ends_with('>') {
+        format!("<< {} >>", s)
+    } else {
+        format!("<<{}>>", s)
+    }
+}
+
+/// Prints the parts of an interpolated string or sigil if every part is
+/// either a literal string or an interpolation.
+fn interpolated(parts: &[Ast], closing: char) -> Option<String> {
+    let mut s = String::new();
+    for part in parts {
+        match part {
+            Ast::String(text) => s.push_str(&escape(text, closing)),
+            _ => s.push_str(&format!("#{{{}}}", interpolation(part)?)),
+        }
+    }
+    Some(s)
+}
+
+/// The expression inside `"#{expr}"`, which quotes to
+/// `{:"::", _, [{{:., _, [Kernel, :to_string]}, _, [expr]}, {:binary, _, _}]}`.
+fn interpolation(ast: &Ast) -> Option<String> {
+    if let Ast::Call(callee, _, args) = ast {
+        if **callee != Ast::Atom("::".into()) || args.len() != 2 {
+            return None;
+        }
+        match args[1] {
+            Ast::Var(ref name, _, _) if name == "binary" => {}
+            _ => return None,
+        }
+        if let Ast::Call(ref callee, _, ref args) = args[0] {
+            if let Ast::Call(ref dot, _, ref target) = **callee {
+                let kernel_to_string = vec![
+                    Ast::Atom("Elixir.Kernel".into()),
+                    Ast::Atom("to_string".into()),
+                ];
+                if **dot == Ast::Atom(".".into()) && *target == kernel_to_string && args.len() == 1
+                {
+                    return Some(statements(&args[0]));
+                }
+            }
+        }
+    }
+    None
+}
+
+fn sigil(name: &str, meta: &[(Symbol, Ast)], args: &[Ast]) -> Option<String> {
+    if !name.starts_with("sigil_") || args.len() != 2 {
+        return None;
+    }
+    let letters = &name["sigil_".len()..];
+    let valid = match letters.chars().next() {
+        Some(c) if c.is_ascii_lowercase() => letters.len() == 1,
+        Some(c) if c.is_ascii_uppercase() => letters
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()),
+        _ => false,
+    };
+    if !valid {
+        return None;
+    }
+    let parts = match args[0] {
+        Ast::Call(ref callee, _, ref parts) if **callee == Ast::Atom("<<>>".into()) => parts,
+        _ => return None,
+    };
+    let modifiers = match args[1] {
+        Ast::List(ref chars) => chars
+            .iter()
+            .map(|c| match c {
+                Ast::Int(i) => i
+                    .to_string()
+                    .parse::<u32>()
+                    .ok()
+                    .and_then(::std::char::from_u32),
+                _ => None,
+            })
+            .collect::<Option<String>>()?,
+        _ => return None,
+    };
+
+    let opening = meta
+        .iter()
+        .find(|(key, _)| key == "delimiter")
+        .and_then(|(_, value)| match value {
+            Ast::String(s) if s.chars().count() == 1 => s.chars().next(),
+            _ => None,
+        })
+        .unwrap_or('"');
+    let closing = match opening {
+        '(' => ')',
+        '[' => ']',
+        '{' => '}',
+        '<' => '>',
+        c => c,
+    };
+
+    // Sigil contents are kept raw, so only the closing delimiter needs
+    // escaping.
+    let mut content = String::new();
+    for part in parts {
+        match part {
+            Ast::String(text) => content.push_str(&escape_delimiter(text, closing)),
+            _ => content.push_str(&format!("#{{{}}}", interpolation(part)?)),
+        }
+    }
+    Some(format!(
+        "~{}{}{}{}{}",
+        letters, opening, content, closing, modifiers
+    ))
+}
+
+fn escape_delimiter(text: &str, closing: char) -> String {
+    let mut s = String::with_capacity(text.len());
+    let mut escaped = false;
+    for c in text.chars() {
+        if c == closing && !escaped {
+            s.push('\\');
+        }
+        escaped = c == '\\' && !escaped;
+        s.push(c);
+    }
+    s
+}
+
+fn operator(name: &str, args: &[Ast]) -> Option<String> {
+    match args {
+        [arg] if name == "not" && is_call_to(arg, "in", 2) => {
+            if let Ast::Call(_, _, ref operands) = *arg {
+                return Some(format!(
+                    "{} not in {}",
+                    operand(&operands[0], 170, Assoc::Left),
+                    operand(&operands[1], 170, Assoc::Right)
+                ));
+            }
+            None
+        }
+        [arg] => unary_op(name).map(|prec| unary(name, prec, arg)),
+        [left, right] => binary_op(name).map(|(_, prec)| {
+            let left = operand(left, prec, Assoc::Left);
+            let right = operand(right, prec, Assoc::Right);
+            match name {
+                ".." | "//" => format!("{}{}{}", left, name, right),
+                _ => format!("{} {} {}", left, name, right),
+            }
+        }),
+        [first, last, step] if name == "..//" => Some(format!(
+            "{}..{}//{}",
+            operand(first, 200, Assoc::Left),
+            operand(last, 200, Assoc::Right),
+            operand(step, 190, Assoc::Right)
+        )),
+        _ => None,
+    }
+}
+
+fn unary(name: &str, prec: u32, arg: &Ast) -> String {
+    match name {
+        "@" => match arg {
+            Ast::Var(attr, _, _) => format!("@{}", attr),
+            Ast::Call(callee, _, args) => match **callee {
+                Ast::Atom(ref attr) if is_identifier(attr) && args.len() == 1 => {
+                    format!("@{} {}", attr, argument(&args[0]))
+                }
+                _ => format!("@({})", expr(arg)),
+            },
+            _ => format!("@({})", expr(arg)),
+        },
+        "&" => match arg {
+            Ast::Int(i) => format!("&{}", i),
+            _ => match capture(arg) {
+                Some(s) => format!("&{}", s),
+                None if precedence(arg).is_some() => format!("&({})", expr(arg)),
+                None => format!("&{}", expr(arg)),
+            },
+        },
+        "not" => format!("not {}", operand(arg, prec, Assoc::Right)),
+        _ => {
+            // `- -x` must not become `--x`, and `-1` is a literal.
+            let s = match arg {
+                Ast::Call(..) if precedence(arg).is_some() => format!("({})", expr(arg)),
+                Ast::Int(i) if i.sign() == ::num_bigint::Sign::Minus => format!("({})", i),
+                _ => operand(arg, prec, Assoc::Right),
+            };
+            format!("{}{}", name, s)
+        }
+    }
+}
+
+/// `&name/arity` and `&Mod.name/arity`, written without spaces.
+fn capture(arg: &Ast) -> Option<String> {
+    match arg {
+        Ast::Call(callee, _, args) if **callee == Ast::Atom("/".into()) && args.len() == 2 => {
+            let arity = match args[1] {
+                Ast::Int(ref i) => i.to_string(),
+                _ => return None,
+            };
+            let name = match args[0] {
+                Ast::Var(ref name, _, _) => name.to_string(),
+                Ast::Call(ref callee, _, ref call_args) if call_args.is_empty() => match **callee {
+                    Ast::Atom(ref name) if is_identifier(name) => name.to_string(),
+                    Ast::Call(..) => dot_call(callee, &[], call_args)
+                        .map(|s| s.trim_end_matches("()").to_string())?,
+                    _ => return None,
+                },
+                _ => return None,
+            };
+            Some(format!("{}/{}", name, arity))
+        }
+        _ => None,
+    }
+}
+
+/// Remote calls `Mod.fun(args)`, anonymous function calls `fun.(args)`,
+/// field access `map.key` and `data[key]`.
+fn dot_call(callee: &Ast, meta: &[(Symbol, Ast)], args: &[Ast]) -> Option<String> {
+    let target = match callee {
+        Ast::Call(dot, _, target) if **dot == Ast::Atom(".".into()) => target,
+        _ => return None,
+    };
+    match target.as_slice() {
+        [fun] => Some(format!(
+            "{}.({})",
+            operand(fun, 310, Assoc::Left),
+            arguments(args)
+        )),
+        [left, Ast::Atom(name)] => {
+            let access = [
+                Ast::Atom("Elixir.Access".into()),
+                Ast::Call(
+                    Box::new(Ast::Atom("__aliases__".into())),
+                    vec![],
+                    vec![Ast::Atom("Access".into())],
+                ),
+            ];
+            let is_access = name == "get"
+                && args.len() == 2
+                && access.iter().any(|a| without_meta(a) == without_meta(left));
+            if is_access {
+                return Some(format!(
+                    "{}[{}]",
+                    operand(&args[0], 310, Assoc::Left),
+                    expr(&args[1])
+                ));
+            }
+            let head = format!(
+                "{}.{}",
+                operand(left, 310, Assoc::Left),
+                if is_identifier(name) || binary_op(name).is_some() || unary_op(name).is_some() {
+                    name.to_string()
+                } else {
+                    format!("\"{}\"", escape(name, '"'))
+                }
+            );
+            let no_parens = meta
+                .iter()
+                .any(|(key, value)| key == "no_parens" && *value == Ast::Atom("true".into()));
+            if args.is_empty() && no_parens {
+                Some(head)
+            } else {
+                Some(call_with_args(&head, args))
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Compares aliases ignoring their metadata.
+fn without_meta(ast: &Ast) -> Ast {
+    match ast {
+        Ast::Call(callee, _, args) => Ast::Call(callee.clone(), vec![], args.clone()),
+        other => other.clone(),
+    }
+}
+
+/// Prints an operand of an operator with precedence `prec`, adding
+/// parentheses if the operand binds looser, or equally on the side the
+/// operator does not associate to.
+fn operand(ast: &Ast, prec: u32, side: Assoc) -> String {
+    if block(ast).is_some_and(|exprs| exprs.len() != 1) {
+        return format!("({})", block_inline(ast));
+    }
+    let needs_parens = match precedence(ast) {
+        Some(inner) if inner < prec => true,
+        Some(inner) if inner == prec => match ast {
+            Ast::Call(callee, _, args) if args.len() == 2 => match **callee {
+                Ast::Atom(ref op) => binary_op(op).is_none_or(|(assoc, _)| assoc != side),
+                _ => true,
+            },
+            _ => true,
+        },
+        _ => false,
+    };
+    if needs_parens {
+        format!("({})", expr(ast))
+    } else {
+        expr(ast)
+    }
+}
+
+fn list(elems: &[Ast]) -> String {
+    if is_keyword(elems) {
+        format!("[{}]", keyword(elems))
+    } else {
+        format!(
+            "[{}]",
+            elems.iter().map(argument).collect::<Vec<_>>().join(", ")
+        )
+    }
+}
+
+fn is_keyword(elems: &[Ast]) -> bool {
+    !elems.is_empty()
+        && elems.iter().all(|elem| match elem {
+            Ast::Pair(key, _) => matches!(**key, Ast::Atom(_)),
+            _ => false,
+        })
+}
+
+fn keyword(pairs: &[Ast]) -> String {
+    pairs
+        .iter()
+        .map(|pair| match pair {
+            Ast::Pair(key, value) => match **key {
+                Ast::Atom(ref key) => format!("{}: {}", keyword_key(key), argument(value)),
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn keyword_key(key: &str) -> String {
+    if is_atom_identifier(key) || OPERATOR_KEYWORDS.contains(&key) {
+        key.to_string()
+    } else {
+        format!("\"{}\"", escape(key, '"'))
+    }
+}
+
+fn atom(s: &str) -> String {
+    match s {
+        "true" | "false" | "nil" => s.to_string(),
+        _ if s.starts_with("Elixir.") && is_alias(&s["Elixir.".len()..]) => {
+            s["Elixir.".len()..].to_string()
+        }
+        _ if is_atom_identifier(s) || OPERATOR_ATOMS.contains(&s) => format!(":{}", s),
+        _ => format!(":\"{}\"", escape(s, '"')),
+    }
+}
+
+fn is_alias(s: &str) -> bool {
+    !s.is_empty()
+        && s.split('.').all(|segment| {
+            let mut chars = segment.chars();
+            chars.next().is_some_and(|c| c.is_ascii_uppercase())
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+}
+
+/// Names usable for variables and local calls.
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c == '_' || c.is_lowercase() => {}
+        _ => return false,
+    }
+    let rest = chars.as_str();
+    let rest = rest
+        .strip_suffix('?')
+        .or_else(|| rest.strip_suffix('!'))
+        .unwrap_or(rest);
+    rest.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Atoms written without quotes, `:foo`, `:Foo` or `:foo@bar`.
+fn is_atom_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c == '_' || c.is_alphabetic() => {}
+        _ => return false,
+    }
+    let rest = chars.as_str();
+    let rest = rest
+        .strip_suffix('?')
+        .or_else(|| rest.strip_suffix('!'))
+        .unwrap_or(rest);
+    rest.chars()
+        .all(|c| c.is_alphanumeric() || c == '_' || c == '@')
+}
+
+fn float(f: f64) -> String {
+    let s = format!("{:?}", f);
+    // Elixir needs a digit after the point, as in `1.0e20`.
+    match s.find('e') {
+        Some(i) if !s[..i].contains('.') => format!("{}.0{}", &s[..i], &s[i..]),
+        _ => s,
+    }
+}
+
+/// Escapes text for a double-quoted string or atom.
+fn escape(s: &str, quote: char) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '#' if chars.peek() == Some(&'{') => out.push_str("\\#"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if c.is_control() => out.push_str(&format!("\\u{{{:X}}}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn indent(s: &str) -> String {
+    s.lines()
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", INDENT, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn print_operators() {
+    let int = |i: i32| Ast::Int(i.into());
+    let sum = local("+", 0, vec![var("a", 0), var("b", 0)]);
+    assert_eq!(
+        local("*", 0, vec![sum.clone(), var("c", 0)]).to_string(),
+        "(a + b) * c"
+    );
+    assert_eq!(
+        local("+", 0, vec![var("c", 0), sum.clone()]).to_string(),
+        "c + (a + b)"
+    );
+    assert_eq!(
+        local("+", 0, vec![sum.clone(), var("c", 0)]).to_string(),
+        "a + b + c"
+    );
+    let concat = local("++", 0, vec![var("a", 0), var("b", 0)]);
+    assert_eq!(
+        local("++", 0, vec![var("c", 0), concat.clone()]).to_string(),
+        "c ++ a ++ b"
+    );
+    assert_eq!(
+        local("++", 0, vec![concat, var("c", 0)]).to_string(),
+        "(a ++ b) ++ c"
+    );
+    assert_eq!(
+        local("-", 0, vec![local("-", 0, vec![var("x", 0)])]).to_string(),
+        "-(-x)"
+    );
+    assert_eq!(local("-", 0, vec![sum.clone()]).to_string(), "-(a + b)");
+    assert_eq!(local("not", 0, vec![sum]).to_string(), "not (a + b)");
+    assert_eq!(
+        local(
+            "not",
+            0,
+            vec![local("in", 0, vec![var("x", 0), var("xs", 0)])]
+        )
+        .to_string(),
+        "x not in xs"
+    );
+    assert_eq!(local("..", 0, vec![int(1), int(10)]).to_string(), "1..10");
+    assert_eq!(
+        local("&", 0, vec![local("/", 0, vec![var("foo", 0), int(1)])]).to_string(),
+        "&foo/1"
+    );
+    assert_eq!(
+        local(
+            "&",
+            0,
+            vec![local("+", 0, vec![local("&", 0, vec![int(1)]), int(1)])]
+        )
+        .to_string(),
+        "&(&1 + 1)"
+    );
+    assert_eq!(
+        local(
+            "@",
+            0,
+            vec![local("doc", 0, vec![Ast::String("hi".to_string())])]
+        )
+        .to_string(),
+        "@doc \"hi\""
+    );
+}
+
+#[test]
+fn print_do_block_arguments() {
+    // A do-block call passed to another call keeps its parentheses, or the
+    // block would attach to the outer call.
+    let if_ = local("if", 0, vec![var("x", 0), kw(vec![("do", var("a", 0))])]);
+    assert_eq!(if_.to_string(), "if x do\n  a\nend");
+    assert_eq!(
+        local("foo", 0, vec![if_.clone()]).to_string(),
+        "foo((if x do\n  a\nend))"
+    );
+    assert_eq!(
+        local("+", 0, vec![if_, Ast::Int(1.into())]).to_string(),
+        "(if x do\n  a\nend) + 1"
+    );
+    let quote = local("quote", 0, vec![kw(vec![("do", var("a", 0))])]);
+    assert_eq!(
+        local("foo", 0, vec![quote]).to_string(),
+        "foo((quote do\n  a\nend))"
+    );
+}
+
+#[test]
+fn print_atoms_and_literals() {
+    for (atom, printed) in &[
+        ("foo", ":foo"),
+        ("<<>>", ":<<>>"),
+        ("%{}", ":%{}"),
+        ("...", ":..."),
+        ("foo bar", ":\"foo bar\""),
+        ("Elixir.Foo.Bar", "Foo.Bar"),
+        ("nil", "nil"),
+    ] {
+        assert_eq!(Ast::Atom((*atom).into()).to_string(), *printed);
+    }
+    assert_eq!(Ast::Float(1e20).to_string(), "1.0e20");
+    assert_eq!(
+        Ast::String("a\"#{b}\n".to_string()).to_string(),
+        r#""a\"\#{b}\n""#
+    );
+    assert_eq!(
+        kw(vec![
+            ("a", Ast::Int(1.into())),
+            ("%{}", var("x", 0)),
+            ("b c", var("y", 0))
+        ])
+        .to_string(),
+        r#"[a: 1, %{}: x, "b c": y]"#
+    );
+}
+
+#[test]
+fn print_calls() {
+    let alias = local("__aliases__", 0, vec![Ast::Atom("Enum".into())]);
+    let map = Ast::Call(
+        Box::new(local(".", 0, vec![alias, Ast::Atom("map".into())])),
+        vec![],
+        vec![var("xs", 0), kw(vec![("into", Ast::List(vec![]))])],
+    );
+    assert_eq!(map.to_string(), "Enum.map(xs, into: [])");
+
+    let sigil = Ast::Call(
+        Box::new(Ast::Atom("sigil_r".into())),
+        vec![("delimiter".into(), Ast::String("/".to_string()))],
+        vec![
+            local("<<>>", 0, vec![Ast::String("a/b".to_string())]),
+            Ast::List(vec![Ast::Int(('i' as u32).into())]),
+        ],
+    );
+    assert_eq!(sigil.to_string(), r"~r/a\/b/i");
+
+    let function = local(
+        "def",
+        0,
+        vec![
+            local("foo", 0, vec![var("x", 0)]),
+            kw(vec![(
+                "do",
+                local(
+                    "__block__",
+                    0,
+                    vec![
+                        local("bar", 0, vec![]),
+                        local(
+                            "case",
+                            0,
+                            vec![
+                                var("x", 0),
+                                kw(vec![(
+                                    "do",
+                                    Ast::List(vec![local(
+                                        "->",
+                                        0,
+                                        vec![Ast::List(vec![Ast::Int(1.into())]), var("y", 0)],
+                                    )]),
+                                )]),
+                            ],
+                        ),
+                    ],
+                ),
+            )]),
+        ],
+    );
+    assert_eq!(
+        function.to_string(),
+        "def foo(x) do\n  bar()\n  case x do\n    1 -> y\n  end\nend"
+    );
+
+    let anonymous = local(
+        "fn",
+        0,
+        vec![local(
+            "->",
+            0,
+            vec![Ast::List(vec![var("x", 0)]), var("x", 0)],
+        )],
+    );
+    assert_eq!(anonymous.to_string(), "fn x -> x end");
+}
@@ -0,0 +1,328 @@
+//! Lexing every file of a Mix project in parallel, with results cached on
+//! disk by content hash so a second run only lexes the files that changed.
+//!
+//! `discover` finds the `.ex` and `.exs` files of a project or umbrella,
+//! leaving out `_build`, `deps`, hidden directories and whatever
+//! `.gitignore` lists. `analyze` runs any per-file analysis over them across
+//! all cores and caches its results; `check` is the lexing analysis the
+//! `project` subcommand runs. Without a parser the cached result is what
+//! lexing found. Analyses built on the AST will cache theirs the same way.
+
+use diagnostics::{self, Diagnostic};
+use ignore::WalkBuilder;
+use lexer::{Lexed, LexicalError};
+use rayon::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use xxhash_rust::xxh3::xxh3_128;
+
+/// Directories that hold build output and dependencies rather than the
+/// project's own code, at any depth so umbrella apps are covered too.
+const SKIPPED_DIRS: &[&str] = &["_build", "deps"];
+
+/// The `.ex` and `.exs` files under `root`, sorted, and the errors for
+/// entries that could not be read, which are left out rather than ending
+/// the walk.
+pub fn discover(root: &Path) -> (Vec<PathBuf>, Vec<io::Error>) {
+    let walker = WalkBuilder::new(root)
+        .require_git(false)
+        .filter_entry(|entry| {
+            let skipped = entry.depth() > 0
+                && entry.file_type().is_some_and(|ty| ty.is_dir())
+                && entry
+                    .file_name()
+                    .to_str()
+                    .is_some_and(|name| SKIPPED_DIRS.contains(&name));
+            !skipped
+        })
+        .build();
+
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                errors.push(io::Error::other(err));
+                continue;
+            }
+        };
+        let is_elixir = entry
+            .path()
+            .extension()
+            .is_some_and(|ext| ext == "ex" || ext == "exs");
+        if is_elixir && entry.file_type().is_some_and(|ty| ty.is_file()) {
+            files.push(entry.into_path());
+        }
+    }
+    files.sort();
+    (files, errors)
+}
+
+/// A hash of a file's contents, which keys its cache entries.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ContentHash(u128);
+
+impl ContentHash {
+    pub fn of(contents: &str) -> Self {
+        ContentHash(xxh3_128(contents.as_bytes()))
+    }
+}
+
+impl fmt::Display for ContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
+/// Analysis results on disk, one JSON file per content hash. Entries that
+/// cannot be read or decoded are misses, so a damaged cache only costs time.
+pub struct Cache {
+    dir: PathBuf,
+}
+
+/// Distinguishes the temporary files of concurrent writers.
+static NEXT_TEMPORARY: AtomicUsize = AtomicUsize::new(0);
+
+impl Cache {
+    /// The cache of `analysis` under `dir`. Each analysis and each version
+    /// of this crate has a directory of its own, so changing either starts
+    /// afresh rather than reading stale results.
+    pub fn new<P: Into<PathBuf>>(dir: P, analysis: &str) -> Self {
+        let name = format!("{}-{}", analysis, env!("CARGO_PKG_VERSION"));
+        Cache {
+            dir: dir.into().join(name),
+        }
+    }
+
+    /// Where a project's cache goes by default, alongside Mix's own build
+    /// output.
+    pub fn default_dir(root: &Path) -> PathBuf {
+        root.join("_build").join("elixir_parser")
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, hash: ContentHash) -> Option<T> {
+        let bytes = fs::read(self.entry(hash)).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    pub fn put<T: Serialize>(&self, hash: ContentHash, value: &T) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        // Written aside and renamed, so readers never see half an entry.
+        let temporary = self.dir.join(format!(
+            "{}.{}.{}.tmp",
+            hash,
+            ::std::process::id(),
+            NEXT_TEMPORARY.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&temporary, serde_json::to_vec(value)?)?;
+        fs::rename(&temporary, self.entry(hash))
+    }
+
+    fn entry(&self, hash: ContentHash) -> PathBuf {
+        self.dir.join(format!("{}.json", hash))
+    }
+}
+
+/// The result of an analysis of one file.
+#[derive(Debug)]
+pub struct Analyzed<T> {
+    pub path: PathBuf,
+    /// The result, or the error reading the file.
+    pub result: io::Result<T>,
+    /// The hash of the contents the result is for. Offsets in the result
+    /// only fit those contents, which the file may no longer have.
+    pub hash: Option<ContentHash>,
+    /// Whether the result came from the cache.
+    pub cached: bool,
+}
+
+/// Runs `analysis` on the contents of each file in parallel, in the order
+/// of `files`. With a cache, results for contents seen before are read
+/// from it and new ones are added; failing to write an entry is ignored.
+pub fn analyze<T, F>(files: &[PathBuf], cache: Option<&Cache>, analysis: F) -> Vec<Analyzed<T>>
+where
+    T: Serialize + DeserializeOwned + Send,
+    F: Fn(&str) -> T + Sync,
+{
+    files
+        .par_iter()
+        .map(|path| {
+            let contents = match fs::read_to_string(path) {
+                Ok(contents) => contents,
+                Err(err) => {
+                    return Analyzed {
+                        path: path.clone(),
+                        result: Err(err),
+                        hash: None,
+                        cached: false,
+                    }
+                }
+            };
+            let hash = ContentHash::of(&contents);
+            if let Some(result) = cache.and_then(|cache| cache.get(hash)) {
+                return Analyzed {
+                    path: path.clone(),
+                    result: Ok(result),
+                    hash: Some(hash),
+                    cached: true,
+                };
+            }
+            let result = analysis(&contents);
+            if let Some(cache) = cache {
+                let _ = cache.put(hash, &result);
+            }
+            Analyzed {
+                path: path.clone(),
+                result: Ok(result),
+                hash: Some(hash),
+                cached: false,
+            }
+        })
+        .collect()
+}
+
+/// What lexing a file found.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Summary {
+    pub tokens: usize,
+    pub errors: Vec<LexicalError>,
+    /// The byte offset where the lexer stopped on input it does not handle
+    /// yet.
+    pub stopped: Option<usize>,
+}
+
+impl Summary {
+    pub fn new(source: &str) -> Self {
+        Summary::from(Lexed::new(source))
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty() && self.stopped.is_none()
+    }
+
+    /// The diagnostics for `source`, the contents the summary was made from.
+    pub fn diagnostics(&self, source: &str) -> Vec<Diagnostic> {
+        diagnostics::lexing(source, &self.errors, self.stopped)
+    }
+}
+
+impl From<Lexed> for Summary {
+    fn from(lexed: Lexed) -> Self {
+        Summary {
+            tokens: lexed.tokens.len(),
+            errors: lexed.errors,
+            stopped: lexed.stopped,
+        }
+    }
+}
+
+/// Lexes every file of the project at `root`, and returns the errors of
+/// entries that could not be read, as `discover` does.
+pub fn check(root: &Path, cache: Option<&Cache>) -> (Vec<Analyzed<Summary>>, Vec<io::Error>) {
+    let (files, errors) = discover(root);
+    (analyze(&files, cache, Summary::new), errors)
+}
+
+#[cfg(test)]
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir =
+        ::std::env::temp_dir().join(format!("elixir_parser-{}-{}", name, ::std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[cfg(test)]
+fn write(root: &Path, path: &str, contents: &str) {
+    let path = root.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+#[test]
+fn discovery_skips_build_output_and_ignored_files() {
+    let root = scratch_dir("discover");
+    write(&root, "mix.exs", ":+");
+    write(&root, "lib/a.ex", ":+");
+    write(&root, "lib/notes.md", ":+");
+    write(&root, "apps/web/lib/b.ex", ":+");
+    write(&root, "apps/web/test/b_test.exs", ":+");
+    write(&root, "apps/web/_build/dev/c.ex", ":+");
+    write(&root, "deps/plug/lib/plug.ex", ":+");
+    write(&root, ".elixir_ls/d.ex", ":+");
+    write(&root, "priv/generated/e.ex", ":+");
+    write(&root, ".gitignore", "/priv/generated\n");
+
+    let (files, errors) = discover(&root);
+    assert!(errors.is_empty());
+    let files: Vec<_> = files
+        .iter()
+        .map(|path| path.strip_prefix(&root).unwrap().to_path_buf())
+        .collect();
+    let expected: Vec<PathBuf> = vec![
+        "apps/web/lib/b.ex".into(),
+        "apps/web/test/b_test.exs".into(),
+        "lib/a.ex".into(),
+        "mix.exs".into(),
+    ];
+    assert_eq!(files, expected);
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn cached_results_are_reused_until_contents_change() {
+    let root = scratch_dir("cache");
+    write(&root, "lib/a.ex", ":+ :-");
+    write(&root, "lib/b.ex", ":+ foo");
+    let cache = Cache::new(Cache::default_dir(&root), "check");
+
+    let (first, _) = check(&root, Some(&cache));
+    assert!(first.iter().all(|file| !file.cached));
+    let summaries: Vec<_> = first
+        .iter()
+        .map(|file| file.result.as_ref().unwrap())
+        .collect();
+    assert_eq!(summaries[0], &Summary::new(":+ :-"));
+    assert_eq!(summaries[1].stopped, Some(3));
+    assert_eq!(first[1].hash, Some(ContentHash::of(":+ foo")));
+
+    write(&root, "lib/b.ex", ":+ :*");
+    let (second, _) = check(&root, Some(&cache));
+    assert_eq!(
+        second.iter().map(|file| file.cached).collect::<Vec<_>>(),
+        vec![true, false]
+    );
+    assert!(second[1].result.as_ref().unwrap().is_ok());
+    assert_eq!(
+        first[0].result.as_ref().unwrap(),
+        second[0].result.as_ref().unwrap()
+    );
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn unreadable_entries_are_reported_and_skipped() {
+    let root = scratch_dir("unreadable");
+    let (files, errors) = discover(&root.join("missing"));
+    assert!(files.is_empty());
+    assert_eq!(errors.len(), 1);
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn stale_offsets_do_not_panic() {
+    // Diagnostics for contents that have since shrunk point at their end.
+    let summary = Summary::new(":+ :- foo");
+    let diagnostics = summary.diagnostics(":+");
+    assert_eq!(
+        (diagnostics[0].label.start, diagnostics[0].label.end),
+        (2, 2)
+    );
+}
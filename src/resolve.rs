@@ -0,0 +1,1064 @@
+//! What module names and unqualified calls refer to.
+//!
+//! `alias`, `import` and `require` are lexical: they apply to the rest of
+//! the block they are in, including modules, functions and clauses nested
+//! in it, and not outside it. `resolve` walks the quoted form keeping that
+//! scope and expands every alias to the module it names, recording how the
+//! module is used. Each unqualified call gets the modules it could be
+//! imported from under the `only:` and `except:` of the imports in scope.
+//! `Kernel` is imported everywhere but is not listed, since without its
+//! exports every call would name it; a call that lists no imports is to a
+//! local function or to `Kernel`.
+
+use ast::{Ast, Meta};
+#[cfg(test)]
+use builders::{alias, block_of, call, kw, remote, var};
+use num_traits::ToPrimitive;
+use symbol::Symbol;
+
+/// Calls to these are syntax rather than functions that could be imported.
+const SPECIAL_FORMS: &[&str] = &[
+    "__aliases__",
+    "__block__",
+    "__MODULE__",
+    "__DIR__",
+    "__ENV__",
+    "__CALLER__",
+    "__STACKTRACE__",
+    "{}",
+    "%{}",
+    "%",
+    "<<>>",
+    ".",
+    "@",
+    "&",
+    "^",
+    "=",
+    "::",
+    "->",
+    "<-",
+    "\\\\",
+    "|",
+    "when",
+    "fn",
+    "case",
+    "cond",
+    "for",
+    "receive",
+    "try",
+    "with",
+    "quote",
+    "unquote",
+    "unquote_splicing",
+    "super",
+    "alias",
+    "import",
+    "require",
+    "use",
+    "defmodule",
+    "defprotocol",
+    "defimpl",
+    "def",
+    "defp",
+    "defmacro",
+    "defmacrop",
+    "defguard",
+    "defguardp",
+    "defdelegate",
+];
+
+#[derive(Clone, Debug, Default)]
+pub struct Resolution<'ast> {
+    /// Every reference to a module, in source order.
+    pub modules: Vec<ModuleRef<'ast>>,
+    /// Every unqualified call, in source order.
+    pub calls: Vec<LocalCall<'ast>>,
+}
+
+/// How a module is referred to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Usage {
+    Alias,
+    Import,
+    Require,
+    Use,
+    /// A struct, as in `%User{}`.
+    Struct,
+    /// `@behaviour`.
+    Behaviour,
+    /// The protocol or a target of a `defimpl`.
+    Impl,
+    /// The module of a remote call.
+    Call,
+    /// Anything else, such as a module passed as a value.
+    Reference,
+}
+
+#[derive(Clone, Debug)]
+pub struct ModuleRef<'ast> {
+    /// The alias, or the atom of an Erlang module.
+    pub node: &'ast Ast,
+    /// The module, such as `App.Accounts.User`. Erlang modules keep their
+    /// colon, as in `:lists`.
+    pub module: Symbol,
+    pub usage: Usage,
+    /// The module the reference is in, if any.
+    pub within: Option<Symbol>,
+    /// Whether the reference is in the body of a function or macro, rather
+    /// than in the module body, which runs at compile time.
+    pub in_function: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct LocalCall<'ast> {
+    /// The call, or the `&name/arity` capture.
+    pub node: &'ast Ast,
+    pub name: Symbol,
+    /// The arity, counting the argument piped in with `|>`.
+    pub arity: usize,
+    /// The explicitly imported modules that may define the function, in
+    /// the order they were imported.
+    pub imports: Vec<Symbol>,
+    pub within: Option<Symbol>,
+    pub in_function: bool,
+}
+
+impl<'ast> ModuleRef<'ast> {
+    /// The line of the alias. Atoms have no metadata to say.
+    pub fn line(&self) -> Option<usize> {
+        line(self.node)
+    }
+}
+
+impl<'ast> LocalCall<'ast> {
+    pub fn line(&self) -> Option<usize> {
+        line(self.node)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Import {
+    module: Symbol,
+    only: Option<Vec<(Symbol, usize)>>,
+    except: Vec<(Symbol, usize)>,
+}
+
+impl Import {
+    fn allows(&self, name: Symbol, arity: usize) -> bool {
+        let listed = |list: &[(Symbol, usize)]| list.contains(&(name, arity));
+        self.only.as_ref().is_none_or(|only| listed(only)) && !listed(&self.except)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Scope {
+    module: Option<Symbol>,
+    /// Later aliases shadow earlier ones of the same name.
+    aliases: Vec<(Symbol, Symbol)>,
+    imports: Vec<Import>,
+    in_function: bool,
+}
+
+impl Scope {
+    fn alias(&mut self, name: Symbol, module: Symbol) {
+        self.aliases.push((name, module));
+    }
+
+    fn lookup(&self, name: Symbol) -> Option<Symbol> {
+        self.aliases
+            .iter()
+            .rev()
+            .find(|&&(alias, _)| alias == name)
+            .map(|&(_, module)| module)
+    }
+}
+
+/// Resolves the names in `ast`, a file or any part of one.
+pub fn resolve(ast: &Ast) -> Resolution<'_> {
+    let mut resolver = Resolver {
+        resolution: Resolution::default(),
+    };
+    resolver.walk(ast, &mut Scope::default());
+    resolver.resolution
+}
+
+struct Resolver<'ast> {
+    resolution: Resolution<'ast>,
+}
+
+impl<'ast> Resolver<'ast> {
+    /// Walks `ast`. Directives change `scope`, which the caller shares with
+    /// the expressions that follow in the same block.
+    fn walk(&mut self, ast: &'ast Ast, scope: &mut Scope) {
+        let (callee, args) = match ast {
+            Ast::Call(callee, _, args) => (&**callee, &args[..]),
+            Ast::List(elems) => return self.walk_each(elems, scope),
+            Ast::Pair(a, b) => {
+                self.walk(a, &mut scope.clone());
+                return self.walk(b, &mut scope.clone());
+            }
+            _ => return,
+        };
+        let name = match callee {
+            Ast::Atom(name) => *name,
+            Ast::Call(dot, _, target) if **dot == Ast::Atom(".".into()) => {
+                // A remote call: the module, then the arguments.
+                if let Some(module) = target.first() {
+                    self.module_arg(module, Usage::Call, scope);
+                }
+                return self.walk_each(args, scope);
+            }
+            _ => {
+                self.walk(callee, &mut scope.clone());
+                return self.walk_each(args, scope);
+            }
+        };
+        match (name.as_str(), args) {
+            ("__block__", exprs) => {
+                let mut inner = scope.clone();
+                for expr in exprs {
+                    self.walk(expr, &mut inner);
+                }
+            }
+            ("__aliases__", _) => self.module_ref(ast, Usage::Reference, scope),
+            ("defmodule", [module, rest @ ..]) | ("defprotocol", [module, rest @ ..]) => {
+                self.defmodule(module, rest, scope)
+            }
+            ("defimpl", [protocol, rest @ ..]) => self.defimpl(protocol, rest, scope),
+            ("alias", [target, rest @ ..]) => {
+                for (node, module) in self.targets(target, scope) {
+                    self.push_ref(node, module, Usage::Alias, scope);
+                    let name = keyword(rest.first(), "as")
+                        .and_then(|as_| self.expand(as_, &Scope::default()))
+                        .unwrap_or_else(|| last_part(module));
+                    scope.alias(name, module);
+                }
+            }
+            ("require", [target, rest @ ..]) => {
+                for (node, module) in self.targets(target, scope) {
+                    self.push_ref(node, module, Usage::Require, scope);
+                    if let Some(name) = keyword(rest.first(), "as")
+                        .and_then(|as_| self.expand(as_, &Scope::default()))
+                    {
+                        scope.alias(name, module);
+                    }
+                }
+            }
+            ("import", [target, rest @ ..]) => {
+                for (node, module) in self.targets(target, scope) {
+                    self.push_ref(node, module, Usage::Import, scope);
+                    // Importing a module again replaces its earlier import.
+                    scope.imports.retain(|import| import.module != module);
+                    scope.imports.push(Import {
+                        module,
+                        only: keyword(rest.first(), "only").and_then(function_list),
+                        except: keyword(rest.first(), "except")
+                            .and_then(function_list)
+                            .unwrap_or_default(),
+                    });
+                }
+            }
+            ("use", [target, rest @ ..]) => {
+                for (node, module) in self.targets(target, scope) {
+                    self.push_ref(node, module, Usage::Use, scope);
+                }
+                self.walk_each(rest, scope);
+            }
+            ("def", [head, rest @ ..])
+            | ("defp", [head, rest @ ..])
+            | ("defmacro", [head, rest @ ..])
+            | ("defmacrop", [head, rest @ ..])
+            | ("defguard", [head, rest @ ..])
+            | ("defguardp", [head, rest @ ..])
+            | ("defdelegate", [head, rest @ ..]) => {
+                let mut inner = Scope {
+                    in_function: true,
+                    ..scope.clone()
+                };
+                // The head is a pattern, but its guard and the defaults of
+                // its arguments are code.
+                let (call, guard) = match head {
+                    Ast::Call(when, _, parts)
+                        if **when == Ast::Atom("when".into()) && parts.len() == 2 =>
+                    {
+                        (&parts[0], Some(&parts[1]))
+                    }
+                    head => (head, None),
+                };
+                if let Ast::Call(_, _, params) = call {
+                    for param in params {
+                        self.pattern(param, &inner);
+                    }
+                }
+                if let Some(guard) = guard {
+                    self.walk(guard, &mut inner.clone());
+                }
+                if name == "defdelegate" {
+                    // `to:` names a module at compile time.
+                    inner.in_function = false;
+                }
+                self.walk_each(rest, &inner);
+            }
+            ("@", [attribute]) => match local_call(attribute) {
+                Some((name, [value])) if name == "behaviour" => {
+                    self.module_arg(value, Usage::Behaviour, scope)
+                }
+                Some((_, values)) => self.walk_each(values, scope),
+                None => {}
+            },
+            ("%", [module, fields]) => {
+                self.module_arg(module, Usage::Struct, scope);
+                self.walk(fields, &mut scope.clone());
+            }
+            ("|>", [lhs, rhs]) => {
+                self.walk(lhs, &mut scope.clone());
+                match local_call(rhs) {
+                    Some((name, args)) if !is_special(name) => {
+                        self.push_call(rhs, name, args.len() + 1, scope);
+                        self.walk_each(args, scope);
+                    }
+                    _ => self.walk(rhs, &mut scope.clone()),
+                }
+            }
+            ("&", [capture]) => match local_call(capture).map(|(name, args)| (name.as_str(), args))
+            {
+                Some(("/", [function, Ast::Int(arity)])) => match (function, arity.to_usize()) {
+                    (Ast::Var(name, _, _), Some(arity)) => self.push_call(ast, *name, arity, scope),
+                    // `&Mod.fun/2`: the module is all there is to resolve.
+                    (function, _) => self.walk(function, &mut scope.clone()),
+                },
+                _ => self.walk(capture, &mut scope.clone()),
+            },
+            _ => {
+                if !is_special(name) {
+                    self.push_call(ast, name, args.len(), scope);
+                }
+                self.walk_each(args, scope);
+            }
+        }
+    }
+
+    /// Walks a pattern, such as a function parameter. It binds rather than
+    /// calls, so only the modules it names are recorded: structs, aliases,
+    /// and whatever a `^` pin or a default argument refers to.
+    fn pattern(&mut self, ast: &'ast Ast, scope: &Scope) {
+        let (callee, args) = match ast {
+            Ast::Call(callee, _, args) => (&**callee, &args[..]),
+            Ast::List(elems) => {
+                for elem in elems {
+                    self.pattern(elem, scope);
+                }
+                return;
+            }
+            Ast::Pair(a, b) => {
+                self.pattern(a, scope);
+                return self.pattern(b, scope);
+            }
+            _ => return,
+        };
+        match (callee, args) {
+            (Ast::Atom(name), _) if *name == "__aliases__" => {
+                self.module_ref(ast, Usage::Reference, scope)
+            }
+            (Ast::Atom(name), [module, fields]) if *name == "%" => {
+                match module {
+                    Ast::Atom(_) => self.module_ref(module, Usage::Struct, scope),
+                    Ast::Call(callee, _, _) if **callee == Ast::Atom("__aliases__".into()) => {
+                        self.module_ref(module, Usage::Struct, scope)
+                    }
+                    module => self.pattern(module, scope),
+                }
+                self.pattern(fields, scope);
+            }
+            (Ast::Atom(name), [pinned]) if *name == "^" => self.walk(pinned, &mut scope.clone()),
+            (Ast::Atom(name), [pattern, default]) if *name == "\\\\" => {
+                self.pattern(pattern, scope);
+                self.walk(default, &mut scope.clone());
+            }
+            (Ast::Atom(_), args) => {
+                for arg in args {
+                    self.pattern(arg, scope);
+                }
+            }
+            (callee, args) => {
+                self.pattern(callee, scope);
+                for arg in args {
+                    self.pattern(arg, scope);
+                }
+            }
+        }
+    }
+
+    /// Walks each of `asts` in a scope of its own, so directives in one do
+    /// not reach the others.
+    fn walk_each(&mut self, asts: &'ast [Ast], scope: &Scope) {
+        for ast in asts {
+            self.walk(ast, &mut scope.clone());
+        }
+    }
+
+    fn defmodule(&mut self, module: &'ast Ast, rest: &'ast [Ast], scope: &mut Scope) {
+        let written = match self.expand(module, scope) {
+            Some(written) => written,
+            None => return,
+        };
+        // Nesting prefixes the name with the enclosing module's and aliases
+        // its first part, unless that part is an alias already.
+        let first = match module {
+            Ast::Call(_, _, parts) => match parts.first() {
+                Some(Ast::Atom(first)) if scope.lookup(*first).is_none() && *first != "Elixir" => {
+                    Some(*first)
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        let name = match (scope.module, first) {
+            (Some(outer), Some(first)) => {
+                scope.alias(first, format!("{}.{}", outer, first).into());
+                Symbol::from(format!("{}.{}", outer, written))
+            }
+            _ => written,
+        };
+        let inner = Scope {
+            module: Some(name),
+            in_function: false,
+            ..scope.clone()
+        };
+        self.walk_each(rest, &inner);
+    }
+
+    /// Walks a `defimpl` once for each of its targets, as the module
+    /// `Protocol.Target` the compiler defines for it.
+    fn defimpl(&mut self, protocol: &'ast Ast, rest: &'ast [Ast], scope: &mut Scope) {
+        let pairs = rest.iter().flat_map(|opts| match opts {
+            Ast::List(pairs) => &pairs[..],
+            _ => &[],
+        });
+        let mut targets = None;
+        let mut bodies = Vec::new();
+        for pair in pairs {
+            match pair {
+                Ast::Pair(key, value) if **key == Ast::Atom("for".into()) => {
+                    targets = Some(match &**value {
+                        Ast::List(targets) => &targets[..],
+                        value => ::std::slice::from_ref(value),
+                    });
+                }
+                Ast::Pair(_, value) => bodies.push(&**value),
+                _ => {}
+            }
+        }
+        // Without `for:`, the implementation is for the enclosing module.
+        let targets: Vec<_> = match targets {
+            Some(targets) => targets
+                .iter()
+                .map(|target| (Some(target), self.expand(target, scope)))
+                .collect(),
+            None => vec![(None, scope.module)],
+        };
+        // Both names are expanded where the `defimpl` is, where
+        // `__MODULE__` is the enclosing module.
+        let protocol_name = self.expand(protocol, scope);
+        if protocol_name.is_none() || targets.is_empty() {
+            self.module_arg(protocol, Usage::Impl, scope);
+        }
+        for (node, target) in targets {
+            let module = match (protocol_name, target) {
+                (Some(protocol), Some(target)) => Some(format!("{}.{}", protocol, target).into()),
+                _ => None,
+            };
+            let inner = Scope {
+                module,
+                in_function: false,
+                ..scope.clone()
+            };
+            if let Some(protocol_name) = protocol_name {
+                self.push_ref(protocol, protocol_name, Usage::Impl, &inner);
+            }
+            match (node, target) {
+                (Some(node), Some(target)) => self.push_ref(node, target, Usage::Impl, &inner),
+                (Some(node), None) => self.walk(node, &mut scope.clone()),
+                (None, _) => {}
+            }
+            for body in &bodies {
+                self.walk(body, &mut inner.clone());
+            }
+        }
+    }
+
+    /// Records a module given as an argument, or walks the argument if it
+    /// is some other expression.
+    fn module_arg(&mut self, ast: &'ast Ast, usage: Usage, scope: &Scope) {
+        match ast {
+            Ast::Call(callee, _, _) if **callee == Ast::Atom("__aliases__".into()) => {
+                self.module_ref(ast, usage, scope)
+            }
+            Ast::Atom(_) => self.module_ref(ast, usage, scope),
+            ast => self.walk(ast, &mut scope.clone()),
+        }
+    }
+
+    fn module_ref(&mut self, ast: &'ast Ast, usage: Usage, scope: &Scope) {
+        if let Some(module) = self.expand(ast, scope) {
+            self.push_ref(ast, module, usage, scope);
+        }
+    }
+
+    fn push_ref(&mut self, node: &'ast Ast, module: Symbol, usage: Usage, scope: &Scope) {
+        self.resolution.modules.push(ModuleRef {
+            node,
+            module,
+            usage,
+            within: scope.module,
+            in_function: scope.in_function,
+        });
+    }
+
+    fn push_call(&mut self, node: &'ast Ast, name: Symbol, arity: usize, scope: &Scope) {
+        let imports = scope
+            .imports
+            .iter()
+            .filter(|import| import.allows(name, arity))
+            .map(|import| import.module)
+            .collect();
+        self.resolution.calls.push(LocalCall {
+            node,
+            name,
+            arity,
+            imports,
+            within: scope.module,
+            in_function: scope.in_function,
+        });
+    }
+
+    /// The modules a directive names: one, or several for `Foo.{Bar, Baz}`.
+    fn targets(&mut self, target: &'ast Ast, scope: &Scope) -> Vec<(&'ast Ast, Symbol)> {
+        if let Ast::Call(callee, _, nested) = target {
+            if let Ast::Call(dot, _, parts) = &**callee {
+                if let ([base, Ast::Atom(braces)], true) =
+                    (&parts[..], **dot == Ast::Atom(".".into()))
+                {
+                    if *braces == "{}" {
+                        let base = match self.expand(base, scope) {
+                            Some(base) => base,
+                            None => return vec![],
+                        };
+                        return nested
+                            .iter()
+                            .filter_map(|alias| {
+                                let name = self.expand(alias, &Scope::default())?;
+                                Some((alias, format!("{}.{}", base, name).into()))
+                            })
+                            .collect();
+                    }
+                }
+            }
+        }
+        match self.expand(target, scope) {
+            Some(module) => vec![(target, module)],
+            None => {
+                self.walk(target, &mut scope.clone());
+                vec![]
+            }
+        }
+    }
+
+    /// The module an alias, `__MODULE__` or Erlang module atom names.
+    fn expand(&self, ast: &Ast, scope: &Scope) -> Option<Symbol> {
+        expand_alias(ast, scope.module, |name| scope.lookup(name))
+    }
+}
+
+/// The module an alias, `__MODULE__` or Erlang module atom names, written
+/// as Elixir does without the `Elixir.` prefix: `Elixir.Foo` is `Foo` and
+/// `:foo` stays `:foo`. `module` is the one `__MODULE__` stands for, and
+/// `lookup` gives what the first part of an alias is an alias for.
+pub fn expand_alias(
+    ast: &Ast,
+    module: Option<Symbol>,
+    lookup: impl Fn(Symbol) -> Option<Symbol>,
+) -> Option<Symbol> {
+    let parts = match ast {
+        Ast::Atom(name) if *name != "nil" && *name != "true" && *name != "false" => {
+            return Some(format!(":{}", name).into())
+        }
+        Ast::Var(name, _, _) if *name == "__MODULE__" => return module,
+        Ast::Call(callee, _, parts) if **callee == Ast::Atom("__aliases__".into()) => parts,
+        _ => return None,
+    };
+    let (first, rest) = parts.split_first()?;
+    let mut name = match first {
+        Ast::Var(var, _, _) if *var == "__MODULE__" => module?.to_string(),
+        Ast::Atom(first) if *first == "Elixir" => String::new(),
+        Ast::Atom(first) => lookup(*first).unwrap_or(*first).to_string(),
+        _ => return None,
+    };
+    for part in rest {
+        match part {
+            Ast::Atom(part) => {
+                if !name.is_empty() {
+                    name.push('.');
+                }
+                name.push_str(part);
+            }
+            _ => return None,
+        }
+    }
+    Some(name.into())
+}
+
+fn is_special(name: Symbol) -> bool {
+    SPECIAL_FORMS.contains(&name.as_str())
+}
+
+fn local_call(ast: &Ast) -> Option<(Symbol, &[Ast])> {
+    match ast {
+        Ast::Call(callee, _, args) => match **callee {
+            Ast::Atom(name) => Some((name, args)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The value of `key` in the keyword list of options `opts`.
+fn keyword<'a>(opts: Option<&'a Ast>, key: &str) -> Option<&'a Ast> {
+    match opts? {
+        Ast::List(pairs) => pairs.iter().find_map(|pair| match pair {
+            Ast::Pair(k, value) if **k == Ast::Atom(key.into()) => Some(&**value),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+/// The functions of `only: [map: 2]`. `only: :functions` and the like
+/// restrict by kind, which needs the module's exports, so they allow all.
+fn function_list(list: &Ast) -> Option<Vec<(Symbol, usize)>> {
+    match list {
+        Ast::List(pairs) => Some(
+            pairs
+                .iter()
+                .filter_map(|pair| match pair {
+                    Ast::Pair(name, arity) => match (&**name, &**arity) {
+                        (Ast::Atom(name), Ast::Int(arity)) => Some((*name, arity.to_usize()?)),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// The last part of a module name, which `alias` defines by default.
+fn last_part(module: Symbol) -> Symbol {
+    module.rsplit('.').next().unwrap_or(&module).into()
+}
+
+fn line(ast: &Ast) -> Option<usize> {
+    let meta: &Meta = match ast {
+        Ast::Var(_, meta, _) | Ast::Call(_, meta, _) => meta,
+        _ => return None,
+    };
+    match meta.iter().find(|(key, _)| *key == "line") {
+        Some((_, Ast::Int(line))) => line.to_usize(),
+        _ => None,
+    }
+}
+
+/// defmodule App.Web do
+///   alias App.{Repo, Accounts}
+///   alias Accounts.User, as: U
+///   import Ecto.Query, only: [from: 2]
+///   import Enum, except: [map: 2]
+///   require Logger
+///   @behaviour Plug
+///
+///   defmodule Helpers do
+///     def h(x), do: x
+///   end
+///
+///   def show(id) do
+///     alias App.Cache
+///     user = Repo.get(U, id)
+///     Logger.info(id)
+///     Helpers.h(user)
+///     from(1, 2)
+///     map(user, 1)
+///     id |> filter(&valid?/1)
+///     Cache.get(%U{})
+///   end
+///
+///   def other, do: Cache.get(:lists.sum([]))
+/// end
+#[cfg(test)]
+fn sample() -> Ast {
+    let show = call(
+        "def",
+        13,
+        vec![
+            call("show", 13, vec![var("id", 0)]),
+            kw(vec![(
+                "do",
+                block_of(vec![
+                    call("alias", 14, vec![alias("App.Cache", 14)]),
+                    call(
+                        "=",
+                        15,
+                        vec![
+                            var("user", 0),
+                            remote(
+                                alias("Repo", 15),
+                                "get",
+                                15,
+                                vec![alias("U", 15), var("id", 0)],
+                            ),
+                        ],
+                    ),
+                    remote(alias("Logger", 16), "info", 16, vec![var("id", 0)]),
+                    remote(alias("Helpers", 17), "h", 17, vec![var("user", 0)]),
+                    call("from", 18, vec![Ast::Int(1.into()), Ast::Int(2.into())]),
+                    call("map", 19, vec![var("user", 0), Ast::Int(1.into())]),
+                    call(
+                        "|>",
+                        20,
+                        vec![
+                            var("id", 0),
+                            call(
+                                "filter",
+                                20,
+                                vec![call(
+                                    "&",
+                                    20,
+                                    vec![call("/", 20, vec![var("valid?", 0), Ast::Int(1.into())])],
+                                )],
+                            ),
+                        ],
+                    ),
+                    remote(
+                        alias("Cache", 21),
+                        "get",
+                        21,
+                        vec![call("%", 21, vec![alias("U", 21), call("%{}", 21, vec![])])],
+                    ),
+                ]),
+            )]),
+        ],
+    );
+    let other = call(
+        "def",
+        24,
+        vec![
+            var("other", 0),
+            kw(vec![(
+                "do",
+                remote(
+                    alias("Cache", 24),
+                    "get",
+                    24,
+                    vec![remote(
+                        Ast::Atom("lists".into()),
+                        "sum",
+                        24,
+                        vec![Ast::List(vec![])],
+                    )],
+                ),
+            )]),
+        ],
+    );
+    let braces = Ast::Call(
+        Box::new(call(".", 2, vec![alias("App", 2), Ast::Atom("{}".into())])),
+        vec![],
+        vec![alias("Repo", 2), alias("Accounts", 2)],
+    );
+    call(
+        "defmodule",
+        1,
+        vec![
+            alias("App.Web", 1),
+            kw(vec![(
+                "do",
+                block_of(vec![
+                    call("alias", 2, vec![braces]),
+                    call(
+                        "alias",
+                        3,
+                        vec![alias("Accounts.User", 3), kw(vec![("as", alias("U", 3))])],
+                    ),
+                    call(
+                        "import",
+                        4,
+                        vec![
+                            alias("Ecto.Query", 4),
+                            kw(vec![("only", kw(vec![("from", Ast::Int(2.into()))]))]),
+                        ],
+                    ),
+                    call(
+                        "import",
+                        5,
+                        vec![
+                            alias("Enum", 5),
+                            kw(vec![("except", kw(vec![("map", Ast::Int(2.into()))]))]),
+                        ],
+                    ),
+                    call("require", 6, vec![alias("Logger", 6)]),
+                    call("@", 7, vec![call("behaviour", 7, vec![alias("Plug", 7)])]),
+                    call(
+                        "defmodule",
+                        9,
+                        vec![
+                            alias("Helpers", 9),
+                            kw(vec![(
+                                "do",
+                                call(
+                                    "def",
+                                    10,
+                                    vec![
+                                        call("h", 10, vec![var("x", 0)]),
+                                        kw(vec![("do", var("x", 0))]),
+                                    ],
+                                ),
+                            )]),
+                        ],
+                    ),
+                    show,
+                    other,
+                ]),
+            )]),
+        ],
+    )
+}
+
+#[test]
+fn modules() {
+    let ast = sample();
+    let resolution = resolve(&ast);
+    let refs: Vec<_> = resolution
+        .modules
+        .iter()
+        .map(|r| (r.line(), r.module.as_str(), r.usage, r.in_function))
+        .collect();
+    use self::Usage::*;
+    assert_eq!(
+        refs,
+        vec![
+            (Some(2), "App.Repo", Alias, false),
+            (Some(2), "App.Accounts", Alias, false),
+            (Some(3), "App.Accounts.User", Alias, false),
+            (Some(4), "Ecto.Query", Import, false),
+            (Some(5), "Enum", Import, false),
+            (Some(6), "Logger", Require, false),
+            (Some(7), "Plug", Behaviour, false),
+            (Some(14), "App.Cache", Alias, true),
+            (Some(15), "App.Repo", Call, true),
+            (Some(15), "App.Accounts.User", Reference, true),
+            (Some(16), "Logger", Call, true),
+            (Some(17), "App.Web.Helpers", Call, true),
+            (Some(21), "App.Cache", Call, true),
+            (Some(21), "App.Accounts.User", Struct, true),
+            // The alias of `App.Cache` ended with `show`.
+            (Some(24), "Cache", Call, true),
+            (None, ":lists", Call, true),
+        ]
+    );
+    assert!(resolution
+        .modules
+        .iter()
+        .all(|r| r.within == Some("App.Web".into())));
+}
+
+#[test]
+fn local_calls() {
+    let ast = sample();
+    let resolution = resolve(&ast);
+    let calls: Vec<_> = resolution
+        .calls
+        .iter()
+        .map(|call| {
+            let imports: Vec<_> = call.imports.iter().map(|module| module.as_str()).collect();
+            (call.name.as_str(), call.arity, imports)
+        })
+        .collect();
+    assert_eq!(
+        calls,
+        vec![
+            // Without `only:`, Enum may export anything.
+            ("from", 2, vec!["Ecto.Query", "Enum"]),
+            // Enum.map/2 is excepted.
+            ("map", 2, vec![]),
+            ("filter", 2, vec!["Enum"]),
+            ("valid?", 1, vec!["Enum"]),
+        ]
+    );
+    assert_eq!(resolution.calls[2].line(), Some(20));
+    assert!(resolution.calls.iter().all(|call| call.in_function));
+}
+
+#[test]
+fn head_patterns() {
+    // defmodule App.Web do
+    //   def handle(%Conn{} = conn, opts \\ defaults()), do: conn
+    // end
+    let conn = call("%", 2, vec![alias("Conn", 2), call("%{}", 2, vec![])]);
+    let head = call(
+        "handle",
+        2,
+        vec![
+            call("=", 2, vec![conn, var("conn", 2)]),
+            call("\\\\", 2, vec![var("opts", 2), call("defaults", 2, vec![])]),
+        ],
+    );
+    let ast = call(
+        "defmodule",
+        1,
+        vec![
+            alias("App.Web", 1),
+            kw(vec![(
+                "do",
+                call("def", 2, vec![head, kw(vec![("do", var("conn", 2))])]),
+            )]),
+        ],
+    );
+    let resolution = resolve(&ast);
+    let refs: Vec<_> = resolution
+        .modules
+        .iter()
+        .map(|r| (r.module.as_str(), r.usage, r.in_function))
+        .collect();
+    assert_eq!(refs, vec![("Conn", Usage::Struct, true)]);
+    // The default is a call; `handle` and `=` are not.
+    let calls: Vec<_> = resolution.calls.iter().map(|call| call.name).collect();
+    assert_eq!(calls, vec!["defaults"]);
+}
+
+#[test]
+fn directives_are_lexical() {
+    // alias Foo.Bar
+    // if x do
+    //   import Bar
+    //   alias Baz.Bar
+    //   Bar.one(two())
+    // end
+    // Bar.three(two())
+    let ast = block_of(vec![
+        call("alias", 1, vec![alias("Foo.Bar", 1)]),
+        call(
+            "if",
+            2,
+            vec![
+                var("x", 0),
+                kw(vec![(
+                    "do",
+                    block_of(vec![
+                        call("import", 3, vec![alias("Bar", 3)]),
+                        call("alias", 4, vec![alias("Baz.Bar", 4)]),
+                        remote(alias("Bar", 5), "one", 5, vec![call("two", 5, vec![])]),
+                    ]),
+                )]),
+            ],
+        ),
+        remote(alias("Bar", 7), "three", 7, vec![call("two", 7, vec![])]),
+    ]);
+    let resolution = resolve(&ast);
+    let refs: Vec<_> = resolution
+        .modules
+        .iter()
+        .map(|r| (r.line().unwrap(), r.module.as_str()))
+        .collect();
+    assert_eq!(
+        refs,
+        vec![
+            (1, "Foo.Bar"),
+            (3, "Foo.Bar"),
+            (4, "Baz.Bar"),
+            (5, "Baz.Bar"),
+            (7, "Foo.Bar"),
+        ]
+    );
+    let calls: Vec<_> = resolution
+        .calls
+        .iter()
+        .map(|call| (call.name.as_str(), call.imports.len()))
+        .collect();
+    assert_eq!(calls, vec![("if", 0), ("two", 1), ("two", 0)]);
+    assert!(resolution.calls.iter().all(|call| call.within.is_none()));
+}
+
+#[test]
+fn impls() {
+    // defmodule App do
+    //   defimpl Size, for: [Map, __MODULE__] do
+    //     def size(_), do: Helper.count(1)
+    //   end
+    //   defimpl Inspect do end
+    // end
+    let size = call(
+        "def",
+        3,
+        vec![
+            call("size", 3, vec![var("_", 0)]),
+            kw(vec![(
+                "do",
+                remote(alias("Helper", 3), "count", 3, vec![Ast::Int(1.into())]),
+            )]),
+        ],
+    );
+    let ast = call(
+        "defmodule",
+        1,
+        vec![
+            alias("App", 1),
+            kw(vec![(
+                "do",
+                block_of(vec![
+                    call(
+                        "defimpl",
+                        2,
+                        vec![
+                            alias("Size", 2),
+                            kw(vec![
+                                (
+                                    "for",
+                                    Ast::List(vec![alias("Map", 2), var("__MODULE__", 0)]),
+                                ),
+                                ("do", size),
+                            ]),
+                        ],
+                    ),
+                    call(
+                        "defimpl",
+                        5,
+                        vec![alias("Inspect", 5), kw(vec![("do", block_of(vec![]))])],
+                    ),
+                ]),
+            )]),
+        ],
+    );
+    let resolution = resolve(&ast);
+    let refs: Vec<_> = resolution
+        .modules
+        .iter()
+        .map(|r| {
+            let within = r.within.map(|within| within.as_str());
+            (r.line(), r.module.as_str(), r.usage, within)
+        })
+        .collect();
+    use self::Usage::*;
+    assert_eq!(
+        refs,
+        vec![
+            // The body is walked once for each target, as that target's
+            // implementation.
+            (Some(2), "Size", Impl, Some("Size.Map")),
+            (Some(2), "Map", Impl, Some("Size.Map")),
+            (Some(3), "Helper", Call, Some("Size.Map")),
+            (Some(2), "Size", Impl, Some("Size.App")),
+            (None, "App", Impl, Some("Size.App")),
+            (Some(3), "Helper", Call, Some("Size.App")),
+            (Some(5), "Inspect", Impl, Some("Inspect.App")),
+        ]
+    );
+}
@@ -0,0 +1,584 @@
+//! Structural rewrites, for codemods such as turning `Logger.warn($$$ARGS)`
+//! into `Logger.warning($$$ARGS)` across a project.
+//!
+//! A `Rule` pairs a pattern with a template: Elixir code in which the
+//! pattern's metavariables stand for what they matched, with `$$$X`
+//! spliced into arguments and lists. `Rule::rewrite` rewrites an `Ast`.
+//! `Rule::edits` leaves the source to be rewritten as text instead: each
+//! match becomes one `Edit` replacing just that code with the printed
+//! replacement, so comments and formatting elsewhere survive. Matches are
+//! found by converting the nodes of the concrete syntax tree, which keeps
+//! the spans the `Ast` lacks; until the parser builds expression nodes
+//! only literal tokens are reached.
+//!
+//! `Rule::quoted_edits` does the same for calls, taking their spans from
+//! the metadata of `Code.string_to_quoted(source, columns: true,
+//! token_metadata: true)`, decoded with `etf::decode_ast`. Variables,
+//! aliases and calls written with parentheses or a `do` block have spans;
+//! a match without one, such as an operator, is not edited, though matches
+//! inside it are.
+//!
+//! `Edits` collects edits from any number of rules and refuses ones that
+//! overlap, so two rules can never garble the same code.
+
+use ast::{Ast, Meta};
+#[cfg(test)]
+use builders::{alias, at, remote, var};
+use cst::{self, SyntaxElement, SyntaxNode};
+use incremental::Edit;
+use num_traits::ToPrimitive;
+use pattern::{Binding, Bindings, Pattern};
+use printer;
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+use visit::{self, VisitorMut};
+
+pub struct Rule {
+    pattern: Pattern,
+    template: Ast,
+}
+
+impl Rule {
+    pub fn new(pattern: Pattern, template: Ast) -> Self {
+        Rule { pattern, template }
+    }
+
+    /// The template with the metavariables replaced by `bindings`. Ones
+    /// that are not bound are left as they are.
+    pub fn replacement(&self, bindings: &Bindings) -> Ast {
+        substitute(&self.template, bindings)
+    }
+
+    /// Rewrites every match in `ast`, innermost first, and returns how many
+    /// there were. Replacements are not searched again, so a rule whose
+    /// template contains its pattern terminates.
+    pub fn rewrite(&self, ast: &mut Ast) -> usize {
+        let mut rewriter = Rewriter {
+            rule: self,
+            count: 0,
+        };
+        rewriter.visit_mut(ast);
+        rewriter.count
+    }
+
+    /// The edits to `tree`, the concrete syntax tree of the source, that
+    /// rewrite every match, in source order. A match nested in another is
+    /// rewritten within the outer one's edit.
+    pub fn edits(&self, tree: &SyntaxNode) -> Vec<Edit> {
+        let mut edits = Vec::new();
+        // The file itself is left out: replacing it would take its leading
+        // and trailing comments along.
+        for child in tree.children_with_tokens() {
+            self.element_edits(child, &mut edits);
+        }
+        edits
+    }
+
+    fn element_edits(&self, element: SyntaxElement, edits: &mut Vec<Edit>) {
+        if element.kind().is_trivia() || element.kind().is_error() {
+            return;
+        }
+        let ast = match &element {
+            rowan::NodeOrToken::Node(node) => cst::to_ast(node),
+            rowan::NodeOrToken::Token(token) => cst::token_to_ast(token),
+        };
+        match ast {
+            Some(mut ast) if self.pattern.matches(&ast).is_some() => {
+                self.rewrite(&mut ast);
+                let range = element.text_range();
+                edits.push(Edit {
+                    start: range.start().into(),
+                    end: range.end().into(),
+                    text: printer::to_string(&ast),
+                });
+            }
+            _ => {
+                if let rowan::NodeOrToken::Node(node) = element {
+                    for child in node.children_with_tokens() {
+                        self.element_edits(child, edits);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The edits to `source` that rewrite every match in `ast`, its quoted
+    /// form with column and token metadata, in source order. A match
+    /// nested in another is rewritten within the outer one's edit.
+    pub fn quoted_edits(&self, source: &str, ast: &Ast) -> Vec<Edit> {
+        let lines = Lines::new(source);
+        let mut edits = Vec::new();
+        self.quoted_node_edits(&lines, ast, &mut edits);
+        edits
+    }
+
+    fn quoted_node_edits(&self, lines: &Lines, ast: &Ast, edits: &mut Vec<Edit>) {
+        if self.pattern.matches(ast).is_some() {
+            if let Some(span) = lines.span(ast) {
+                let mut ast = ast.clone();
+                self.rewrite(&mut ast);
+                edits.push(Edit {
+                    start: span.start,
+                    end: span.end,
+                    text: printer::to_string(&ast),
+                });
+                return;
+            }
+        }
+        for child in visit::children(ast) {
+            self.quoted_node_edits(lines, child, edits);
+        }
+    }
+}
+
+/// Where each line of a source starts, to turn the one-based lines and
+/// columns of quoted metadata into byte offsets.
+struct Lines<'a> {
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> Lines<'a> {
+    fn new(source: &'a str) -> Self {
+        let starts = ::std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Lines { source, starts }
+    }
+
+    /// The offset of the position `meta` records, `None` if it has none or
+    /// it lies outside the source.
+    fn offset(&self, meta: &Meta) -> Option<usize> {
+        let line = position(meta, "line")?;
+        let column = position(meta, "column")?;
+        let start = *self.starts.get(line.checked_sub(1)?)?;
+        let end = self.starts.get(line).map_or(self.source.len(), |&end| end);
+        let text = &self.source[start..end];
+        text.char_indices()
+            .map(|(i, _)| i)
+            .chain(::std::iter::once(text.len()))
+            .nth(column.checked_sub(1)?)
+            .map(|i| start + i)
+    }
+
+    /// The code `ast` was parsed from.
+    fn span(&self, ast: &Ast) -> Option<Range<usize>> {
+        let span = match ast {
+            Ast::Var(name, meta, _) => {
+                let start = self.offset(meta)?;
+                start..start + name.len()
+            }
+            Ast::Call(callee, meta, args) => {
+                let start = match &**callee {
+                    Ast::Call(dot, _, dot_args) if **dot == Ast::Atom(".".into()) => {
+                        self.span(dot_args.first()?)?.start
+                    }
+                    _ => self.offset(meta)?,
+                };
+                let end = if let Some(closing) = keyword(meta, "closing") {
+                    self.offset(&closing)? + ')'.len_utf8()
+                } else if let Some(end) = keyword(meta, "end") {
+                    self.offset(&end)? + "end".len()
+                } else {
+                    match (&**callee, keyword(meta, "last"), args.last()) {
+                        (Ast::Atom(aliases), Some(last), Some(Ast::Atom(part)))
+                            if *aliases == "__aliases__" =>
+                        {
+                            self.offset(&last)? + part.len()
+                        }
+                        _ => return None,
+                    }
+                };
+                start..end
+            }
+            _ => return None,
+        };
+        // Metadata for some other source must not cut it apart.
+        if span.start < span.end && self.source.get(span.clone()).is_some() {
+            Some(span)
+        } else {
+            None
+        }
+    }
+}
+
+fn position(meta: &Meta, key: &str) -> Option<usize> {
+    match meta.iter().find(|(k, _)| *k == key) {
+        Some((_, Ast::Int(i))) => i.to_usize(),
+        _ => None,
+    }
+}
+
+/// A keyword list in `meta`, such as `closing: [line: 1, column: 19]`, as
+/// metadata of its own.
+fn keyword(meta: &Meta, key: &str) -> Option<Meta> {
+    let list = match meta.iter().find(|(k, _)| *k == key) {
+        Some((_, Ast::List(list))) => list,
+        _ => return None,
+    };
+    list.iter()
+        .map(|pair| match pair {
+            Ast::Pair(k, v) => match **k {
+                Ast::Atom(k) => Some((k, (**v).clone())),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+struct Rewriter<'a> {
+    rule: &'a Rule,
+    count: usize,
+}
+
+impl<'a> VisitorMut for Rewriter<'a> {
+    fn visit_mut(&mut self, ast: &mut Ast) {
+        visit::walk_mut(self, ast);
+        let replacement = self
+            .rule
+            .pattern
+            .matches(ast)
+            .map(|bindings| self.rule.replacement(&bindings));
+        if let Some(replacement) = replacement {
+            *ast = replacement;
+            self.count += 1;
+        }
+    }
+}
+
+fn substitute(template: &Ast, bindings: &Bindings) -> Ast {
+    if let Ast::Var(name, _, _) = template {
+        let bound = name
+            .strip_prefix('$')
+            .and_then(|name| lookup(bindings, name.trim_start_matches("$$")));
+        match bound {
+            Some(Binding::Node(ast)) => return ast.clone(),
+            // A run outside arguments or a list can only stand alone.
+            Some(Binding::Nodes(asts)) if asts.len() == 1 => return asts[0].clone(),
+            _ => {}
+        }
+    }
+    match template {
+        Ast::List(elems) => Ast::List(substitute_all(elems, bindings)),
+        Ast::Pair(a, b) => Ast::Pair(
+            Box::new(substitute(a, bindings)),
+            Box::new(substitute(b, bindings)),
+        ),
+        Ast::Call(callee, meta, args) => Ast::Call(
+            Box::new(substitute(callee, bindings)),
+            meta.clone(),
+            substitute_all(args, bindings),
+        ),
+        ast => ast.clone(),
+    }
+}
+
+fn substitute_all(templates: &[Ast], bindings: &Bindings) -> Vec<Ast> {
+    let mut asts = Vec::with_capacity(templates.len());
+    for template in templates {
+        let run = match template {
+            Ast::Var(name, _, _) => name
+                .strip_prefix("$$$")
+                .and_then(|name| lookup(bindings, name)),
+            _ => None,
+        };
+        match run {
+            Some(Binding::Nodes(run)) => asts.extend_from_slice(run),
+            _ => asts.push(substitute(template, bindings)),
+        }
+    }
+    asts
+}
+
+fn lookup<'ast>(bindings: &Bindings<'ast>, name: &str) -> Option<Binding<'ast>> {
+    bindings
+        .iter()
+        .find(|(bound, _)| *bound == name)
+        .map(|&(_, binding)| binding)
+}
+
+/// Two edits that touch the same code, or insert at the same offset.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Overlap {
+    pub first: Range<usize>,
+    pub second: Range<usize>,
+}
+
+impl fmt::Display for Overlap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "edits to {}..{} and {}..{} overlap",
+            self.first.start, self.first.end, self.second.start, self.second.end
+        )
+    }
+}
+
+impl Error for Overlap {}
+
+/// Edits to one source, kept in order and apart.
+#[derive(Clone, Default, Debug)]
+pub struct Edits {
+    edits: Vec<Edit>,
+}
+
+impl Edits {
+    pub fn new() -> Self {
+        Edits::default()
+    }
+
+    /// Adds `edit`, unless it overlaps one already added. Edits that only
+    /// meet end to start do not overlap.
+    pub fn insert(&mut self, edit: Edit) -> Result<(), Overlap> {
+        let i = self.edits.partition_point(|other| other.start < edit.start);
+        let neighbours = self.edits[i.saturating_sub(1)..]
+            .iter()
+            .take(if i == 0 { 1 } else { 2 });
+        for other in neighbours {
+            let overlaps =
+                (other.start < edit.end && edit.start < other.end) || other.start == edit.start;
+            if overlaps {
+                return Err(Overlap {
+                    first: other.start..other.end,
+                    second: edit.start..edit.end,
+                });
+            }
+        }
+        self.edits.insert(i, edit);
+        Ok(())
+    }
+
+    /// Adds all of `edits`, or none of them if any overlap.
+    pub fn extend<I: IntoIterator<Item = Edit>>(&mut self, edits: I) -> Result<(), Overlap> {
+        let mut all = self.clone();
+        for edit in edits {
+            all.insert(edit)?;
+        }
+        *self = all;
+        Ok(())
+    }
+
+    /// The edits in source order.
+    pub fn iter(&self) -> impl Iterator<Item = &Edit> {
+        self.edits.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.edits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// `source` with the edits made.
+    pub fn apply(&self, source: &str) -> String {
+        let mut out = String::with_capacity(source.len());
+        let mut pos = 0;
+        for edit in &self.edits {
+            out.push_str(&source[pos..edit.start]);
+            out.push_str(&edit.text);
+            pos = edit.end;
+        }
+        out.push_str(&source[pos..]);
+        out
+    }
+}
+
+#[cfg(test)]
+fn logger(fun: &str, args: Vec<Ast>) -> Ast {
+    remote(alias("Logger", 0), fun, 0, args)
+}
+
+#[test]
+fn rewrite_ast() {
+    let rule = Rule::new(
+        Pattern::new(logger("warn", vec![var("$$$ARGS", 0)])),
+        logger("warning", vec![var("$$$ARGS", 0)]),
+    );
+    let message = Ast::String("slow".into());
+    let opts = Ast::List(vec![Ast::Pair(
+        Box::new(Ast::Atom("ms".into())),
+        Box::new(var("ms", 0)),
+    )]);
+    // Logger.warn("slow", Logger.warn("slow"))
+    let mut ast = logger(
+        "warn",
+        vec![message.clone(), logger("warn", vec![message.clone()])],
+    );
+    assert_eq!(rule.rewrite(&mut ast), 2);
+    assert_eq!(
+        printer::to_string(&ast),
+        "Logger.warning(\"slow\", Logger.warning(\"slow\"))"
+    );
+
+    let mut ast = Ast::List(vec![
+        logger("warn", vec![message.clone(), opts.clone()]),
+        logger("info", vec![message.clone()]),
+    ]);
+    assert_eq!(rule.rewrite(&mut ast), 1);
+    assert_eq!(
+        ast,
+        Ast::List(vec![
+            logger("warning", vec![message.clone(), opts]),
+            logger("info", vec![message]),
+        ])
+    );
+
+    // A template may reorder and repeat what it binds.
+    let swap = Rule::new(
+        Pattern::new(Ast::Pair(Box::new(var("$A", 0)), Box::new(var("$B", 0)))),
+        Ast::List(vec![var("$B", 0), var("$A", 0), var("$B", 0)]),
+    );
+    let mut ast = Ast::Pair(Box::new(Ast::Int(1.into())), Box::new(var("x", 0)));
+    assert_eq!(swap.rewrite(&mut ast), 1);
+    assert_eq!(
+        ast,
+        Ast::List(vec![var("x", 0), Ast::Int(1.into()), var("x", 0)])
+    );
+}
+
+#[test]
+fn source_edits() {
+    let source = "# concatenation\n:<> :+ # keep\n:<>\n";
+    let tree = cst::build(source);
+    let rule = Rule::new(Pattern::new(Ast::Atom("<>".into())), Ast::Atom("++".into()));
+    let mut edits = Edits::new();
+    edits.extend(rule.edits(&tree)).unwrap();
+    assert_eq!(edits.len(), 2);
+    assert_eq!(edits.apply(source), "# concatenation\n:++ :+ # keep\n:++\n");
+
+    // A second rule touching the same code is refused whole.
+    let conflicting = Rule::new(Pattern::new(Ast::Atom("+".into())), Ast::Atom("-".into()));
+    let mut edits_plus = conflicting.edits(&tree);
+    edits_plus.push(Edit {
+        start: 21,
+        end: 22,
+        text: "x".into(),
+    });
+    assert_eq!(
+        edits.extend(edits_plus),
+        Err(Overlap {
+            first: 20..22,
+            second: 21..22,
+        })
+    );
+    assert_eq!(edits.len(), 2);
+}
+
+#[cfg(test)]
+fn at_column(line: usize, column: usize) -> Meta {
+    let mut meta = at(line);
+    meta.push(("column".into(), Ast::Int(column.into())));
+    meta
+}
+
+#[cfg(test)]
+fn nested(key: &str, meta: Meta) -> (::symbol::Symbol, Ast) {
+    let list = meta
+        .into_iter()
+        .map(|(k, v)| Ast::Pair(Box::new(Ast::Atom(k)), Box::new(v)))
+        .collect();
+    (key.into(), Ast::List(list))
+}
+
+/// `Logger.fun(args)` as `Code.string_to_quoted/2` quotes it with columns
+/// and token metadata, written at `line` and `column`.
+#[cfg(test)]
+fn quoted_logger(line: usize, column: usize, fun: &str, closing: usize, args: Vec<Ast>) -> Ast {
+    let mut alias_meta = vec![nested("last", at_column(line, column))];
+    alias_meta.extend(at_column(line, column));
+    let alias = Ast::Call(
+        Box::new(Ast::Atom("__aliases__".into())),
+        alias_meta,
+        vec![Ast::Atom("Logger".into())],
+    );
+    let dot = Ast::Call(
+        Box::new(Ast::Atom(".".into())),
+        at_column(line, column + 6),
+        vec![alias, Ast::Atom(fun.into())],
+    );
+    let mut meta = vec![nested("closing", at_column(line, closing))];
+    meta.extend(at_column(line, column + 7));
+    Ast::Call(Box::new(dot), meta, args)
+}
+
+#[test]
+fn quoted_source_edits() {
+    let source = "# keep\nLogger.warn(\"slow\")\nLogger.info(Logger.warn(\"x\", ms: 1)) # ü\n";
+    let ms = Ast::List(vec![Ast::Pair(
+        Box::new(Ast::Atom("ms".into())),
+        Box::new(Ast::Int(1.into())),
+    )]);
+    let ast = Ast::Call(
+        Box::new(Ast::Atom("__block__".into())),
+        vec![],
+        vec![
+            quoted_logger(2, 1, "warn", 19, vec![Ast::String("slow".into())]),
+            quoted_logger(
+                3,
+                1,
+                "info",
+                36,
+                vec![quoted_logger(
+                    3,
+                    13,
+                    "warn",
+                    35,
+                    vec![Ast::String("x".into()), ms],
+                )],
+            ),
+        ],
+    );
+    let rule = Rule::new(
+        Pattern::new(logger("warn", vec![var("$$$ARGS", 0)])),
+        logger("warning", vec![var("$$$ARGS", 0)]),
+    );
+    let mut edits = Edits::new();
+    edits.extend(rule.quoted_edits(source, &ast)).unwrap();
+    assert_eq!(
+        edits
+            .iter()
+            .map(|edit| edit.start..edit.end)
+            .collect::<Vec<_>>(),
+        vec![7..26, 39..62]
+    );
+    assert_eq!(
+        edits.apply(source),
+        "# keep\nLogger.warning(\"slow\")\nLogger.info(Logger.warning(\"x\", ms: 1)) # ü\n"
+    );
+
+    // Metadata that does not fit the source is not trusted.
+    assert!(rule.quoted_edits("Logger.warn(", &ast).is_empty());
+}
+
+#[test]
+fn overlapping_edits() {
+    let edit = |start, end| Edit {
+        start,
+        end,
+        text: "x".into(),
+    };
+    let mut edits = Edits::new();
+    edits.insert(edit(4, 6)).unwrap();
+    edits.insert(edit(0, 2)).unwrap();
+    // Meeting end to start is fine.
+    edits.insert(edit(2, 4)).unwrap();
+    assert_eq!(
+        edits.insert(edit(5, 8)),
+        Err(Overlap {
+            first: 4..6,
+            second: 5..8,
+        })
+    );
+    assert!(edits.insert(edit(1, 1)).is_err());
+    assert!(edits.insert(edit(6, 6)).is_ok());
+    assert!(edits.insert(edit(6, 6)).is_err());
+    assert_eq!(edits.apply("abcdefgh"), "xxxxgh");
+    assert_eq!(
+        edits.iter().map(|edit| edit.start).collect::<Vec<_>>(),
+        vec![0, 2, 4, 6]
+    );
+}
@@ -0,0 +1,227 @@
+//! Interned names: atoms, variables and keys. A `Symbol` is the index of
+//! its name in a table the whole process shares, so every file of a project
+//! analyzed in parallel gets the same handles, and a handle is four bytes.
+//! Interning takes a lock, but reading, comparing and hashing symbols does
+//! not: equal names are the same index, and names are read from buckets
+//! that are never moved once written. Names are never freed; a project
+//! repeats a few thousand of them, not millions. Input that could be
+//! crafted to make millions, such as a decoded term, goes through
+//! `Symbol::try_intern`, which has the limits the BEAM puts on atoms.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{OnceLock, RwLock};
+
+/// The longest name, in characters, `try_intern` accepts.
+pub const MAX_LEN: usize = 255;
+
+/// How many names there may be before `try_intern` refuses new ones.
+pub const MAX_SYMBOLS: usize = 1 << 20;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+/// The index of every name, for interning.
+fn table() -> &'static RwLock<HashMap<&'static str, u32>> {
+    static TABLE: OnceLock<RwLock<HashMap<&'static str, u32>>> = OnceLock::new();
+    TABLE.get_or_init(Default::default)
+}
+
+type Bucket = Box<[OnceLock<&'static str>]>;
+
+/// The name of every index. Bucket `b` holds the `2^b` names from index
+/// `2^b - 1` on, so no bucket has to grow and readers need no lock.
+static NAMES: [OnceLock<Bucket>; 32] = [const { OnceLock::new() }; 32];
+
+/// The bucket of an index and the slot within it.
+fn slot(index: u32) -> (usize, usize) {
+    let n = u64::from(index) + 1;
+    let bucket = 63 - n.leading_zeros() as usize;
+    (bucket, (n - (1 << bucket)) as usize)
+}
+
+impl Symbol {
+    pub fn intern(name: &str) -> Self {
+        Symbol::intern_within(name, usize::MAX).unwrap()
+    }
+
+    /// Interns `name` unless it is longer than `MAX_LEN` characters or new
+    /// when there are already `MAX_SYMBOLS` names.
+    pub fn try_intern(name: &str) -> Option<Self> {
+        if name.chars().count() > MAX_LEN {
+            return None;
+        }
+        Symbol::intern_within(name, MAX_SYMBOLS)
+    }
+
+    fn intern_within(name: &str, limit: usize) -> Option<Self> {
+        if let Some(&index) = table().read().unwrap().get(name) {
+            return Some(Symbol(index));
+        }
+        let mut table = table().write().unwrap();
+        // Another thread may have interned it since the read lock was let go.
+        if let Some(&index) = table.get(name) {
+            return Some(Symbol(index));
+        }
+        if table.len() >= limit {
+            return None;
+        }
+        // The buckets end one short of `u32::MAX` names.
+        assert!(table.len() < u32::MAX as usize, "too many symbols");
+        let index = table.len() as u32;
+        let name: &'static str = Box::leak(name.into());
+        let (bucket, i) = slot(index);
+        NAMES[bucket].get_or_init(|| (0..1usize << bucket).map(|_| OnceLock::new()).collect())[i]
+            .set(name)
+            .unwrap();
+        table.insert(name, index);
+        Some(Symbol(index))
+    }
+
+    pub fn as_str(self) -> &'static str {
+        // A symbol is only handed out once its name is written.
+        let (bucket, i) = slot(self.0);
+        NAMES[bucket].get().unwrap()[i].get().unwrap()
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<'a> From<&'a str> for Symbol {
+    fn from(name: &'a str) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Self {
+        Symbol::intern(&name)
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<'a> PartialEq<&'a str> for Symbol {
+    fn eq(&self, other: &&'a str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+/// Symbols sort by name, so the order does not depend on which name was
+/// interned first.
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self == other {
+            Ordering::Equal
+        } else {
+            self.as_str().cmp(other.as_str())
+        }
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ::serde::Serialize for Symbol {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> ::serde::Deserialize<'de> for Symbol {
+    fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = ::std::borrow::Cow::<str>::deserialize(deserializer)?;
+        Symbol::try_intern(&name)
+            .ok_or_else(|| ::serde::de::Error::custom("name too long, or too many names interned"))
+    }
+}
+
+#[test]
+fn interning() {
+    let foo = Symbol::intern("foo");
+    assert_eq!(Symbol::intern("foo"), foo);
+    assert_eq!(Symbol::from("foo".to_string()), foo);
+    assert_ne!(Symbol::intern("bar"), foo);
+    assert_eq!(foo.as_str(), "foo");
+    assert!(foo == "foo");
+    assert_eq!(foo.len(), 3);
+    assert_eq!(format!("{} {:?}", foo, foo), "foo \"foo\"");
+    assert!(Symbol::intern("bar") < foo);
+}
+
+#[test]
+fn slots() {
+    assert_eq!(slot(0), (0, 0));
+    assert_eq!(slot(1), (1, 0));
+    assert_eq!(slot(2), (1, 1));
+    assert_eq!(slot(3), (2, 0));
+    assert_eq!(slot(u32::MAX - 1), (31, (1 << 31) - 1));
+    assert_eq!(::std::mem::size_of::<Symbol>(), 4);
+}
+
+#[test]
+fn limits() {
+    let long = "a".repeat(MAX_LEN + 1);
+    assert_eq!(Symbol::try_intern(&long), None);
+    assert_eq!(
+        Symbol::try_intern(&long[1..]),
+        Some(Symbol::intern(&long[1..]))
+    );
+    // Characters count, not bytes.
+    let wide = "é".repeat(MAX_LEN);
+    assert!(Symbol::try_intern(&wide).is_some());
+}
+
+#[test]
+fn shared_across_threads() {
+    let names: Vec<String> = (0..100).map(|i| format!("name_{}", i % 10)).collect();
+    let threads: Vec<_> = names
+        .chunks(25)
+        .map(|chunk| {
+            let chunk = chunk.to_vec();
+            ::std::thread::spawn(move || {
+                chunk
+                    .iter()
+                    .map(|name| Symbol::intern(name))
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    let symbols: Vec<Symbol> = threads
+        .into_iter()
+        .flat_map(|thread| thread.join().unwrap())
+        .collect();
+    for (name, symbol) in names.iter().zip(&symbols) {
+        assert_eq!(symbol, &Symbol::intern(name));
+        assert_eq!(symbol.as_str(), name);
+    }
+}
@@ -0,0 +1,1182 @@
+//! Which variables each function clause binds and reads, for unused and
+//! undefined variable warnings without the compiler.
+//!
+//! `clauses` walks every `def`, `defp`, `defmacro` and guard clause of a
+//! file the way the compiler scopes variables. Patterns bind: the head's
+//! parameters, the left of `=`, and the heads of `case`, `receive`, `try`
+//! and `fn` clauses and of `with` and `for` generators. A pattern binds
+//! only once it has matched, so `x = x + 1` reads the old `x`, and a name
+//! repeated within one pattern reads what its first occurrence bound.
+//! Each `->` clause, each `do` and `else` body, and the generators of
+//! `with` and `for` with the body they lead to get a scope of their own,
+//! and nothing bound in one is visible after it. A function's own body is
+//! the exception: it shares the scope of the head. Binding a name again in
+//! the same scope rebinds it; binding it in an inner scope shadows it
+//! there only. `^x` and everything else reads.
+
+use ast::{Ast, Meta};
+#[cfg(test)]
+use builders::{block_of, call, kw, var};
+use num_traits::ToPrimitive;
+use std::collections::HashMap;
+use std::fmt;
+use std::mem;
+use symbol::Symbol;
+
+const DEFS: &[&str] = &[
+    "def",
+    "defp",
+    "defmacro",
+    "defmacrop",
+    "defguard",
+    "defguardp",
+];
+
+/// Keys whose values are bodies or clauses in a scope of their own.
+const BLOCKS: &[&str] = &["do", "else", "after", "rescue", "catch"];
+
+/// A function clause and the variables it uses.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Clause {
+    /// `def`, `defp`, `defmacro` and so on.
+    pub kind: Symbol,
+    pub name: Symbol,
+    /// The arity with every default given.
+    pub arity: usize,
+    pub line: Option<usize>,
+    /// False for a bodiless head, which only declares defaults.
+    pub body: bool,
+    /// Every binding, in the order the clause makes them.
+    pub variables: Vec<Variable>,
+    /// Reads of names not bound where they are read, in source order.
+    pub undefined: Vec<Undefined>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Variable {
+    pub name: Symbol,
+    /// The context of a variable written by a macro, which keeps it apart
+    /// from one of the same name in the caller.
+    pub context: Option<Symbol>,
+    pub line: Option<usize>,
+    /// Bound by the function head.
+    pub parameter: bool,
+    pub previous: Option<Previous>,
+    pub reads: Vec<Read>,
+}
+
+/// The binding of the same name that a binding hides.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Previous {
+    /// An enclosing scope's, visible again once this scope ends. The index
+    /// is into `Clause::variables`.
+    Shadows(usize),
+    /// The same scope's, which is gone for good.
+    Rebinds(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Read {
+    pub line: Option<usize>,
+    /// Read with `^` in a pattern, or outside one.
+    pub pinned: bool,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Undefined {
+    pub name: Symbol,
+    pub context: Option<Symbol>,
+    pub line: Option<usize>,
+}
+
+impl Variable {
+    /// Whether the name says the value is meant to be ignored.
+    pub fn is_underscored(&self) -> bool {
+        self.name.starts_with('_')
+    }
+
+    pub fn is_unused(&self) -> bool {
+        self.reads.is_empty() && !self.is_underscored()
+    }
+}
+
+impl Clause {
+    /// What the compiler would report about the clause's variables, by
+    /// line. A bodiless head's parameters are never unused.
+    pub fn warnings(&self) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+        for variable in &self.variables {
+            if variable.is_unused() && (self.body || !variable.parameter) {
+                warnings.push(Warning {
+                    kind: WarningKind::Unused,
+                    name: variable.name,
+                    line: variable.line,
+                });
+            } else if variable.is_underscored() && !variable.reads.is_empty() {
+                warnings.push(Warning {
+                    kind: WarningKind::UnderscoredUsed,
+                    name: variable.name,
+                    line: variable.reads[0].line,
+                });
+            }
+        }
+        warnings.extend(self.undefined.iter().map(|undefined| Warning {
+            kind: WarningKind::Undefined,
+            name: undefined.name,
+            line: undefined.line,
+        }));
+        warnings.sort_by_key(|warning| warning.line);
+        warnings
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum WarningKind {
+    Unused,
+    UnderscoredUsed,
+    /// An error to the compiler.
+    Undefined,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub name: Symbol,
+    pub line: Option<usize>,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            WarningKind::Unused => write!(
+                f,
+                "variable \"{}\" is unused (if the variable is not meant to be used, \
+                 prefix it with an underscore)",
+                self.name
+            ),
+            WarningKind::UnderscoredUsed => write!(
+                f,
+                "the underscored variable \"{}\" is used after being set",
+                self.name
+            ),
+            WarningKind::Undefined => write!(f, "undefined variable \"{}\"", self.name),
+        }
+    }
+}
+
+/// Every function clause in `ast`, in source order.
+pub fn clauses(ast: &Ast) -> Vec<Clause> {
+    let mut clauses = Vec::new();
+    collect(ast, &mut clauses);
+    clauses
+}
+
+fn collect(ast: &Ast, clauses: &mut Vec<Clause>) {
+    match ast {
+        Ast::Call(callee, meta, args) => {
+            if let (Ast::Atom(kind), [head, rest @ ..]) = (&**callee, &args[..]) {
+                if DEFS.contains(&kind.as_str()) {
+                    if let Some(clause) = clause(*kind, meta, head, rest.first()) {
+                        clauses.push(clause);
+                    }
+                    return;
+                }
+            }
+            for arg in args {
+                collect(arg, clauses);
+            }
+        }
+        Ast::List(elems) => {
+            for elem in elems {
+                collect(elem, clauses);
+            }
+        }
+        Ast::Pair(a, b) => {
+            collect(a, clauses);
+            collect(b, clauses);
+        }
+        _ => {}
+    }
+}
+
+fn clause(kind: Symbol, meta: &Meta, head: &Ast, body: Option<&Ast>) -> Option<Clause> {
+    let (call, guard) = match local_call(head) {
+        Some(("when", [call, guard])) => (call, Some(guard)),
+        _ => (head, None),
+    };
+    // `def unquote(name)(...)` has no name until it is expanded.
+    let (name, params) = match call {
+        Ast::Call(name, _, params) => match **name {
+            Ast::Atom(name) => (name, &params[..]),
+            _ => return None,
+        },
+        Ast::Var(name, _, _) => (*name, &[][..]),
+        _ => return None,
+    };
+    let mut analyzer = Analyzer {
+        parameters: true,
+        ..Analyzer::default()
+    };
+    analyzer.head(params, guard);
+    analyzer.parameters = false;
+    match body {
+        // The body shares the head's scope; the `rescue`, `catch`, `else`
+        // and `after` of an implicit `try` each get one of their own.
+        Some(Ast::List(blocks)) if is_blocks(blocks) => {
+            for block in blocks {
+                match block {
+                    Ast::Pair(key, body) if **key == Ast::Atom("do".into()) => analyzer.expr(body),
+                    Ast::Pair(_, body) => analyzer.body(body, Head::Pattern),
+                    _ => {}
+                }
+            }
+        }
+        Some(body) => analyzer.expr(body),
+        None => {}
+    }
+    Some(Clause {
+        kind,
+        name,
+        arity: params.len(),
+        line: meta_line(meta),
+        body: body.is_some(),
+        variables: analyzer.variables,
+        undefined: analyzer.undefined,
+    })
+}
+
+type Key = (Symbol, Option<Symbol>);
+
+/// Whether a clause head is a pattern, or code as in `cond` and the
+/// `after` of `receive`.
+#[derive(Clone, Copy, PartialEq)]
+enum Head {
+    Pattern,
+    Expr,
+}
+
+struct Analyzer {
+    variables: Vec<Variable>,
+    undefined: Vec<Undefined>,
+    /// The bindings visible in each scope, innermost last.
+    scopes: Vec<HashMap<Key, usize>>,
+    /// The bindings of the pattern being matched, which take effect when
+    /// it has matched.
+    pending: Vec<(Key, usize)>,
+    parameters: bool,
+}
+
+impl Default for Analyzer {
+    fn default() -> Self {
+        Analyzer {
+            variables: Vec::new(),
+            undefined: Vec::new(),
+            scopes: vec![HashMap::new()],
+            pending: Vec::new(),
+            parameters: false,
+        }
+    }
+}
+
+impl Analyzer {
+    fn scoped<F: FnOnce(&mut Self)>(&mut self, f: F) {
+        self.scopes.push(HashMap::new());
+        f(self);
+        self.scopes.pop();
+    }
+
+    fn lookup(&self, key: Key) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&key).cloned())
+    }
+
+    fn read(&mut self, var: &Ast, pinned: bool) {
+        let (name, meta, context) = match var {
+            Ast::Var(name, meta, context) => (*name, meta, *context),
+            _ => return self.expr(var),
+        };
+        if name == "_" || is_special(name) {
+            return;
+        }
+        let key = (name, context);
+        let line = meta_line(meta);
+        // Sizes in a binary pattern can read what the pattern bound
+        // before them, but a pin always reads from outside.
+        let pending = if pinned {
+            None
+        } else {
+            self.pending
+                .iter()
+                .find(|(bound, _)| *bound == key)
+                .map(|&(_, index)| index)
+        };
+        match pending.or_else(|| self.lookup(key)) {
+            Some(index) => self.variables[index].reads.push(Read { line, pinned }),
+            None => self.undefined.push(Undefined {
+                name,
+                context,
+                line,
+            }),
+        }
+    }
+
+    fn bind(&mut self, name: Symbol, meta: &Meta, context: Option<Symbol>) {
+        if name == "_" || is_special(name) {
+            return;
+        }
+        let key = (name, context);
+        let line = meta_line(meta);
+        if let Some(&(_, index)) = self.pending.iter().find(|(bound, _)| *bound == key) {
+            self.variables[index].reads.push(Read {
+                line,
+                pinned: false,
+            });
+            return;
+        }
+        let innermost = self.scopes.last().and_then(|scope| scope.get(&key));
+        let previous = match innermost {
+            Some(&index) => Some(Previous::Rebinds(index)),
+            None => self.lookup(key).map(Previous::Shadows),
+        };
+        self.pending.push((key, self.variables.len()));
+        self.variables.push(Variable {
+            name,
+            context,
+            line,
+            parameter: self.parameters,
+            previous,
+            reads: Vec::new(),
+        });
+    }
+
+    /// Matches `patterns` and then reads `guard`, in the current scope.
+    fn head(&mut self, patterns: &[Ast], guard: Option<&Ast>) {
+        let outer = mem::take(&mut self.pending);
+        for pattern in patterns {
+            self.pattern(pattern);
+        }
+        let bound = mem::replace(&mut self.pending, outer);
+        let scope = self.scopes.last_mut().expect("a scope");
+        scope.extend(bound);
+        if let Some(guard) = guard {
+            self.expr(guard);
+        }
+    }
+
+    /// Like `head`, with the guard written on the last pattern as in `->`
+    /// clauses and generators.
+    fn guarded_head(&mut self, patterns: &[Ast]) {
+        match patterns {
+            [pattern] => match local_call(pattern) {
+                Some(("when", [patterns @ .., guard])) => self.head(patterns, Some(guard)),
+                _ => self.head(patterns, None),
+            },
+            _ => self.head(patterns, None),
+        }
+    }
+
+    fn pattern(&mut self, ast: &Ast) {
+        match ast {
+            Ast::Var(name, meta, context) => self.bind(*name, meta, *context),
+            Ast::List(elems) => {
+                for elem in elems {
+                    self.pattern(elem);
+                }
+            }
+            Ast::Pair(a, b) => {
+                self.pattern(a);
+                self.pattern(b);
+            }
+            Ast::Call(..) => match local_call(ast) {
+                Some(("^", [var])) => self.read(var, true),
+                Some(("@", _)) => {}
+                Some(("::", [value, spec])) => {
+                    self.pattern(value);
+                    self.size(spec);
+                }
+                // A default, and the exceptions of a `rescue` clause.
+                Some(("\\\\", [pattern, code])) | Some(("in", [pattern, code])) => {
+                    self.pattern(pattern);
+                    self.expr(code);
+                }
+                Some(("unquote", args)) | Some(("unquote_splicing", args)) => {
+                    for arg in args {
+                        self.expr(arg);
+                    }
+                }
+                _ => {
+                    if let Ast::Call(_, _, args) = ast {
+                        for arg in args {
+                            self.pattern(arg);
+                        }
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+
+    /// The type after `::` in a binary, where names such as `binary` are
+    /// types and only the arguments of `size(n)` and the like are code.
+    fn size(&mut self, spec: &Ast) {
+        match local_call(spec) {
+            Some(("-", parts)) | Some(("*", parts)) => {
+                for part in parts {
+                    self.size(part);
+                }
+            }
+            Some((_, args)) => {
+                for arg in args {
+                    self.read(arg, false);
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn expr(&mut self, ast: &Ast) {
+        let (callee, args) = match ast {
+            Ast::Var(..) => return self.read(ast, false),
+            Ast::List(elems) if is_blocks(elems) => {
+                return self.blocks(elems, |_| Head::Pattern);
+            }
+            Ast::List(elems) => {
+                for elem in elems {
+                    self.expr(elem);
+                }
+                return;
+            }
+            Ast::Pair(a, b) => {
+                self.expr(a);
+                return self.expr(b);
+            }
+            Ast::Call(callee, _, args) => (&**callee, &args[..]),
+            _ => return,
+        };
+        let name = match callee {
+            Ast::Atom(name) => *name,
+            callee => {
+                self.expr(callee);
+                for arg in args {
+                    self.expr(arg);
+                }
+                return;
+            }
+        };
+        match (name.as_str(), args) {
+            ("=", [pattern, value]) => {
+                self.expr(value);
+                self.head(std::slice::from_ref(pattern), None);
+            }
+            ("^", [var]) => self.read(var, true),
+            ("@", _) => {}
+            ("::", [value, spec]) => {
+                self.expr(value);
+                self.size(spec);
+            }
+            // `&name/arity` names a function rather than reading a variable.
+            ("&", [function]) => match local_call(function) {
+                Some(("/", [Ast::Var(..), _])) => {}
+                _ => self.expr(function),
+            },
+            ("fn", clauses) => {
+                for clause in clauses {
+                    self.clause(clause, Head::Pattern);
+                }
+            }
+            ("cond", [Ast::List(blocks)]) => self.blocks(blocks, |_| Head::Expr),
+            ("receive", [Ast::List(blocks)]) => self.blocks(blocks, |key| {
+                if key == "after" {
+                    Head::Expr
+                } else {
+                    Head::Pattern
+                }
+            }),
+            ("with", args) => self.with(args),
+            ("for", args) => self.comprehension(args),
+            ("quote", args) => {
+                for arg in args {
+                    self.quote(arg);
+                }
+            }
+            (_, args) => {
+                for arg in args {
+                    self.expr(arg);
+                }
+            }
+        }
+    }
+
+    /// The values of `do:`, `else:` and the like, each in a scope of its
+    /// own.
+    fn blocks<F: Fn(&str) -> Head>(&mut self, blocks: &[Ast], head: F) {
+        for block in blocks {
+            if let Ast::Pair(key, body) = block {
+                if let Ast::Atom(key) = **key {
+                    self.body(body, head(&key));
+                }
+            }
+        }
+    }
+
+    /// A body, or `->` clauses.
+    fn body(&mut self, body: &Ast, head: Head) {
+        match body {
+            Ast::List(clauses) if is_clauses(clauses) => {
+                for clause in clauses {
+                    self.clause(clause, head);
+                }
+            }
+            body => self.scoped(|analyzer| analyzer.expr(body)),
+        }
+    }
+
+    fn clause(&mut self, clause: &Ast, head: Head) {
+        let (heads, body) = match local_call(clause) {
+            Some(("->", [Ast::List(heads), body])) => (heads, body),
+            _ => return self.expr(clause),
+        };
+        self.scoped(|analyzer| {
+            match head {
+                Head::Pattern => analyzer.guarded_head(heads),
+                Head::Expr => {
+                    for head in heads {
+                        analyzer.expr(head);
+                    }
+                }
+            }
+            analyzer.expr(body);
+        });
+    }
+
+    /// `pattern <- value` binds for the generators after it and the body;
+    /// anything else is code, and `=` binds the same way.
+    fn generator(&mut self, ast: &Ast) {
+        match local_call(ast) {
+            Some(("<-", [pattern, value])) => {
+                self.expr(value);
+                self.guarded_head(std::slice::from_ref(pattern));
+            }
+            // A generator over the bytes of a binary.
+            Some(("<<>>", [generator]))
+                if local_call(generator).map(|(name, _)| name) == Some("<-") =>
+            {
+                self.generator(generator)
+            }
+            _ => self.expr(ast),
+        }
+    }
+
+    /// The `else` clauses cannot see what the `with` clauses bound.
+    fn with(&mut self, args: &[Ast]) {
+        let (generators, opts) = split_options(args);
+        self.scoped(|analyzer| {
+            for generator in generators {
+                analyzer.generator(generator);
+            }
+            if let Some(body) = option(&opts, "do") {
+                analyzer.body(body, Head::Pattern);
+            }
+        });
+        if let Some(clauses) = option(&opts, "else") {
+            self.body(clauses, Head::Pattern);
+        }
+    }
+
+    /// Options such as `into:` are read outside the generators; with
+    /// `reduce:` the body is clauses matching the accumulator.
+    fn comprehension(&mut self, args: &[Ast]) {
+        let (generators, opts) = split_options(args);
+        for opt in &opts {
+            if let Ast::Pair(key, value) = opt {
+                if **key != Ast::Atom("do".into()) {
+                    self.expr(value);
+                }
+            }
+        }
+        self.scoped(|analyzer| {
+            for generator in generators {
+                analyzer.generator(generator);
+            }
+            for opt in &opts {
+                if let Ast::Pair(key, body) = opt {
+                    if **key == Ast::Atom("do".into()) {
+                        analyzer.body(body, Head::Pattern);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Quoted code is data, apart from what it unquotes and the values of
+    /// `bind_quoted:`.
+    fn quote(&mut self, ast: &Ast) {
+        match ast {
+            Ast::Call(..) => match local_call(ast) {
+                Some(("unquote", args)) | Some(("unquote_splicing", args)) => {
+                    for arg in args {
+                        self.expr(arg);
+                    }
+                }
+                _ => {
+                    if let Ast::Call(callee, _, args) = ast {
+                        self.quote(callee);
+                        for arg in args {
+                            self.quote(arg);
+                        }
+                    }
+                }
+            },
+            Ast::List(elems) => {
+                for elem in elems {
+                    self.quote(elem);
+                }
+            }
+            Ast::Pair(key, value) if **key == Ast::Atom("bind_quoted".into()) => self.expr(value),
+            Ast::Pair(a, b) => {
+                self.quote(a);
+                self.quote(b);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Names such as `__MODULE__` and `__STACKTRACE__`, written like
+/// variables but special forms.
+fn is_special(name: Symbol) -> bool {
+    name.len() > 4 && name.starts_with("__") && name.ends_with("__")
+}
+
+fn is_blocks(elems: &[Ast]) -> bool {
+    !elems.is_empty()
+        && elems.iter().all(|elem| match elem {
+            Ast::Pair(key, _) => match **key {
+                Ast::Atom(key) => BLOCKS.contains(&key.as_str()),
+                _ => false,
+            },
+            _ => false,
+        })
+}
+
+fn is_clauses(elems: &[Ast]) -> bool {
+    !elems.is_empty()
+        && elems
+            .iter()
+            .all(|elem| local_call(elem).map(|(name, _)| name) == Some("->"))
+}
+
+/// The arguments of `with` or `for`, and the pairs of the options ending
+/// them. With a `do` block, options before it are a keyword list of their
+/// own, as in `for x <- xs, into: acc do ... end`, and are merged in.
+fn split_options(args: &[Ast]) -> (&[Ast], Vec<&Ast>) {
+    let (last, rest) = match args.split_last() {
+        Some((Ast::List(last), rest)) if keyword_list(last) && option(last, "do").is_some() => {
+            (last, rest)
+        }
+        _ => return (args, Vec::new()),
+    };
+    match rest.split_last() {
+        Some((Ast::List(inner), rest)) if keyword_list(inner) => {
+            (rest, inner.iter().chain(last).collect())
+        }
+        _ => (rest, last.iter().collect()),
+    }
+}
+
+fn keyword_list(elems: &[Ast]) -> bool {
+    !elems.is_empty()
+        && elems.iter().all(|elem| match elem {
+            Ast::Pair(key, _) => matches!(**key, Ast::Atom(_)),
+            _ => false,
+        })
+}
+
+/// The value of `key` among the pairs of a keyword list.
+fn option<'a, T: ::std::borrow::Borrow<Ast>>(pairs: &'a [T], key: &str) -> Option<&'a Ast> {
+    pairs.iter().find_map(|pair| match pair.borrow() {
+        Ast::Pair(k, value) if **k == Ast::Atom(key.into()) => Some(&**value),
+        _ => None,
+    })
+}
+
+fn local_call(ast: &Ast) -> Option<(&str, &[Ast])> {
+    match ast {
+        Ast::Call(callee, _, args) => match **callee {
+            Ast::Atom(ref name) => Some((name.as_str(), args)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn meta_line(meta: &Meta) -> Option<usize> {
+    match meta.iter().find(|(key, _)| *key == "line") {
+        Some((_, Ast::Int(line))) => line.to_usize(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+fn arrow(heads: Vec<Ast>, line: usize, body: Ast) -> Ast {
+    call("->", line, vec![Ast::List(heads), body])
+}
+
+#[cfg(test)]
+fn def(head: Ast, body: Vec<Ast>) -> Ast {
+    let line = meta_line(match &head {
+        Ast::Call(_, meta, _) => meta,
+        _ => unreachable!(),
+    })
+    .unwrap();
+    call("def", line, vec![head, kw(vec![("do", block_of(body))])])
+}
+
+#[cfg(test)]
+fn names(clause: &Clause) -> Vec<(&str, Option<usize>)> {
+    clause
+        .variables
+        .iter()
+        .map(|variable| (variable.name.as_str(), variable.line))
+        .collect()
+}
+
+#[cfg(test)]
+fn undefined(clause: &Clause) -> Vec<(&str, Option<usize>)> {
+    clause
+        .undefined
+        .iter()
+        .map(|undefined| (undefined.name.as_str(), undefined.line))
+        .collect()
+}
+
+/// def f(x, _y, z) do
+///   a = x
+///   a = a + 1
+///   {b, b} = {_y, a}
+///   c
+/// end
+#[test]
+fn bindings() {
+    let ast = def(
+        call("f", 1, vec![var("x", 1), var("_y", 1), var("z", 1)]),
+        vec![
+            call("=", 2, vec![var("a", 2), var("x", 2)]),
+            call(
+                "=",
+                3,
+                vec![
+                    var("a", 3),
+                    call("+", 3, vec![var("a", 3), Ast::Int(1.into())]),
+                ],
+            ),
+            call(
+                "=",
+                4,
+                vec![
+                    Ast::Pair(Box::new(var("b", 4)), Box::new(var("b", 4))),
+                    Ast::Pair(Box::new(var("_y", 4)), Box::new(var("a", 4))),
+                ],
+            ),
+            var("c", 5),
+        ],
+    );
+    let clauses = clauses(&ast);
+    assert_eq!(clauses.len(), 1);
+    let clause = &clauses[0];
+    assert_eq!((clause.name.as_str(), clause.arity), ("f", 3));
+    assert_eq!(
+        names(clause),
+        vec![
+            ("x", Some(1)),
+            ("_y", Some(1)),
+            ("z", Some(1)),
+            ("a", Some(2)),
+            ("a", Some(3)),
+            ("b", Some(4)),
+        ]
+    );
+    let parameters: Vec<_> = clause.variables.iter().map(|v| v.parameter).collect();
+    assert_eq!(parameters, vec![true, true, true, false, false, false]);
+    // The first `a` is read by the match that rebinds it.
+    assert_eq!(clause.variables[3].reads.len(), 1);
+    assert_eq!(clause.variables[4].previous, Some(Previous::Rebinds(3)));
+    // Matching `b` twice reads what the first one bound.
+    assert_eq!(clause.variables[5].reads.len(), 1);
+
+    let warnings: Vec<_> = clause.warnings().iter().map(|w| w.to_string()).collect();
+    assert_eq!(
+        warnings,
+        vec![
+            "variable \"z\" is unused (if the variable is not meant to be used, \
+             prefix it with an underscore)",
+            "the underscored variable \"_y\" is used after being set",
+            "undefined variable \"c\"",
+        ]
+    );
+}
+
+/// def g(list, n) do
+///   case list do
+///     [h | _] when h > n -> y = h
+///     [] -> n
+///   end
+///   y
+///   f = fn n -> n end
+///   with {:ok, v} <- f.(list), w = v do
+///     w
+///   else
+///     e -> {e, v}
+///   end
+///   for x <- list, x > 0, into: x, do: x
+///   receive do
+///     ^n -> :ok
+///   after
+///     n -> :timeout
+///   end
+///   cond do
+///     list -> y = 1
+///   end
+///   x = 1
+///   if x do
+///     x = 2
+///   end
+///   x
+/// end
+#[test]
+fn scoping() {
+    let ast = def(
+        call("g", 1, vec![var("list", 1), var("n", 1)]),
+        vec![
+            call(
+                "case",
+                2,
+                vec![
+                    var("list", 2),
+                    kw(vec![(
+                        "do",
+                        Ast::List(vec![
+                            arrow(
+                                vec![call(
+                                    "when",
+                                    3,
+                                    vec![
+                                        Ast::List(vec![call(
+                                            "|",
+                                            3,
+                                            vec![var("h", 3), var("_", 3)],
+                                        )]),
+                                        call(">", 3, vec![var("h", 3), var("n", 3)]),
+                                    ],
+                                )],
+                                3,
+                                call("=", 3, vec![var("y", 3), var("h", 3)]),
+                            ),
+                            arrow(vec![Ast::List(vec![])], 4, var("n", 4)),
+                        ]),
+                    )]),
+                ],
+            ),
+            var("y", 6),
+            call(
+                "=",
+                7,
+                vec![
+                    var("f", 7),
+                    call("fn", 7, vec![arrow(vec![var("n", 7)], 7, var("n", 7))]),
+                ],
+            ),
+            call(
+                "with",
+                8,
+                vec![
+                    call(
+                        "<-",
+                        8,
+                        vec![
+                            Ast::Pair(Box::new(Ast::Atom("ok".into())), Box::new(var("v", 8))),
+                            Ast::Call(
+                                Box::new(call(".", 8, vec![var("f", 8)])),
+                                vec![],
+                                vec![var("list", 8)],
+                            ),
+                        ],
+                    ),
+                    call("=", 8, vec![var("w", 8), var("v", 8)]),
+                    kw(vec![
+                        ("do", var("w", 9)),
+                        (
+                            "else",
+                            Ast::List(vec![arrow(
+                                vec![var("e", 11)],
+                                11,
+                                Ast::Pair(Box::new(var("e", 11)), Box::new(var("v", 11))),
+                            )]),
+                        ),
+                    ]),
+                ],
+            ),
+            call(
+                "for",
+                13,
+                vec![
+                    call("<-", 13, vec![var("x", 13), var("list", 13)]),
+                    call(">", 13, vec![var("x", 13), Ast::Int(0.into())]),
+                    kw(vec![("into", var("x", 13)), ("do", var("x", 13))]),
+                ],
+            ),
+            call(
+                "receive",
+                14,
+                vec![kw(vec![
+                    (
+                        "do",
+                        Ast::List(vec![arrow(
+                            vec![call("^", 15, vec![var("n", 15)])],
+                            15,
+                            Ast::Atom("ok".into()),
+                        )]),
+                    ),
+                    (
+                        "after",
+                        Ast::List(vec![arrow(
+                            vec![var("n", 17)],
+                            17,
+                            Ast::Atom("timeout".into()),
+                        )]),
+                    ),
+                ])],
+            ),
+            call(
+                "cond",
+                19,
+                vec![kw(vec![(
+                    "do",
+                    Ast::List(vec![arrow(
+                        vec![var("list", 20)],
+                        20,
+                        call("=", 20, vec![var("y", 20), Ast::Int(1.into())]),
+                    )]),
+                )])],
+            ),
+            call("=", 22, vec![var("x", 22), Ast::Int(1.into())]),
+            call(
+                "if",
+                23,
+                vec![
+                    var("x", 23),
+                    kw(vec![(
+                        "do",
+                        call("=", 24, vec![var("x", 24), Ast::Int(2.into())]),
+                    )]),
+                ],
+            ),
+            var("x", 26),
+        ],
+    );
+    let clause = &clauses(&ast)[0];
+    assert_eq!(
+        names(clause),
+        vec![
+            ("list", Some(1)),
+            ("n", Some(1)),
+            ("h", Some(3)),
+            ("y", Some(3)),
+            ("n", Some(7)),
+            ("f", Some(7)),
+            ("v", Some(8)),
+            ("w", Some(8)),
+            ("e", Some(11)),
+            ("x", Some(13)),
+            ("y", Some(20)),
+            ("x", Some(22)),
+            ("x", Some(24)),
+        ]
+    );
+    // Nothing bound in a clause, `with` or `for` is visible after it, and
+    // `into:` is read outside the generators.
+    assert_eq!(
+        undefined(clause),
+        vec![("y", Some(6)), ("v", Some(11)), ("x", Some(13))]
+    );
+    // The parameter `n` is read by the guard, the second clause, the pin
+    // and `after`, but not inside the `fn` that shadows it.
+    let n = &clause.variables[1];
+    let reads: Vec<_> = n
+        .reads
+        .iter()
+        .map(|read| (read.line, read.pinned))
+        .collect();
+    assert_eq!(
+        reads,
+        vec![
+            (Some(3), false),
+            (Some(4), false),
+            (Some(15), true),
+            (Some(17), false)
+        ]
+    );
+    assert_eq!(clause.variables[4].previous, Some(Previous::Shadows(1)));
+    assert_eq!(clause.variables[12].previous, Some(Previous::Shadows(11)));
+    assert_eq!(clause.variables[11].reads.len(), 2);
+
+    let unused: Vec<_> = clause
+        .warnings()
+        .into_iter()
+        .filter(|warning| warning.kind == WarningKind::Unused)
+        .map(|warning| (warning.name.as_str(), warning.line))
+        .collect();
+    assert_eq!(
+        unused,
+        vec![("y", Some(3)), ("y", Some(20)), ("x", Some(24))]
+    );
+}
+
+/// def f(x) do
+///   x = x + 1
+///   for y <- x, into: y do
+///     y
+///   end
+/// end
+#[test]
+fn body_and_block_options() {
+    let ast = def(
+        call("f", 1, vec![var("x", 1)]),
+        vec![
+            call(
+                "=",
+                2,
+                vec![
+                    var("x", 2),
+                    call("+", 2, vec![var("x", 2), Ast::Int(1.into())]),
+                ],
+            ),
+            call(
+                "for",
+                3,
+                vec![
+                    call("<-", 3, vec![var("y", 3), var("x", 3)]),
+                    kw(vec![("into", var("y", 3))]),
+                    kw(vec![("do", var("y", 4))]),
+                ],
+            ),
+        ],
+    );
+    let clause = &clauses(&ast)[0];
+    assert_eq!(
+        names(clause),
+        vec![("x", Some(1)), ("x", Some(2)), ("y", Some(3))]
+    );
+    // The body is in the head's scope, so the parameter is rebound.
+    assert_eq!(clause.variables[1].previous, Some(Previous::Rebinds(0)));
+    // `into:` before a `do` block is still read outside the generators.
+    assert_eq!(undefined(clause), vec![("y", Some(3))]);
+    assert_eq!(clause.variables[2].reads.len(), 1);
+}
+
+/// defmacro h(<<len, data::binary-size(len)>>, opts \\ [])
+/// defmacro h(<<len, data::binary-size(len)>>, _opts) do
+///   quote bind_quoted: [fun: &to_string/1] do
+///     fun.(unquote(data) <> __MODULE__ <> rest)
+///   end
+/// end
+#[test]
+fn heads_binaries_and_quotes() {
+    let binary = || {
+        call(
+            "<<>>",
+            1,
+            vec![
+                var("len", 1),
+                call(
+                    "::",
+                    1,
+                    vec![
+                        var("data", 1),
+                        call(
+                            "-",
+                            1,
+                            vec![var("binary", 1), call("size", 1, vec![var("len", 1)])],
+                        ),
+                    ],
+                ),
+            ],
+        )
+    };
+    let head = call(
+        "defmacro",
+        1,
+        vec![call(
+            "h",
+            1,
+            vec![
+                binary(),
+                call("\\\\", 1, vec![var("opts", 1), Ast::List(vec![])]),
+            ],
+        )],
+    );
+    let concat = |a, b| call("<>", 4, vec![a, b]);
+    let body = call(
+        "quote",
+        3,
+        vec![
+            kw(vec![(
+                "bind_quoted",
+                kw(vec![(
+                    "fun",
+                    call(
+                        "&",
+                        3,
+                        vec![call("/", 3, vec![var("to_string", 3), Ast::Int(1.into())])],
+                    ),
+                )]),
+            )]),
+            kw(vec![(
+                "do",
+                Ast::Call(
+                    Box::new(call(".", 4, vec![var("fun", 4)])),
+                    vec![],
+                    vec![concat(
+                        concat(
+                            call("unquote", 4, vec![var("data", 4)]),
+                            var("__MODULE__", 4),
+                        ),
+                        var("rest", 4),
+                    )],
+                ),
+            )]),
+        ],
+    );
+    let clause = call(
+        "defmacro",
+        2,
+        vec![
+            call("h", 2, vec![binary(), var("_opts", 2)]),
+            kw(vec![("do", body)]),
+        ],
+    );
+    let clauses = clauses(&Ast::List(vec![head, clause]));
+    assert_eq!(clauses.len(), 2);
+    assert!(!clauses[0].body);
+    assert_eq!(clauses[0].arity, 2);
+    assert_eq!(
+        names(&clauses[0]),
+        vec![("len", Some(1)), ("data", Some(1)), ("opts", Some(1))]
+    );
+    // The size reads `len`; a bodiless head's parameters are not unused.
+    assert_eq!(clauses[0].variables[0].reads.len(), 1);
+    assert_eq!(clauses[0].warnings(), vec![]);
+    // Only what is unquoted is read; `fun` and `rest` belong to the caller.
+    assert_eq!(clauses[1].kind.as_str(), "defmacro");
+    assert!(clauses[1].warnings().is_empty());
+    assert_eq!(clauses[1].variables[1].reads.len(), 1);
+}
@@ -0,0 +1,334 @@
+//! Walking the AST, after `Macro.prewalk/3`, `Macro.postwalk/3` and
+//! `Macro.traverse/4`.
+//!
+//! The walks take the tree by value and thread an accumulator through every
+//! node, returning the rewritten tree with the final accumulator. As in
+//! Elixir, a node's children are the elements of lists and pairs and the
+//! callee and arguments of calls. Metadata and the atom callee of a local
+//! call are not visited.
+//!
+//! `Visitor` and `VisitorMut` are for analyses that keep their state in a
+//! struct instead: override `visit` for the nodes of interest and call
+//! `walk` to carry on into the children.
+
+use ast::Ast;
+#[cfg(test)]
+use builders::{call, var};
+
+/// Applies `fun` to each node before its children, walking the children of
+/// the node `fun` returns.
+pub fn prewalk<A, F>(ast: Ast, acc: A, fun: F) -> (Ast, A)
+where
+    F: FnMut(Ast, A) -> (Ast, A),
+{
+    traverse(ast, acc, fun, |ast, acc| (ast, acc))
+}
+
+/// Applies `fun` to each node after its children.
+pub fn postwalk<A, F>(ast: Ast, acc: A, fun: F) -> (Ast, A)
+where
+    F: FnMut(Ast, A) -> (Ast, A),
+{
+    traverse(ast, acc, |ast, acc| (ast, acc), fun)
+}
+
+/// Applies `pre` to each node before its children and `post` after them.
+pub fn traverse<A, Pre, Post>(ast: Ast, acc: A, mut pre: Pre, mut post: Post) -> (Ast, A)
+where
+    Pre: FnMut(Ast, A) -> (Ast, A),
+    Post: FnMut(Ast, A) -> (Ast, A),
+{
+    traverse_node(ast, acc, &mut pre, &mut post)
+}
+
+fn traverse_node<A, Pre, Post>(ast: Ast, acc: A, pre: &mut Pre, post: &mut Post) -> (Ast, A)
+where
+    Pre: FnMut(Ast, A) -> (Ast, A),
+    Post: FnMut(Ast, A) -> (Ast, A),
+{
+    let (ast, acc) = pre(ast, acc);
+    let (ast, acc) = match ast {
+        Ast::List(elems) => {
+            let (elems, acc) = traverse_all(elems, acc, pre, post);
+            (Ast::List(elems), acc)
+        }
+        Ast::Pair(a, b) => {
+            let (a, acc) = traverse_node(*a, acc, pre, post);
+            let (b, acc) = traverse_node(*b, acc, pre, post);
+            (Ast::Pair(Box::new(a), Box::new(b)), acc)
+        }
+        Ast::Call(callee, meta, args) => {
+            let (callee, acc) = match *callee {
+                callee @ Ast::Atom(_) => (callee, acc),
+                callee => traverse_node(callee, acc, pre, post),
+            };
+            let (args, acc) = traverse_all(args, acc, pre, post);
+            (Ast::Call(Box::new(callee), meta, args), acc)
+        }
+        leaf => (leaf, acc),
+    };
+    post(ast, acc)
+}
+
+fn traverse_all<A, Pre, Post>(
+    asts: Vec<Ast>,
+    mut acc: A,
+    pre: &mut Pre,
+    post: &mut Post,
+) -> (Vec<Ast>, A)
+where
+    Pre: FnMut(Ast, A) -> (Ast, A),
+    Post: FnMut(Ast, A) -> (Ast, A),
+{
+    let mut walked = Vec::with_capacity(asts.len());
+    for ast in asts {
+        let (ast, next) = traverse_node(ast, acc, pre, post);
+        walked.push(ast);
+        acc = next;
+    }
+    (walked, acc)
+}
+
+/// What `prewalk_while` does after visiting a node.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Walk {
+    /// Goes on into the node's children.
+    Continue,
+    /// Leaves out the node's children and goes on with its siblings.
+    Skip,
+    /// Stops the walk.
+    Halt,
+}
+
+/// Applies `fun` to each node before its children, like `prewalk` without
+/// rewriting, until `fun` halts.
+pub fn prewalk_while<A, F>(ast: &Ast, acc: A, mut fun: F) -> A
+where
+    F: FnMut(&Ast, A) -> (Walk, A),
+{
+    prewalk_node(ast, acc, &mut fun).1
+}
+
+/// Returns whether the walk halted along with the accumulator.
+fn prewalk_node<A, F>(ast: &Ast, acc: A, fun: &mut F) -> (bool, A)
+where
+    F: FnMut(&Ast, A) -> (Walk, A),
+{
+    let (walk, mut acc) = fun(ast, acc);
+    match walk {
+        Walk::Continue => {}
+        Walk::Skip => return (false, acc),
+        Walk::Halt => return (true, acc),
+    }
+    for child in children(ast) {
+        let (halted, next) = prewalk_node(child, acc, fun);
+        if halted {
+            return (true, next);
+        }
+        acc = next;
+    }
+    (false, acc)
+}
+
+/// The children of `ast` in the order the walks visit them.
+pub fn children(ast: &Ast) -> Vec<&Ast> {
+    match ast {
+        Ast::List(elems) => elems.iter().collect(),
+        Ast::Pair(a, b) => vec![a, b],
+        Ast::Call(callee, _, args) => {
+            let callee = match **callee {
+                Ast::Atom(_) => None,
+                _ => Some(&**callee),
+            };
+            callee.into_iter().chain(args).collect()
+        }
+        Ast::Atom(_) | Ast::Int(_) | Ast::Float(_) | Ast::String(_) | Ast::Var(..) => vec![],
+    }
+}
+
+/// A walk over a borrowed tree. `visit` is called on every node and by
+/// default walks its children.
+pub trait Visitor<'ast> {
+    fn visit(&mut self, ast: &'ast Ast) {
+        walk(self, ast)
+    }
+}
+
+/// Visits the children of `ast`.
+pub fn walk<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, ast: &'ast Ast) {
+    for child in children(ast) {
+        visitor.visit(child);
+    }
+}
+
+/// A walk that may change the tree in place.
+pub trait VisitorMut {
+    fn visit_mut(&mut self, ast: &mut Ast) {
+        walk_mut(self, ast)
+    }
+}
+
+/// Visits the children of `ast`.
+pub fn walk_mut<V: VisitorMut + ?Sized>(visitor: &mut V, ast: &mut Ast) {
+    match ast {
+        Ast::List(elems) => {
+            for elem in elems {
+                visitor.visit_mut(elem);
+            }
+        }
+        Ast::Pair(a, b) => {
+            visitor.visit_mut(a);
+            visitor.visit_mut(b);
+        }
+        Ast::Call(callee, _, args) => {
+            if !matches!(**callee, Ast::Atom(_)) {
+                visitor.visit_mut(callee);
+            }
+            for arg in args {
+                visitor.visit_mut(arg);
+            }
+        }
+        Ast::Atom(_) | Ast::Int(_) | Ast::Float(_) | Ast::String(_) | Ast::Var(..) => {}
+    }
+}
+
+#[cfg(test)]
+fn sample() -> Ast {
+    // foo(a, [b, {1, c}]) + a
+    call(
+        "+",
+        0,
+        vec![
+            call(
+                "foo",
+                0,
+                vec![
+                    var("a", 1),
+                    Ast::List(vec![
+                        var("b", 1),
+                        Ast::Pair(Box::new(Ast::Int(1.into())), Box::new(var("c", 1))),
+                    ]),
+                ],
+            ),
+            var("a", 1),
+        ],
+    )
+}
+
+#[cfg(test)]
+fn label(ast: &Ast) -> String {
+    match ast {
+        Ast::Atom(name) | Ast::Var(name, _, _) => name.to_string(),
+        Ast::Int(i) => i.to_string(),
+        Ast::List(_) => "[]".to_string(),
+        Ast::Pair(_, _) => "{}".to_string(),
+        Ast::Call(callee, _, _) => format!("{}()", label(callee)),
+        _ => "?".to_string(),
+    }
+}
+
+#[test]
+fn walk_order() {
+    let (ast, pre) = prewalk(sample(), vec![], |ast, mut acc| {
+        acc.push(label(&ast));
+        (ast, acc)
+    });
+    assert_eq!(ast, sample());
+    assert_eq!(
+        pre,
+        vec!["+()", "foo()", "a", "[]", "b", "{}", "1", "c", "a"]
+    );
+
+    let (_, post) = postwalk(sample(), vec![], |ast, mut acc| {
+        acc.push(label(&ast));
+        (ast, acc)
+    });
+    assert_eq!(
+        post,
+        vec!["a", "b", "1", "c", "{}", "[]", "foo()", "a", "+()"]
+    );
+}
+
+#[test]
+fn rewriting() {
+    // Renames `a` and counts the renames; prewalk walks into what it returns.
+    let (ast, renamed) = prewalk(sample(), 0, |ast, count| match ast {
+        Ast::Var(name, meta, context) if name == "a" => {
+            (Ast::Var("x".into(), meta, context), count + 1)
+        }
+        Ast::Int(_) => (Ast::List(vec![var("a", 1)]), count),
+        ast => (ast, count),
+    });
+    assert_eq!(renamed, 3);
+    let (_, names) = postwalk(ast, vec![], |ast, mut acc| {
+        if let Ast::Var(name, _, _) = ast {
+            acc.push(name.to_string());
+        }
+        (ast, acc)
+    });
+    assert_eq!(names, vec!["x", "b", "x", "c", "x"]);
+
+    // Tracks depth on the way down and back up.
+    let (_, (_, deepest)) = traverse(
+        sample(),
+        (0, 0),
+        |ast, (depth, deepest): (usize, usize)| (ast, (depth + 1, deepest.max(depth + 1))),
+        |ast, (depth, deepest)| (ast, (depth - 1, deepest)),
+    );
+    assert_eq!(deepest, 5);
+}
+
+#[test]
+fn walk_while() {
+    let visited = prewalk_while(&sample(), vec![], |ast, mut acc| {
+        acc.push(label(ast));
+        let walk = match ast {
+            Ast::List(_) => Walk::Skip,
+            Ast::Var(name, _, _) if acc.len() > 3 && *name == "a" => Walk::Halt,
+            _ => Walk::Continue,
+        };
+        (walk, acc)
+    });
+    assert_eq!(visited, vec!["+()", "foo()", "a", "[]", "a"]);
+}
+
+#[test]
+fn visitors() {
+    struct Vars<'ast>(Vec<&'ast str>);
+
+    impl<'ast> Visitor<'ast> for Vars<'ast> {
+        fn visit(&mut self, ast: &'ast Ast) {
+            match ast {
+                Ast::Var(name, _, _) => self.0.push(name.as_str()),
+                // Not into lists.
+                Ast::List(_) => {}
+                _ => walk(self, ast),
+            }
+        }
+    }
+
+    let ast = sample();
+    let mut vars = Vars(vec![]);
+    vars.visit(&ast);
+    assert_eq!(vars.0, vec!["a", "a"]);
+
+    struct StripMeta;
+
+    impl VisitorMut for StripMeta {
+        fn visit_mut(&mut self, ast: &mut Ast) {
+            if let Ast::Var(_, meta, _) | Ast::Call(_, meta, _) = ast {
+                meta.clear();
+            }
+            walk_mut(self, ast)
+        }
+    }
+
+    let mut ast = sample();
+    StripMeta.visit_mut(&mut ast);
+    let stripped = prewalk_while(&ast, true, |ast, stripped| match ast {
+        Ast::Var(_, meta, _) => (Walk::Continue, stripped && meta.is_empty()),
+        _ => (Walk::Continue, stripped),
+    });
+    assert!(stripped);
+    assert_ne!(ast, sample());
+}
@@ -0,0 +1,79 @@
+extern crate elixir_parser;
+
+use elixir_parser::diagnostics::line_col;
+use elixir_parser::lexer::{Lexer, Tok};
+use std::fs;
+use std::path::Path;
+
+// Expected output lives next to each input in `elixir/fixtures` and is
+// generated by `elixir/fixtures/regen.exs`. Lines are `LINE:COLUMN KIND VALUE`.
+
+fn format_tok(tok: &Tok) -> String {
+    match tok {
+        Tok::Atom(s) => format!("atom {}", s),
+        Tok::Char(c) => format!("char {}", *c as u32),
+        Tok::Int(i) => format!("int {}", i),
+        Tok::KwIdentifier(s) => format!("kw_identifier {}", s),
+        Tok::Star => "star".to_string(),
+    }
+}
+
+fn lex(source: &str) -> Vec<String> {
+    Lexer::new(source)
+        .map(|spanned| {
+            let (start, text) = match spanned {
+                Ok((start, tok, _)) => (start, format_tok(&tok)),
+                Err(err) => (err.span().0, format!("error {:?}", err)),
+            };
+            let (line, col) = line_col(source, start);
+            format!("{}:{} {}", line, col, text)
+        })
+        .collect()
+}
+
+fn compare(name: &str, expected: &[String], actual: &[String]) -> Option<String> {
+    let mut report = String::new();
+    for i in 0..expected.len().max(actual.len()) {
+        match (expected.get(i), actual.get(i)) {
+            (Some(e), Some(a)) if e == a => {}
+            (Some(e), Some(a)) => {
+                report.push_str(&format!("  token {}: expected `{}`, got `{}`\n", i, e, a))
+            }
+            (Some(e), None) => report.push_str(&format!("  token {}: missing `{}`\n", i, e)),
+            (None, Some(a)) => report.push_str(&format!("  token {}: unexpected `{}`\n", i, a)),
+            (None, None) => unreachable!(),
+        }
+    }
+    if report.is_empty() {
+        None
+    } else {
+        Some(format!("{}:\n{}", name, report))
+    }
+}
+
+#[test]
+fn tokens_match_elixir() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("elixir/fixtures");
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ex"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no fixtures in {}", dir.display());
+
+    let mut failures = Vec::new();
+    for path in &paths {
+        let source = fs::read_to_string(path).unwrap();
+        let expected: Vec<String> = fs::read_to_string(path.with_extension("tokens"))
+            .unwrap_or_else(|_| panic!("{} has no .tokens file", path.display()))
+            .lines()
+            .map(String::from)
+            .collect();
+        let name = path.strip_prefix(&dir).unwrap().display().to_string();
+        if let Some(report) = compare(&name, &expected, &lex(&source)) {
+            failures.push(report);
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.concat());
+}
@@ -0,0 +1,178 @@
+extern crate elixir_parser;
+extern crate num_bigint;
+extern crate proptest;
+
+use elixir_parser::arena::Arena;
+use elixir_parser::ast::Ast;
+use elixir_parser::incremental::{Edit, Tokens};
+use elixir_parser::lexer::{Lexer, Tok, Trivia};
+use num_bigint::BigInt;
+use proptest::prelude::*;
+
+const OPERATOR_ATOMS: &[&str] = &[
+    "...", "<<>>", "%{}", "%", "{}", "~~~", "===", "!==", "&&&", "|||", "<<<", ">>>", "~>>", "<<~",
+    "<~>", "<|>", "^^^", "==", "!=", "=~", "<=", ">=", "&&", "||", "|>", "~>", "<~", "<-", "\\\\",
+    "<>", "..", "++", "--", "->", "::", "@", "!", "^", "&", "+", "-", "*", "/", "<", ">", "=", "|",
+    ".",
+];
+
+const OPERATOR_KEYWORDS: &[&str] = &["...", "<<>>", "%{}", "%", "{}"];
+
+fn atom() -> impl Strategy<Value = Tok> {
+    prop::sample::select(OPERATOR_ATOMS).prop_map(|s| Tok::Atom(s.into()))
+}
+
+fn int() -> impl Strategy<Value = Tok> {
+    prop::collection::vec(any::<u32>(), 1..4)
+        .prop_map(|digits| Tok::Int(BigInt::new(num_bigint::Sign::Plus, digits)))
+}
+
+fn char() -> impl Strategy<Value = Tok> {
+    any::<char>()
+        .prop_filter("escapes are not lexed yet", |c| *c != '\\')
+        .prop_map(Tok::Char)
+}
+
+/// A token stream whose printed form lexes unambiguously: integers swallow a
+/// following digit, so they are always followed by an atom, and a keyword
+/// identifier needs trailing whitespace, so it can only come last, after a
+/// char that cannot run into it.
+fn tokens() -> impl Strategy<Value = Vec<Tok>> {
+    let pair = prop_oneof![
+        (int(), atom()).prop_map(|(i, a)| vec![i, a]),
+        atom().prop_map(|a| vec![a]),
+        char().prop_map(|c| vec![c]),
+    ];
+    let keyword = prop::option::of((
+        char(),
+        prop::sample::select(OPERATOR_KEYWORDS).prop_map(|s| Tok::KwIdentifier(s.into())),
+    ));
+    (prop::collection::vec(pair, 0..16), keyword).prop_map(|(pairs, keyword)| {
+        let mut tokens: Vec<Tok> = pairs.into_iter().flatten().collect();
+        if let Some((c, keyword)) = keyword {
+            tokens.push(c);
+            tokens.push(keyword);
+        }
+        tokens
+    })
+}
+
+fn ast() -> impl Strategy<Value = Ast> {
+    let leaf = prop_oneof![
+        "[a-z_]{1,8}".prop_map(|s| Ast::Atom(s.into())),
+        any::<i64>().prop_map(|i| Ast::Int(i.into())),
+        (-1e9..1e9f64).prop_map(Ast::Float),
+        ".{0,8}".prop_map(Ast::String),
+        "[a-z_]{1,8}".prop_map(|s| Ast::Var(s.into(), vec![], None)),
+    ];
+    leaf.prop_recursive(4, 64, 4, |inner| {
+        let meta = prop::collection::vec(("[a-z]{1,6}", inner.clone()), 0..2)
+            .prop_map(|meta| meta.into_iter().map(|(k, v)| (k.into(), v)).collect());
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Ast::List),
+            (inner.clone(), inner.clone()).prop_map(|(a, b)| Ast::Pair(Box::new(a), Box::new(b))),
+            (inner.clone(), meta, prop::collection::vec(inner, 0..4))
+                .prop_map(|(callee, meta, args)| Ast::Call(Box::new(callee), meta, args)),
+        ]
+    })
+}
+
+fn print(tokens: &[Tok]) -> String {
+    tokens
+        .iter()
+        .map(|tok| match tok {
+            Tok::KwIdentifier(_) => format!("{} ", tok),
+            _ => tok.to_string(),
+        })
+        .collect()
+}
+
+proptest! {
+    #[test]
+    fn print_then_lex(tokens in tokens()) {
+        let source = print(&tokens);
+        let lexed: Vec<Tok> = Lexer::new(&source)
+            .take(tokens.len() + 1)
+            .map(|spanned| spanned.map(|(_, tok, _)| tok))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        prop_assert_eq!(lexed, tokens, "source: {:?}", source);
+    }
+
+    #[test]
+    fn lexer_terminates_on_any_input(source in any::<String>()) {
+        // Every token or error consumes at least one character.
+        let chars = source.chars().count();
+        prop_assert!(Lexer::new(&source).take(chars + 1).count() <= chars);
+    }
+
+    #[test]
+    fn lexer_terminates_on_operator_soup(source in "[-:<>%{}.~=!&|^+*/@?0-9a-fxbo# \\\\\n]{0,64}") {
+        let chars = source.chars().count();
+        prop_assert!(Lexer::new(&source).take(chars + 1).count() <= chars);
+    }
+
+    #[test]
+    fn trivia_covers_input(source in "[-:<>%{}.~=!&|^+*/@?0-9a-fxbo# \t\r\n]{0,64}") {
+        let mut lexer = Lexer::with_trivia(&source);
+        let mut spans = Vec::new();
+        let trivia_spans = |trivia: Vec<Trivia>| trivia.into_iter().map(|t| (t.start, t.end));
+        for item in lexer.by_ref() {
+            match item {
+                Ok(token) => {
+                    spans.extend(trivia_spans(token.leading));
+                    spans.push((token.start, token.end));
+                    spans.extend(trivia_spans(token.trailing));
+                }
+                Err(err) => spans.push(err.span()),
+            }
+        }
+        spans.extend(trivia_spans(lexer.remaining_trivia()));
+        spans.sort();
+
+        let mut offset = 0;
+        for (start, end) in spans {
+            prop_assert_eq!(start, offset, "source: {:?}", source);
+            offset = end;
+        }
+        prop_assert_eq!(offset, lexer.offset());
+    }
+
+    #[test]
+    fn incremental_matches_full_lex(
+        source in "[-:<>%{}.~=!&|^+*/@?0-9a-fxbo# \n]{0,64}",
+        edits in prop::collection::vec(
+            (any::<prop::sample::Index>(), 0..4usize, "[-:<>+*?0-9xa #\n]{0,6}"),
+            1..4,
+        ),
+    ) {
+        let mut tokens = Tokens::new(source);
+        for (at, len, text) in edits {
+            // The alphabet is ASCII, so every offset is a character boundary.
+            let start = at.index(tokens.source().len() + 1);
+            let end = (start + len).min(tokens.source().len());
+            tokens.apply(&Edit { start, end, text });
+
+            let mut lexer = Lexer::new(tokens.source());
+            let items: Vec<_> = lexer.by_ref().collect();
+            prop_assert_eq!(tokens.items(), &items[..], "source: {:?}", tokens.source());
+            let stopped = if lexer.at_end() { None } else { Some(lexer.offset()) };
+            prop_assert_eq!(tokens.stopped(), stopped);
+        }
+    }
+
+    #[test]
+    fn arena_round_trips(asts in prop::collection::vec(ast(), 1..4)) {
+        let mut arena = Arena::new();
+        let roots: Vec<_> = asts.iter().map(|ast| arena.alloc(ast)).collect();
+        for (ast, &root) in asts.iter().zip(&roots) {
+            prop_assert_eq!(&arena.to_ast(root), ast);
+            prop_assert_eq!(arena.parent(root), None);
+        }
+        for (id, node) in arena.iter() {
+            for child in arena.child_ids(node) {
+                prop_assert_eq!(arena.parent(child), Some(id));
+            }
+        }
+    }
+}